@@ -22,6 +22,7 @@ export default class App {
     if (entries && entries[0].isIntersecting && !this.loading && this.cursor) this.loadComments()
   })
   commentCount = 0
+  sort: Sort = 'newest'
   // @ts-ignore strictPropertyInitialization
   observed: boolean
   // @ts-ignore strictPropertyInitialization
@@ -45,7 +46,7 @@ export default class App {
     let userObject = safeParse(user)
 
     if (logged) {
-      userObject = { name: logged.name, avatar: logged.avatar, moderator: true, op: logged.op }
+      userObject = { name: logged.name, avatar: logged.avatar, moderator: true, op: logged.op, role: logged.role }
     }
 
     if (user) user = btoa(user.trim())
@@ -105,6 +106,12 @@ export default class App {
       <form id="besedka-new-comment"></form>
       <div id="besedka-message"></div>
       <h3 id="besedka-heading"></h3>
+      <select id="besedka-sort" aria-label="Sort comments">
+        <option value="newest">Newest</option>
+        <option value="oldest">Oldest</option>
+        <option value="replies">Most replies</option>
+        <option value="top">Top</option>
+      </select>
       <ol class="besedka-comments" id="besedka-comments"></ol>
       <div id="besedka-end-of-comments"></div>
       <div id="besedka-credits">Comments by <a href="https://github.com/muxcmux/besedka" target="_blank">Besedka</a></div>
@@ -112,6 +119,18 @@ export default class App {
     this.comments = document.getElementById('besedka-comments') as HTMLOListElement
     this.endOfComments = document.getElementById('besedka-end-of-comments') as HTMLDivElement
     this.heading = document.getElementById('besedka-heading') as HTMLHeadingElement
+
+    const sort = document.getElementById('besedka-sort') as HTMLSelectElement
+    sort.addEventListener('change', () => this.changeSort(sort.value as Sort))
+  }
+
+  async changeSort(sort: Sort) {
+    if (this.loading) return
+
+    this.sort = sort
+    this.cursor = null
+    this.comments.innerHTML = ''
+    await this.loadComments()
   }
 
   commentUrl(): string {
@@ -132,7 +151,7 @@ export default class App {
     this.loading = true
     try {
       const { status, json } = await request<CommentsResponse>(this.commentUrl(), Object.assign({
-        payload: { token: getToken() }
+        payload: { token: getToken(), sort: this.sort }
       }, this.req))
 
       if (status == 404 || (json && json.total == 0)) {
@@ -155,6 +174,11 @@ export default class App {
   async loadConfig() {
     const { json } = await request<Config>('/api/config', this.req)
     this.config = json
+
+    if (json) {
+      const sort = document.getElementById('besedka-sort') as HTMLSelectElement
+      this.sort = sort.value = json.default_sort
+    }
   }
 
   renderComments({ comments }: { comments: CommentRecord[] }) {
@@ -1,6 +1,6 @@
 import EditCommentForm from "./edit_comment_form"
 import NewCommentForm from "./new_comment_form"
-import { createButton, createElement, getToken, request, timeago } from "./utils"
+import { createButton, createElement, getToken, hasRole, request, setToken, timeago } from "./utils"
 
 const TIME_TO_EDIT = 3 * 60
 
@@ -9,8 +9,11 @@ export default class Comment {
   onApprove: (instance: Comment) => void
   onDelete: (instance: Comment) => void
   comment: CommentRecord
+  depth: number
   replyForm?: NewCommentForm<PostCommentResponse>
   replyButton?: HTMLButtonElement
+  moreRepliesButton?: HTMLButtonElement
+  repliesCursor: string | null = null
 
   replies = createElement('ol', 'replies')
   element = createElement('li', 'comment')
@@ -19,11 +22,11 @@ export default class Comment {
   date    = createElement<HTMLTimeElement>('time', 'comment-timestamp')
   body    = createElement('div', 'comment-body')
 
-  constructor(comment: CommentRecord) {
+  constructor(comment: CommentRecord, depth = 0) {
     this.onApprove = () => {}
     this.onDelete = (instance) => {
       if (instance.isReply()){
-        if (instance.element.parentElement!.querySelectorAll('li').length == 1) {
+        if (instance.element.parentElement!.childElementCount == 1) {
           instance.element.parentElement!.parentElement!.classList.remove('besedka-has-replies');
         }
       } else {
@@ -32,19 +35,35 @@ export default class Comment {
     }
 
     this.comment = comment
+    this.depth = depth
     this.buildComment()
 
-    if (window.__besedka.user.moderator || (this.comment.owned && this.withinEditingPeriod())) {
+    if (this.comment.deleted) {
+      // tombstones only hold replies in place
+    } else if (hasRole('editor') || (this.comment.owned && this.withinEditingPeriod())) {
       this.element.append(this.createEditControls())
       this.element.append(this.createDeleteButton())
     }
 
-    if (window.__besedka.user.moderator && !this.comment.reviewed) {
+    if (hasRole('editor') && !this.comment.deleted) {
+      this.element.append(this.createSpamButton())
+    }
+
+    if (hasRole('approver') && this.comment.reviewed && !this.comment.deleted) {
+      this.element.append(this.createUnapproveButton())
+    }
+
+    if (hasRole('approver') && !this.comment.reviewed && !this.comment.deleted) {
       this.element.append(this.createApproveButton())
+      this.element.append(this.createNotSpamButton())
     }
 
     this.buildReplies()
 
+    if (!this.comment.deleted && this.comment.reviewed) this.element.append(this.createReactionButton())
+    if (!this.comment.deleted && this.comment.reviewed && !this.comment.owned && !hasRole('approver')) {
+      this.element.append(this.createReportButton())
+    }
     if (this.canReply()) this.element.append(this.createReplyButton())
   }
 
@@ -63,24 +82,63 @@ export default class Comment {
 
     if (this.comment.replies?.length) {
       this.comment.replies?.forEach(reply => {
-        const nested = new Comment(reply)
+        const nested = new Comment(reply, this.depth + 1)
         this.replies!.append(nested.element)
       })
     }
+
+    const remaining = this.comment.reply_count - (this.comment.replies?.length ?? 0)
+    if (remaining > 0) this.element.append(this.createMoreRepliesButton(remaining))
+  }
+
+  createMoreRepliesButton(remaining: number): HTMLButtonElement {
+    this.moreRepliesButton = createButton(this.moreRepliesLabel(remaining), 'more-replies')
+    this.moreRepliesButton.addEventListener('click', () => this.loadMoreReplies())
+    return this.moreRepliesButton
+  }
+
+  moreRepliesLabel(remaining: number): string {
+    return `Show ${remaining} more ${remaining == 1 ? 'reply' : 'replies'}`
+  }
+
+  async loadMoreReplies() {
+    const endpoint = this.repliesCursor ? `${this.url()}/replies?cursor=${this.repliesCursor}` : `${this.url()}/replies`
+    const { json } = await request<RepliesResponse>(endpoint, Object.assign({
+      payload: { token: getToken(), sort: window.__besedka.sort }
+    }, window.__besedka.req))
+
+    if (!json) return
+
+    // The first page starts over, including the replies already shown
+    if (!this.repliesCursor) this.replies.innerHTML = ''
+
+    json.replies.forEach(reply => {
+      this.replies.append(new Comment(reply, this.depth + 1).element)
+    })
+    this.element.classList.toggle('besedka-has-replies', this.replies.childElementCount > 0)
+
+    this.repliesCursor = json.cursor
+    if (json.cursor) {
+      this.moreRepliesButton!.textContent = this.moreRepliesLabel(json.total - this.replies.childElementCount)
+    } else {
+      this.moreRepliesButton!.remove()
+    }
   }
 
   canReply() {
-    return !this.comment.parent_id && !window.__besedka.config?.locked
+    return !this.comment.deleted && this.depth < (window.__besedka.config?.max_depth ?? 1) && !window.__besedka.config?.locked
   }
 
   buildComment() {
-    const { created_at, html_body, name, reviewed, owned, edited, op, moderator, replies } = this.comment
+    const { created_at, html_body, name, reviewed, hidden, owned, edited, op, moderator, deleted, replies } = this.comment
 
     if (!reviewed) this.element.classList.add('besedka-unreviewed-comment')
+    if (hidden) this.element.classList.add('besedka-hidden-comment')
     if (owned) this.element.classList.add('besedka-owned-comment')
     if (edited) this.element.classList.add('besedka-edited-comment')
     if (moderator) this.element.classList.add('besedka-moderator-comment')
     if (op) this.element.classList.add('besedka-op-comment')
+    if (deleted) this.element.classList.add('besedka-deleted-comment')
     if (replies?.length) this.element.classList.add('besedka-has-replies')
 
     this.author.textContent = name
@@ -103,6 +161,50 @@ export default class Comment {
     return this.replyButton
   }
 
+  createReactionButton(): HTMLButtonElement {
+    const button = createButton('', 'react-to-comment', { title: '+1' })
+    const update = () => {
+      button.textContent = this.comment.reactions > 0 ? `+1 · ${this.comment.reactions}` : '+1'
+      button.classList.toggle('besedka-reacted', this.comment.reacted)
+    }
+    update()
+
+    button.addEventListener('click', async () => {
+      if (window.__besedka.config?.locked) return
+
+      const { json } = await request<ReactionResponse>(`${this.url()}/reactions`, Object.assign({
+        payload: { token: getToken() }
+      }, window.__besedka.req), this.comment.reacted ? 'DELETE' : 'POST')
+
+      if (json) {
+        if (json.token) setToken(json.token)
+        this.comment.reactions = json.reactions
+        this.comment.reacted = json.reacted
+        update()
+      }
+    })
+    return button
+  }
+
+  createReportButton(): HTMLButtonElement {
+    const button = createButton('Report', 'report-comment', { title: 'Report to moderators' })
+    button.addEventListener('click', async () => {
+      const reason = prompt("What's wrong with this comment?")?.trim()
+      if (!reason) return
+
+      const { json } = await request<ReportResponse>(`${this.url()}/report`, Object.assign({
+        payload: { token: getToken(), reason }
+      }, window.__besedka.req), 'POST')
+
+      if (json) {
+        if (json.token) setToken(json.token)
+        button.textContent = 'Reported'
+        button.disabled = true
+      }
+    })
+    return button
+  }
+
   createApproveButton(): HTMLButtonElement {
     const button = createButton('Approve', 'approve-comment', { title: 'Approve' })
     button.addEventListener('click', async () => {
@@ -116,6 +218,43 @@ export default class Comment {
     return button
   }
 
+  createUnapproveButton(): HTMLButtonElement {
+    const button = createButton('Hide', 'unapprove-comment', { title: 'Hide until approved again' })
+    button.addEventListener('click', async () => {
+      const { status } = await request(`${this.url()}/unapprove`, window.__besedka.req, 'POST')
+      if (status == 200) {
+        this.element.classList.add('besedka-unreviewed-comment', 'besedka-hidden-comment')
+        button.remove()
+      }
+    })
+    return button
+  }
+
+  createNotSpamButton(): HTMLButtonElement {
+    const button = createButton('Not spam', 'not-spam-comment', { title: 'Approve and report as not spam' })
+    button.addEventListener('click', async () => {
+      const { status } = await request(`${this.url()}/ham`, window.__besedka.req, 'POST')
+      if (status == 200) {
+        this.element.classList.remove('besedka-unreviewed-comment')
+        button.remove()
+        this.element.querySelector('.besedka-approve-comment')?.remove()
+        this.onApprove(this)
+      }
+    })
+    return button
+  }
+
+  createSpamButton(): HTMLButtonElement {
+    const button = createButton('Spam', 'spam-comment', { title: 'Delete and report as spam' })
+    button.addEventListener('click', async () => {
+      if (confirm("Delete this comment as spam?")) {
+        const { status } = await request(`${this.url()}/spam`, window.__besedka.req, 'POST')
+        if (status == 200) this.destroy()
+      }
+    })
+    return button
+  }
+
   url(): string {
     return `/api/comment/${this.comment.id}`
   }
@@ -131,7 +270,7 @@ export default class Comment {
       }
     })
 
-    if (!window.__besedka.user.moderator) this.expireControl(button)
+    if (!hasRole('editor')) this.expireControl(button)
     return button
   }
 
@@ -153,7 +292,7 @@ export default class Comment {
       this.element.insertBefore(form, this.body)
     })
 
-    if (!window.__besedka.user.moderator) this.expireControl(button)
+    if (!hasRole('editor')) this.expireControl(button)
     return button
   }
 
@@ -167,7 +306,7 @@ export default class Comment {
     const reply = createElement<HTMLFormElement>('form', 'new-reply')
 
     this.replyForm = new NewCommentForm<PostCommentResponse>(reply, ({ comment }) => {
-      this.replies.append(new Comment(comment).element)
+      this.replies.append(new Comment(comment, this.depth + 1).element)
       this.element.classList.add('besedka-has-replies')
       this.closeReplyForm()
       this.element.classList.remove('besedka-replying')
@@ -1,4 +1,4 @@
-import { createButton, createElement, message, request, safeParse } from "./utils"
+import { createButton, createElement, hasRole, message, request, safeParse } from "./utils"
 import UnreviewedComments from "./unreviewed_comments"
 
 export default class ModeratorControls {
@@ -11,17 +11,15 @@ export default class ModeratorControls {
 
   initUi() {
     this.element.innerHTML = ''
-    if (this.moderator()) {
-      this.loadUnreviewedComments()
-      this.buildLock()
-    }
+    if (this.moderator()) this.loadUnreviewedComments()
+    if (hasRole('admin')) this.buildLock()
 
     if (this.loggedModerator()) this.buildLogout()
     if (!this.loggedModerator() && !this.signedUser()) this.buildLogin()
   }
 
   moderator(): boolean {
-    return window.__besedka.user.moderator === true
+    return hasRole('approver')
   }
 
   signedUser(): boolean {
@@ -35,7 +33,8 @@ export default class ModeratorControls {
   buildLogout() {
     const logout = createButton('Logout', 'logout', { title: 'Logout' })
     this.element.append(logout)
-    logout.addEventListener('click', () => {
+    logout.addEventListener('click', async () => {
+      await request('/api/logout', { sid: window.__besedka.req.sid })
       window.localStorage.removeItem('__besedka_mod')
       window.__besedka.user = safeParse(document.getElementById('besedka-user')?.innerText)
       window.__besedka.req.sid = undefined
@@ -58,9 +57,12 @@ export default class ModeratorControls {
     const form = createElement<HTMLFormElement>('form', 'login-form')
     const name = createElement<HTMLInputElement>('input', 'login-name', { placeholder: 'User' })
     const pass = createElement<HTMLInputElement>('input', 'login-password', { placeholder: 'Pass', type: 'password' })
+    // Only shown to moderators with two-factor authentication enabled
+    const code = createElement<HTMLInputElement>('input', 'login-code', { placeholder: 'Code', autocomplete: 'one-time-code' })
+    code.hidden = true
     const login = createButton('Login', '', { title: 'Login' })
 
-    form.append(name, pass, login)
+    form.append(name, pass, code, login)
     modal.append(form)
 
     loginButton.addEventListener('click', () => {
@@ -72,16 +74,24 @@ export default class ModeratorControls {
       if (name.value && pass.value) {
         try {
           login.disabled = true
-          const { status, json } = await request<LoginResponse>(
+          const { status, text, json } = await request<LoginResponse>(
             '/api/login',
-            { name: name.value, password: pass.value },
+            { name: name.value, password: pass.value, site: window.__besedka.req.site, code: code.value },
             'POST',
             msg
           )
-          if (status === 401) {
+          if (status === 401 && text === "Two-factor authentication code required") {
+            code.hidden = false
+            code.focus()
+            message("Enter the code from your authenticator app or a recovery code", "error", msg)
+          } else if (status === 401) {
             message("Invalid credentials", "error", msg)
+          } else if (status === 403) {
+            message("You are not a moderator of this site", "error", msg)
+          } else if (status === 429) {
+            message("Too many failed login attempts, please try again later", "error", msg)
           } else if(json) {
-            const user = { name: json.name, sid: json.sid, avatar: json.avatar, moderator: true, op: json.op }
+            const user = { name: json.name, sid: json.sid, avatar: json.avatar, moderator: true, op: json.op, role: json.role }
             window.localStorage.setItem('__besedka_mod', JSON.stringify(user))
             window.__besedka.user = user
             window.__besedka.req.sid = json.sid
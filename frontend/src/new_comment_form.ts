@@ -11,7 +11,10 @@ export default class NewCommentForm<R> {
   previewAuthor = createElement<HTMLDivElement>('div', 'author-preview')
   previewTimestamp = createElement<HTMLTimeElement>('time', 'timestamp-preview')
   avatar = createElement<HTMLDivElement>('div', 'avatar no-avatar')
+  website = createElement<HTMLInputElement>('input', 'comment-website-input', { name: 'website', tabindex: '-1', autocomplete: 'off', 'aria-hidden': 'true' })
+  startedAt?: number
   name?: HTMLInputElement
+  email?: HTMLInputElement
   parentId?: number
   callback: Function
 
@@ -49,8 +52,20 @@ export default class NewCommentForm<R> {
       this.element.append(this.name)
     }
 
+    this.element.append(this.body)
+
+    if (window.__besedka.config?.reply_notifications) {
+      this.email = createElement<HTMLInputElement>('input', 'comment-email-input', {
+        type: 'email',
+        placeholder: 'Email me about replies (optional)',
+        autocomplete: 'email'
+      })
+      this.email.value = window.localStorage.getItem('__besedka_email') || ''
+      this.element.append(this.email)
+    }
+
     this.element.append(
-      this.body, this.previewBody, this.previewAuthor,
+      this.website, this.previewBody, this.previewAuthor,
       this.previewTimestamp, this.button, this.editButton
     )
   }
@@ -70,7 +85,10 @@ export default class NewCommentForm<R> {
     })
     this.body.addEventListener('change', debounce(() => this.save()))
     this.body.addEventListener('keyup', debounce(() => this.save()))
-    this.body.addEventListener('focus', () => this.element.classList.add('besedka-focused'))
+    this.body.addEventListener('focus', () => {
+      if (!this.startedAt) this.startedAt = Date.now()
+      this.element.classList.add('besedka-focused')
+    })
     this.name?.addEventListener('focus', () => this.element.classList.add('besedka-focused'))
     this.body.addEventListener('blur', () => this.element.classList.remove('besedka-focused'))
     this.name?.addEventListener('blur', () => this.element.classList.remove('besedka-focused'))
@@ -89,6 +107,7 @@ export default class NewCommentForm<R> {
 
   reset() {
     this.body.value = ''
+    this.startedAt = undefined
     window.localStorage.removeItem(this.storageKey())
   }
 
@@ -125,12 +144,16 @@ export default class NewCommentForm<R> {
     const body = this.body.value
     const name = this.name?.value
     const token = getToken()
+    const email = this.email?.value.trim() || undefined
+    const website = this.website.value
+    const time_to_submit = Math.round((Date.now() - (this.startedAt ?? Date.now())) / 1000)
 
     try {
       const { json } = await request<R>(this.url(), Object.assign({
-        payload: { body, name, token }
+        payload: { body, name, token, email, website, time_to_submit }
       }, window.__besedka.req), this.method())
       if (json) {
+        if (email) window.localStorage.setItem('__besedka_email', email)
         this.callback(json)
         this.reset()
       }
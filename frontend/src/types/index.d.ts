@@ -15,12 +15,17 @@ declare global {
     body: string
     avatar?: string
     reviewed: boolean
+    hidden?: boolean
     created_at: Date
     updated_at: Date
     owned: boolean
     op: boolean
     moderator: boolean
     edited: boolean
+    deleted?: boolean
+    reactions: number
+    reacted: boolean
+    reply_count: number
     replies?: CommentRecord[]
     page_path?: string
     page_title?: string
@@ -32,6 +37,12 @@ declare global {
     comments: CommentRecord[]
   }
 
+  interface RepliesResponse {
+    total: number
+    cursor: string | null
+    replies: CommentRecord[]
+  }
+
   interface ApiRequest {
     site: string
     path: string
@@ -45,13 +56,19 @@ declare global {
     anonymous: boolean
     moderated: boolean
     locked: boolean
+    max_depth: number
+    reply_notifications: boolean
+    default_sort: Sort
   }
 
+  type Sort = 'newest' | 'oldest' | 'replies' | 'top'
+
   interface CreateCommentRequest extends ApiRequest {
     payload?: {
       body: string
       name?: string
       token?: string
+      email?: string
     }
   }
 
@@ -60,14 +77,32 @@ declare global {
     token: string
   }
 
+  interface ReactionResponse {
+    token?: string
+    reactions: number
+    reacted: boolean
+  }
+
+  interface ReportResponse {
+    token?: string
+  }
+
+  interface BulkModerationResult {
+    id: number
+    outcome: 'done' | 'unchanged' | 'not_found'
+  }
+
   interface UpdateCommentResponse {
     body: string
     html_body: string
   }
 
+  type Role = 'approver' | 'editor' | 'admin'
+
   interface User {
     name?: string
     moderator?: boolean
+    role?: Role
     avatar?: string
     op?: boolean
   }
@@ -77,5 +112,6 @@ declare global {
     sid: string
     avatar?: string
     op?: boolean
+    role: Role
   }
 }
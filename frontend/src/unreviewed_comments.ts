@@ -1,5 +1,5 @@
 import Comment from "./comment"
-import { createElement } from "./utils"
+import { createButton, createElement, request } from "./utils"
 
 export default class UnreviewedComments {
   element: HTMLDialogElement
@@ -15,13 +15,30 @@ export default class UnreviewedComments {
   open() { this.element.showModal() }
 
   initUi() {
+    this.element.append(this.createApproveAllButton())
     this.renderComments()
   }
 
+  createApproveAllButton(): HTMLButtonElement {
+    const button = createButton('Approve all', 'approve-all-comments', { title: 'Approve all unreviewed comments' })
+    button.addEventListener('click', async () => {
+      const { status } = await request<BulkModerationResult[]>('/api/comments/moderate', Object.assign({
+        payload: { ids: this.comments.map(c => c.id), action: 'approve' }
+      }, window.__besedka.req), 'POST')
+
+      if (status == 200) {
+        this.element.close()
+        document.querySelector('.besedka-view-unreviewed-comments')?.remove()
+        window.__besedka.run()
+      }
+    })
+    return button
+  }
+
   groupedComments(): {[key: string]: Comment[]} {
     return this.comments.reduce((groups: {[key: string]: Comment[]}, comment: CommentRecord) => {
       const group: Comment[] = groups[comment.page_path || '#'] || []
-      const commentComponent = new Comment(comment)
+      const commentComponent = new Comment(comment, comment.parent_id ? 1 : 0)
       commentComponent.onApprove = (instance) => instance.destroy()
       commentComponent.onDelete = (instance) => {
         const parent = instance.element.parentElement
@@ -29,6 +29,15 @@ export function getToken(): string | null {
   return window.localStorage.getItem('__besedka_token')
 }
 
+const ROLES: Role[] = ['approver', 'editor', 'admin']
+
+// Moderators without a role are admins, as on the server
+export function hasRole(role: Role): boolean {
+  const { moderator, role: userRole } = window.__besedka.user
+  if (!moderator && !userRole) return false
+  return ROLES.indexOf(userRole ?? 'admin') >= ROLES.indexOf(role)
+}
+
 export function createElement<T extends HTMLElement>(el: string, className?: string, attributes?: {}): T {
   const element = document.createElement(el) as T
   if (className) element.className = className.split(' ').map(c => `besedka-${c}`).join(' ')
@@ -0,0 +1 @@
+ALTER TABLE sites ADD COLUMN max_depth INTEGER NOT NULL DEFAULT 1;
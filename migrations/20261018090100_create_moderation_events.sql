@@ -0,0 +1,15 @@
+CREATE TABLE moderation_events (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  site          VARCHAR NOT NULL,
+  actor         VARCHAR NOT NULL,
+  moderator     BOOLEAN NOT NULL DEFAULT 0,
+  action        VARCHAR NOT NULL,
+  comment_id    INTEGER,
+  page_id       INTEGER,
+  body_before   TEXT,
+  body_after    TEXT,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_moderation_events_site_created_at ON moderation_events(site, created_at);
+CREATE INDEX idx_moderation_events_actor           ON moderation_events(actor);
@@ -0,0 +1,12 @@
+CREATE TABLE comment_revisions (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  comment_id    INTEGER NOT NULL REFERENCES comments(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  body          TEXT NOT NULL,
+  html_body     TEXT NOT NULL,
+  reviewed      BOOLEAN NOT NULL DEFAULT 0,
+  created_at    DATETIME NOT NULL
+);
+
+CREATE INDEX idx_comment_revisions_comment_id ON comment_revisions(comment_id);
+
+ALTER TABLE sites ADD COLUMN public_revisions BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE sites ADD COLUMN rate_limit INTEGER NOT NULL DEFAULT 10;
+ALTER TABLE sites ADD COLUMN rate_limit_window INTEGER NOT NULL DEFAULT 60;
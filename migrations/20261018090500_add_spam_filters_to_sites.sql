@@ -0,0 +1,5 @@
+ALTER TABLE sites ADD COLUMN max_links INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sites ADD COLUMN banned_words TEXT NOT NULL DEFAULT '';
+ALTER TABLE sites ADD COLUMN honeypot BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE sites ADD COLUMN min_submit_time INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sites ADD COLUMN spam_action TEXT NOT NULL DEFAULT 'reject';
@@ -0,0 +1,9 @@
+ALTER TABLE sites ADD COLUMN akismet_key TEXT NOT NULL DEFAULT '';
+ALTER TABLE sites ADD COLUMN akismet_url TEXT NOT NULL DEFAULT 'https://rest.akismet.com';
+
+CREATE TABLE comment_metadata (
+  comment_id    INTEGER NOT NULL PRIMARY KEY REFERENCES comments(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  ip            TEXT NOT NULL,
+  user_agent    TEXT,
+  referrer      TEXT
+);
@@ -0,0 +1,31 @@
+ALTER TABLE sites ADD COLUMN smtp_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE sites ADD COLUMN email_from TEXT NOT NULL DEFAULT '';
+ALTER TABLE sites ADD COLUMN moderator_emails TEXT NOT NULL DEFAULT '';
+ALTER TABLE sites ADD COLUMN reply_notifications BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE emails (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  site          VARCHAR NOT NULL,
+  recipient     VARCHAR NOT NULL,
+  subject       TEXT NOT NULL,
+  body          TEXT NOT NULL,
+  attempts      INTEGER NOT NULL DEFAULT 0,
+  last_error    TEXT,
+  send_after    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  sent_at       DATETIME,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_emails_sent_at_send_after ON emails(sent_at, send_after);
+
+CREATE TABLE comment_subscriptions (
+  comment_id    INTEGER NOT NULL PRIMARY KEY REFERENCES comments(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  email         VARCHAR NOT NULL
+);
+
+CREATE TABLE email_unsubscribes (
+  site          VARCHAR NOT NULL,
+  email         VARCHAR NOT NULL,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  PRIMARY KEY (site, email)
+);
@@ -0,0 +1,26 @@
+CREATE TABLE webhooks (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  site          VARCHAR NOT NULL,
+  url           TEXT NOT NULL,
+  -- comma separated, empty for all events
+  events        TEXT NOT NULL DEFAULT '',
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_webhooks_site ON webhooks(site);
+
+CREATE TABLE webhook_deliveries (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  webhook_id    INTEGER NOT NULL REFERENCES webhooks(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  event         VARCHAR NOT NULL,
+  payload       TEXT NOT NULL,
+  attempts      INTEGER NOT NULL DEFAULT 0,
+  last_status   INTEGER,
+  last_error    TEXT,
+  send_after    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  delivered_at  DATETIME,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_webhook_deliveries_delivered_at_send_after ON webhook_deliveries(delivered_at, send_after);
+CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id);
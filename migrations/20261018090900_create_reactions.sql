@@ -0,0 +1,7 @@
+CREATE TABLE reactions (
+  comment_id    INTEGER NOT NULL REFERENCES comments(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  -- "user:<name>" for signed users, "token:<token>" for everyone else
+  reactor       VARCHAR NOT NULL,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  PRIMARY KEY (comment_id, reactor)
+);
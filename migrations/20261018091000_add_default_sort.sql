@@ -0,0 +1 @@
+ALTER TABLE sites ADD COLUMN default_sort TEXT NOT NULL DEFAULT 'newest';
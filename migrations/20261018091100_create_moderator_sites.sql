@@ -0,0 +1,7 @@
+ALTER TABLE moderators ADD COLUMN all_sites BOOLEAN NOT NULL DEFAULT 1;
+
+CREATE TABLE moderator_sites (
+  moderator      VARCHAR NOT NULL REFERENCES moderators(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  site           VARCHAR NOT NULL REFERENCES sites(site) ON UPDATE CASCADE ON DELETE CASCADE,
+  PRIMARY KEY (moderator, site)
+);
@@ -0,0 +1 @@
+ALTER TABLE moderators ADD COLUMN role TEXT NOT NULL DEFAULT 'admin';
@@ -0,0 +1,18 @@
+CREATE TABLE sessions (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  sid           BLOB NOT NULL UNIQUE,
+  moderator     VARCHAR NOT NULL REFERENCES moderators(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  user_agent    TEXT,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  last_seen_at  DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  expires_at    DATETIME NOT NULL
+);
+
+CREATE INDEX idx_sessions_moderator ON sessions(moderator);
+
+-- Moderators who are logged in stay logged in for a while, after
+-- which sessions expire unless they are used
+INSERT INTO sessions (sid, moderator, expires_at)
+SELECT sid, name, strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '+14 days') FROM moderators WHERE sid IS NOT NULL;
+
+UPDATE moderators SET sid = NULL;
@@ -0,0 +1,11 @@
+-- Failed logins by moderator name and by IP address
+CREATE TABLE login_failures (
+  kind            VARCHAR NOT NULL,
+  value           VARCHAR NOT NULL,
+  failures        INTEGER NOT NULL DEFAULT 0,
+  last_failed_at  DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  locked_until    DATETIME,
+  PRIMARY KEY (kind, value)
+);
+
+CREATE INDEX idx_login_failures_last_failed_at ON login_failures(last_failed_at);
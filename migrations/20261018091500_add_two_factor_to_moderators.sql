@@ -0,0 +1,15 @@
+-- Shared secret of the moderator's authenticator app, NULL when 2FA is off
+ALTER TABLE moderators ADD COLUMN totp_secret BLOB;
+-- Last time step a code was accepted for, so codes can't be replayed
+ALTER TABLE moderators ADD COLUMN totp_last_step INTEGER;
+
+-- One-off codes to log in with when the authenticator app is lost. Codes
+-- start with a lookup part, the rest is hashed with Argon2 like passwords
+CREATE TABLE recovery_codes (
+  id          INTEGER NOT NULL PRIMARY KEY,
+  moderator   VARCHAR NOT NULL REFERENCES moderators(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  lookup      VARCHAR NOT NULL,
+  code        VARCHAR NOT NULL
+);
+
+CREATE INDEX idx_recovery_codes_moderator_lookup ON recovery_codes(moderator, lookup);
@@ -0,0 +1,16 @@
+CREATE TABLE bans (
+  id          INTEGER NOT NULL PRIMARY KEY,
+  site        VARCHAR NOT NULL,
+  -- what the ban matches on: name, token or ip
+  kind        VARCHAR NOT NULL,
+  -- a signed user name, a base64 commenter token,
+  -- or an IP address or CIDR range
+  value       VARCHAR NOT NULL,
+  reason      TEXT,
+  -- moderator who added the ban, NULL when added from the command line
+  created_by  VARCHAR,
+  expires_at  DATETIME,
+  created_at  DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_bans_site ON bans(site);
@@ -0,0 +1,7 @@
+-- Shadow banned commenters can still post, but only they see their comments
+ALTER TABLE bans ADD COLUMN shadow BOOLEAN NOT NULL DEFAULT 0;
+
+-- Comments by shadow banned commenters, hidden from everyone but their author
+ALTER TABLE comments ADD COLUMN shadowed BOOLEAN NOT NULL DEFAULT 0;
+-- Name of the signed user who posted the comment, NULL for anonymous commenters
+ALTER TABLE comments ADD COLUMN signed_name VARCHAR;
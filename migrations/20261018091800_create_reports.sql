@@ -0,0 +1,14 @@
+CREATE TABLE reports (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  comment_id    INTEGER NOT NULL REFERENCES comments(id) ON UPDATE CASCADE ON DELETE CASCADE,
+  -- "user:<name>" for signed users, "token:<token>" for everyone else
+  reporter      VARCHAR NOT NULL,
+  -- reports from the same address only count once towards the threshold
+  ip            VARCHAR NOT NULL,
+  reason        TEXT NOT NULL,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  UNIQUE (comment_id, reporter)
+);
+
+-- Number of reports after which a comment is hidden until a moderator reviews it
+ALTER TABLE sites ADD COLUMN report_threshold INTEGER NOT NULL DEFAULT 3;
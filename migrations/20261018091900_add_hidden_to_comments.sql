@@ -0,0 +1,5 @@
+-- Comments a moderator took back for review after they were published,
+-- as opposed to comments which have never been reviewed
+ALTER TABLE comments ADD COLUMN hidden BOOLEAN NOT NULL DEFAULT 0;
+-- Hidden comments which their author doesn't see either
+ALTER TABLE comments ADD COLUMN hidden_from_author BOOLEAN NOT NULL DEFAULT 0;
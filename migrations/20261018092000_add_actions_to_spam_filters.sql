@@ -0,0 +1,20 @@
+ALTER TABLE sites ADD COLUMN max_links_action TEXT NOT NULL DEFAULT 'reject';
+ALTER TABLE sites ADD COLUMN banned_words_action TEXT NOT NULL DEFAULT 'reject';
+ALTER TABLE sites ADD COLUMN honeypot_action TEXT NOT NULL DEFAULT 'reject';
+ALTER TABLE sites ADD COLUMN min_submit_time_action TEXT NOT NULL DEFAULT 'reject';
+ALTER TABLE sites ADD COLUMN akismet_action TEXT NOT NULL DEFAULT 'reject';
+
+UPDATE sites SET
+  max_links_action = spam_action,
+  banned_words_action = spam_action,
+  honeypot_action = spam_action,
+  min_submit_time_action = spam_action,
+  akismet_action = spam_action;
+
+ALTER TABLE sites DROP COLUMN spam_action;
+
+-- the honeypot is off for new sites, like every other filter
+ALTER TABLE sites RENAME COLUMN honeypot TO honeypot_enabled;
+ALTER TABLE sites ADD COLUMN honeypot BOOLEAN NOT NULL DEFAULT 0;
+UPDATE sites SET honeypot = honeypot_enabled;
+ALTER TABLE sites DROP COLUMN honeypot_enabled;
@@ -0,0 +1,14 @@
+CREATE TABLE akismet_submissions (
+  id            INTEGER NOT NULL PRIMARY KEY,
+  site          VARCHAR NOT NULL,
+  spam          BOOLEAN NOT NULL,
+  -- the comment as described to Akismet, in JSON
+  comment       TEXT NOT NULL,
+  attempts      INTEGER NOT NULL DEFAULT 0,
+  last_error    TEXT,
+  send_after    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  sent_at       DATETIME,
+  created_at    DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_akismet_submissions_sent_at_send_after ON akismet_submissions(sent_at, send_after);
@@ -0,0 +1,5 @@
+-- only set for anonymous readers, who can react once
+-- to a comment from each address, whatever their token
+ALTER TABLE reactions ADD COLUMN ip VARCHAR;
+
+CREATE UNIQUE INDEX idx_reactions_comment_id_ip ON reactions(comment_id, ip);
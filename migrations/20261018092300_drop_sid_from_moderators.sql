@@ -0,0 +1,32 @@
+-- Sessions keep their own sids, so the old column on moderators is unused.
+-- SQLite can't drop a UNIQUE column, so the table is rebuilt. Dropping it
+-- cascades into the tables which reference moderators, so their rows are
+-- copied aside and put back once the new table takes the old one's name
+CREATE TABLE moderators_without_sid (
+  name            VARCHAR NOT NULL UNIQUE,
+  password        VARCHAR NOT NULL,
+  op              BOOLEAN NOT NULL DEFAULT 0,
+  avatar          TEXT,
+  all_sites       BOOLEAN NOT NULL DEFAULT 1,
+  role            TEXT NOT NULL DEFAULT 'admin',
+  totp_secret     BLOB,
+  totp_last_step  INTEGER
+);
+
+INSERT INTO moderators_without_sid (name, password, op, avatar, all_sites, role, totp_secret, totp_last_step)
+SELECT name, password, op, avatar, all_sites, role, totp_secret, totp_last_step FROM moderators;
+
+CREATE TABLE moderator_sites_copy AS SELECT * FROM moderator_sites;
+CREATE TABLE sessions_copy AS SELECT * FROM sessions;
+CREATE TABLE recovery_codes_copy AS SELECT * FROM recovery_codes;
+
+DROP TABLE moderators;
+ALTER TABLE moderators_without_sid RENAME TO moderators;
+
+INSERT INTO moderator_sites SELECT * FROM moderator_sites_copy;
+INSERT INTO sessions SELECT * FROM sessions_copy;
+INSERT INTO recovery_codes SELECT * FROM recovery_codes_copy;
+
+DROP TABLE moderator_sites_copy;
+DROP TABLE sessions_copy;
+DROP TABLE recovery_codes_copy;
@@ -0,0 +1,8 @@
+-- IP addresses moderators logged in from, which failed logins
+-- for their name don't lock them out of
+CREATE TABLE moderator_logins (
+  moderator      VARCHAR NOT NULL REFERENCES moderators(name) ON UPDATE CASCADE ON DELETE CASCADE,
+  ip             VARCHAR NOT NULL,
+  last_login_at  DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  PRIMARY KEY (moderator, ip)
+);
@@ -0,0 +1,28 @@
+-- Bans go away along with their site. SQLite can't add a foreign key
+-- to an existing table, so it is rebuilt, leaving out bans of sites
+-- which were deleted already
+CREATE TABLE bans_with_site (
+  id          INTEGER NOT NULL PRIMARY KEY,
+  site        VARCHAR NOT NULL REFERENCES sites(site) ON UPDATE CASCADE ON DELETE CASCADE,
+  -- what the ban matches on: name, token or ip
+  kind        VARCHAR NOT NULL,
+  -- a signed user name, a base64 commenter token,
+  -- or an IP address or CIDR range
+  value       VARCHAR NOT NULL,
+  reason      TEXT,
+  -- moderator who added the ban, NULL when added from the command line
+  created_by  VARCHAR,
+  expires_at  DATETIME,
+  created_at  DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+  -- lets the commenters post, but only they see their comments
+  shadow      BOOLEAN NOT NULL DEFAULT 0
+);
+
+INSERT INTO bans_with_site (id, site, kind, value, reason, created_by, expires_at, created_at, shadow)
+SELECT id, site, kind, value, reason, created_by, expires_at, created_at, shadow FROM bans
+WHERE site IN (SELECT site FROM sites);
+
+DROP TABLE bans;
+ALTER TABLE bans_with_site RENAME TO bans;
+
+CREATE INDEX idx_bans_site ON bans(site);
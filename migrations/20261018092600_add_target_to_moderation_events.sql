@@ -0,0 +1,3 @@
+-- What an event was about when it wasn't a comment or a page, like the
+-- commenters a ban was added or lifted for
+ALTER TABLE moderation_events ADD COLUMN target VARCHAR;
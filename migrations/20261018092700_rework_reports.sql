@@ -0,0 +1,16 @@
+-- Reported comments are only hidden on sites which ask for it. Sites still
+-- at the old default of 3 never chose it, so they are switched off too
+ALTER TABLE sites RENAME COLUMN report_threshold TO report_threshold_before;
+ALTER TABLE sites ADD COLUMN report_threshold INTEGER NOT NULL DEFAULT 0;
+UPDATE sites SET report_threshold = report_threshold_before WHERE report_threshold_before <> 3;
+ALTER TABLE sites DROP COLUMN report_threshold_before;
+
+-- Anonymous readers make up their own tokens, so they
+-- can only report a comment once from each address
+DELETE FROM reports
+WHERE reporter NOT LIKE 'user:%'
+AND id NOT IN (
+  SELECT min(id) FROM reports WHERE reporter NOT LIKE 'user:%' GROUP BY comment_id, ip
+);
+
+CREATE UNIQUE INDEX idx_reports_comment_id_ip ON reports(comment_id, ip) WHERE reporter NOT LIKE 'user:%';
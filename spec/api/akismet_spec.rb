@@ -0,0 +1,113 @@
+require 'socket'
+require 'uri'
+
+# A stand-in for the Akismet API which treats every comment
+# mentioning viagra as spam and remembers all requests
+class AkismetStub
+  attr_reader :requests
+
+  def initialize(port)
+    @requests = []
+    @server = TCPServer.new('127.0.0.1', port)
+    @thread = Thread.new { loop { handle(@server.accept) } }
+  end
+
+  def stop
+    @thread.kill
+    @server.close
+  end
+
+  # Moderators' decisions are submitted in the background, so give them a moment to arrive
+  def wait_for(path)
+    50.times do
+      request = requests.find { |r| r[:path] == path }
+      return request if request
+
+      sleep 0.1
+    end
+    nil
+  end
+
+  private
+
+  def handle(client)
+    path = client.gets.split[1]
+    headers = {}
+    while (line = client.gets) && line != "\r\n"
+      key, value = line.split(': ', 2)
+      headers[key.downcase] = value.strip
+    end
+    params = URI.decode_www_form(client.read(headers['content-length'].to_i)).to_h
+    requests << { path:, params: }
+
+    body = path.end_with?('comment-check') ? params['comment_content'].include?('viagra').to_s : 'Thanks'
+    client.write("HTTP/1.1 200 OK\r\nContent-Length: #{body.bytesize}\r\nConnection: close\r\n\r\n#{body}")
+  ensure
+    client.close
+  end
+end
+
+RSpec.describe 'Checking comments with Akismet' do
+  let(:akismet) { AkismetStub.new(6354) }
+  let(:site) do
+    add_site(
+      'test', private: false, anonymous: true, moderated: false,
+      akismet_key: 'secret-key', akismet_url: 'http://127.0.0.1:6354', akismet_action: 'review'
+    )
+  end
+  let(:moderator) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:moderator_req) { { site: 'test', path: '/', user: moderator.first, signature: moderator.last } }
+
+  def post_comment(body)
+    JSON.parse(post('/api/comment', { site: 'test', path: '/', payload: { body: } }).body, symbolize_names: true)
+  end
+
+  before do
+    akismet
+    site
+  end
+
+  after { akismet.stop }
+
+  it 'checks new comments' do
+    expect(post_comment('hello')).to match(hash_including(comment: hash_including(reviewed: true)))
+    expect(akismet.requests.last).to match(
+      path: '/1.1/comment-check',
+      params: hash_including(
+        'api_key' => 'secret-key', 'blog' => 'https://test', 'user_ip' => '127.0.0.1',
+        'permalink' => 'https://test/', 'comment_type' => 'comment', 'comment_content' => 'hello'
+      )
+    )
+  end
+
+  it 'holds spam for review' do
+    expect(post_comment('buy viagra')).to match(hash_including(comment: hash_including(reviewed: false)))
+  end
+
+  it 'reports comments marked as spam by moderators' do
+    post_comment('sneaky spam')
+
+    expect(post('/api/comment/1/spam', moderator_req).status).to eq 200
+    expect(akismet.wait_for('/1.1/submit-spam')).to match(
+      path: '/1.1/submit-spam', params: hash_including('comment_content' => 'sneaky spam')
+    )
+    expect(post('/api/comments', { site: 'test', path: '/' }).body).not_to match(/sneaky spam/)
+  end
+
+  it 'reports comments marked as not spam by moderators' do
+    post_comment('viagra is a medicine')
+
+    expect(post('/api/comment/1/ham', moderator_req).status).to eq 200
+    expect(akismet.wait_for('/1.1/submit-ham')).to match(
+      path: '/1.1/submit-ham', params: hash_including('comment_content' => 'viagra is a medicine')
+    )
+    expect(JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body, symbolize_names: true)).to match(
+      hash_including(comments: [hash_including(body: 'viagra is a medicine', reviewed: true)])
+    )
+  end
+
+  it 'only allows moderators to mark spam' do
+    post_comment('hello')
+    expect(post('/api/comment/1/spam', { site: 'test', path: '/' }).status).to eq 401
+  end
+end
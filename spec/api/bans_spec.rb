@@ -0,0 +1,157 @@
+RSpec.describe 'Banning commenters' do
+  let(:comment) { { site: 'test', path: '/', payload: { body: 'hello' } } }
+
+  def as(name, **kwargs)
+    user, signature = sign({ name:, **kwargs }, @secret)
+    { site: 'test', path: '/', user:, signature: }
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: false)
+  end
+
+  it 'bans IP addresses and CIDR ranges from the command line' do
+    expect(command('bans', 'add', 'test', 'ip', '127.0.0.0/8', reason: 'spam')).to match(/Success/)
+    expect(post('/api/comment', comment).status).to eq 403
+
+    command('bans', 'remove', '1')
+    expect(post('/api/comment', comment).status).to eq 200
+  end
+
+  it 'rejects invalid IP addresses' do
+    expect(command('bans', 'add', 'test', 'ip', '300.0.0.1')).to match(/not a valid IP address/)
+  end
+
+  it 'ignores expired bans' do
+    command('bans', 'add', 'test', 'ip', '127.0.0.1', expires: '2020-01-01')
+    expect(post('/api/comment', comment).status).to eq 200
+  end
+
+  it 'bans signed users by name' do
+    command('bans', 'add', 'test', 'name', 'troll')
+
+    expect(post('/api/comment', comment.merge(as('troll'))).status).to eq 403
+    expect(post('/api/comment', comment.merge(as('someone else'))).status).to eq 200
+  end
+
+  it 'does not ban moderators' do
+    command('bans', 'add', 'test', 'ip', '127.0.0.0/8')
+    expect(post('/api/comment', comment.merge(as('mod', moderator: true))).status).to eq 200
+  end
+
+  context 'from the moderator api' do
+    let(:admin) { as('admin', moderator: true) }
+
+    it 'bans the author of a comment by their token' do
+      token = JSON.parse(post('/api/comment', comment).body)['token']
+      ban = post('/api/ban', admin.merge(payload: { kind: 'token', comment_id: 1, reason: 'trolling' }))
+      expect(ban.status).to eq 200
+
+      expect(post('/api/comment', comment.merge(payload: { body: 'again', token: })).status).to eq 403
+      expect(post('/api/comment/1', comment.merge(payload: { body: 'again', token: })).status).to eq 403
+      expect(post('/api/comment', comment).status).to eq 200
+    end
+
+    it 'bans the author of a comment by name only if they signed it' do
+      post('/api/comment', comment.merge(payload: { body: 'anonymous', name: 'troll' }))
+      post('/api/comment', comment.merge(as('troll')).merge(payload: { body: 'signed' }))
+
+      expect(post('/api/ban', admin.merge(payload: { kind: 'name', comment_id: 1 })).status).to eq 422
+      expect(post('/api/ban', admin.merge(payload: { kind: 'name', comment_id: 2 })).status).to eq 200
+      expect(JSON.parse(post('/api/bans', admin).body).map { |b| b['value'] }).to eq ['troll']
+    end
+
+    it 'lists and lifts bans' do
+      post('/api/ban', admin.merge(payload: { kind: 'ip', value: '127.0.0.1' }))
+
+      bans = JSON.parse(post('/api/bans', admin).body)
+      expect(bans.map { |b| b['value'] }).to eq ['127.0.0.1']
+
+      expect(delete("/api/ban/#{bans.first['id']}", admin).status).to eq 200
+      expect(post('/api/comment', comment).status).to eq 200
+    end
+
+    it 'records bans in the audit log' do
+      post('/api/ban', admin.merge(payload: { kind: 'name', value: 'troll' }))
+      expect(command('audit', site: 'test')).to match(/ban by admin.*target: +name troll/m)
+    end
+
+    it 'is only available to admins' do
+      editor = as('editor', role: 'editor')
+      expect(post('/api/ban', editor.merge(payload: { kind: 'ip', value: '127.0.0.1' })).status).to eq 403
+      expect(post('/api/bans', editor).status).to eq 403
+    end
+  end
+end
+
+RSpec.describe 'Shadow banning commenters' do
+  let(:comment) { { site: 'test', path: '/', payload: { body: 'hello' } } }
+
+  def as(name, **kwargs)
+    user, signature = sign({ name:, **kwargs }, @secret)
+    { site: 'test', path: '/', user:, signature: }
+  end
+
+  def bodies(req)
+    JSON.parse(post('/api/comments', req).body)['comments'].map { |c| c['body'] }
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: false)
+    post('/api/comment', comment.merge(payload: { body: 'visible' }))
+  end
+
+  it 'shows comments to their author only' do
+    command('bans', 'add', 'test', 'ip', '127.0.0.1', shadow: true)
+    token = JSON.parse(post('/api/comment', comment.merge(payload: { body: 'hidden' })).body)['token']
+
+    expect(bodies({ site: 'test', path: '/' })).to eq ['visible']
+    expect(bodies({ site: 'test', path: '/', payload: { token: } })).to eq %w[hidden visible]
+    expect(bodies({ site: 'test', path: '/' }.merge(as('mod', moderator: true)))).to eq %w[hidden visible]
+  end
+
+  it 'matches signed users by name' do
+    command('bans', 'add', 'test', 'name', 'troll', shadow: true)
+    post('/api/comment', comment.merge(as('troll')).merge(payload: { body: 'hidden' }))
+
+    expect(bodies({ site: 'test', path: '/' }.merge(as('someone else')))).to eq ['visible']
+    expect(bodies({ site: 'test', path: '/' }.merge(as('troll')))).to eq %w[hidden visible]
+  end
+
+  it 'does not let anyone else react to or report comments' do
+    command('bans', 'add', 'test', 'name', 'troll', shadow: true)
+    post('/api/comment', comment.merge(as('troll')).merge(payload: { body: 'hidden' }))
+
+    expect(post('/api/comment/2/reactions', as('someone else')).status).to eq 404
+    expect(post('/api/comment/2/report', as('someone else').merge(payload: { reason: 'spam' })).status).to eq 404
+    expect(post('/api/comment/2/reactions', as('troll')).status).to eq 200
+  end
+
+  it 'hides replies and does not count them' do
+    command('bans', 'add', 'test', 'ip', '127.0.0.1', shadow: true)
+    post('/api/comment/1', comment.merge(payload: { body: 'hidden reply' }))
+
+    listed = JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body)['comments'].first
+    expect(listed['reply_count']).to eq 0
+    expect(listed['replies']).to be_empty
+  end
+
+  it 'does not keep deleted comments around for hidden replies only' do
+    post('/api/comment', comment.merge(payload: { body: 'deleted' }))
+    command('bans', 'add', 'test', 'name', 'troll', shadow: true)
+    post('/api/comment/2', comment.merge(as('troll')).merge(payload: { body: 'hidden reply' }))
+    delete('/api/comment/2', { site: 'test', path: '/' }.merge(as('mod', moderator: true)))
+
+    expect(bodies({ site: 'test', path: '/' })).to eq ['visible']
+    expect(bodies({ site: 'test', path: '/' }.merge(as('troll')))).to eq ['[deleted]', 'visible']
+  end
+
+  it 'keeps comments out of the unreviewed queue' do
+    command('sites', 'update', 'test', moderated: true)
+    command('bans', 'add', 'test', 'ip', '127.0.0.1', shadow: true)
+    post('/api/comment', comment)
+
+    unreviewed = post('/api/comments/unreviewed', { site: 'test', path: '/' }.merge(as('mod', moderator: true)))
+    expect(JSON.parse(unreviewed.body)).to be_empty
+  end
+end
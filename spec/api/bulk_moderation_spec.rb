@@ -0,0 +1,72 @@
+RSpec.describe 'Moderating comments in bulk' do
+  let(:req) { { site: 'test', path: '/' } }
+
+  def as(name, **kwargs)
+    user, signature = sign({ name:, **kwargs }, @secret)
+    { site: 'test', path: '/', user:, signature: }
+  end
+
+  def moderate(ids, action, user = as('mod', moderator: true))
+    post('/api/comments/moderate', user.merge(payload: { ids:, action: }))
+  end
+
+  def outcomes(response)
+    JSON.parse(response.body, symbolize_names: true).map { |r| [r[:id], r[:outcome]] }
+  end
+
+  def bodies
+    JSON.parse(post('/api/comments', req).body, symbolize_names: true)[:comments].map { |c| c[:body] }
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: true)
+    add_site('other', private: false, anonymous: true, moderated: true)
+
+    %w[first second third].each { |body| post('/api/comment', req.merge(payload: { body: })) }
+    post('/api/comment', { site: 'other', path: '/', payload: { body: 'elsewhere' } })
+  end
+
+  it 'approves comments and returns the outcome for each of them' do
+    response = moderate([1, 2, 4, 42], 'approve')
+
+    expect(response.status).to eq 200
+    expect(outcomes(response)).to eq [[1, 'done'], [2, 'done'], [4, 'not_found'], [42, 'not_found']]
+    expect(bodies).to eq %w[second first]
+  end
+
+  it 'leaves comments which already are in the requested state unchanged' do
+    moderate([1], 'approve')
+
+    expect(outcomes(moderate([1, 2], 'approve'))).to eq [[1, 'unchanged'], [2, 'done']]
+    expect(outcomes(moderate([1, 3], 'unapprove'))).to eq [[1, 'done'], [3, 'unchanged']]
+    expect(bodies).to eq %w[second]
+  end
+
+  it 'shows comments hidden from their author to them again' do
+    moderate([1], 'approve')
+    post('/api/comment/1/unapprove', as('mod', moderator: true).merge(payload: { from_author: true }))
+
+    expect(outcomes(moderate([1], 'unapprove'))).to eq [[1, 'done']]
+    expect(outcomes(moderate([1], 'unapprove'))).to eq [[1, 'unchanged']]
+  end
+
+  it 'records the changes in the audit log' do
+    moderate([1, 2], 'approve')
+
+    expect(command('audit', site: 'test')).to match(/approve by mod.*approve by mod/m)
+  end
+
+  it 'deletes comments' do
+    expect(outcomes(moderate([2, 3], 'delete'))).to eq [[2, 'done'], [3, 'done']]
+    expect(outcomes(moderate([2], 'delete'))).to eq [[2, 'not_found']]
+  end
+
+  it 'requires a moderator' do
+    expect(moderate([1], 'approve', req).status).to eq 401
+    expect(bodies).to eq []
+  end
+
+  it 'rejects unknown actions' do
+    expect(moderate([1], 'destroy').status).to eq 422
+  end
+end
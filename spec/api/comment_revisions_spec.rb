@@ -0,0 +1,49 @@
+RSpec.describe 'Comment revisions' do
+  let(:public_revisions) { false }
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: false, public_revisions:) }
+  let(:s) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:req) { { site: 'test', path: '/' } }
+  let(:response) { post('/api/comment/1/revisions', req) }
+  let(:json) { JSON.parse(response.body, symbolize_names: true) }
+
+  before do
+    site
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'first version' } })
+    put('/api/comment/1', { site: 'test', path: '/', user: s.first, signature: s.last, payload: { body: 'second version' } })
+  end
+
+  context 'an anonymous user' do
+    it 'is not allowed to see revisions' do
+      expect(response.status).to eq 401
+    end
+
+    context 'when revisions are public' do
+      let(:public_revisions) { true }
+
+      it 'is allowed to see revisions' do
+        expect(response.status).to eq 200
+      end
+    end
+  end
+
+  context 'a moderator' do
+    let(:req) { { site: 'test', path: '/', user: s.first, signature: s.last } }
+
+    it 'sees every version with a diff to the previous one' do
+      expect(json).to match(
+        [
+          hash_including(body: 'first version', diff: nil),
+          hash_including(body: 'second version', diff: /-first version.*\+second version/m)
+        ]
+      )
+    end
+  end
+
+  context 'a comment that does not exist' do
+    let(:response) { post('/api/comment/42/revisions', req) }
+
+    it 'returns not found' do
+      expect(response.status).to eq 404
+    end
+  end
+end
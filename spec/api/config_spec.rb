@@ -18,7 +18,10 @@ RSpec.describe 'Getting config' do
       expect(JSON.parse(response.body, symbolize_names: true)).to eq({
         anonymous: false,
         moderated: true,
-        locked: false
+        locked: false,
+        max_depth: 1,
+        reply_notifications: false,
+        default_sort: "newest"
       })
     end
   end
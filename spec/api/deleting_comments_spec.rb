@@ -49,4 +49,76 @@ RSpec.describe 'Deleting a comment' do
       expect(response.status).to eq 200
     end
   end
+
+  context 'a moderator of another site' do
+    let(:other) { add_site('other', private: false, anonymous: true, moderated: false) }
+    let(:s) { sign({ name: 'moderator', moderator: true }, other) }
+    let(:req) { { site: 'other', path: '/', user: s.first, signature: s.last } }
+
+    it 'returns not found' do
+      expect(response.status).to eq 404
+    end
+  end
+end
+
+RSpec.describe 'Deleting a comment with replies' do
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: false) }
+  let(:s) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:req) { { site: 'test', path: '/', user: s.first, signature: s.last } }
+  let(:comments) { JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body, symbolize_names: true) }
+
+  before do
+    site
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'a comment' } })
+    post('/api/comment/1', { site: 'test', path: '/', payload: { body: 'a reply' } })
+    delete('/api/comment/1', req)
+  end
+
+  it 'keeps the replies under a tombstone' do
+    expect(comments).to match(
+      hash_including(
+        comments: [
+          hash_including(id: 1, name: '[deleted]', body: '[deleted]', deleted: true, replies: [
+            hash_including(id: 2, body: 'a reply', deleted: false)
+          ])
+        ]
+      )
+    )
+  end
+
+  it 'drops the tombstone once the replies are deleted too' do
+    delete('/api/comment/2', req)
+    expect(comments).to match(hash_including(comments: [], total: 0))
+  end
+
+  context 'restoring' do
+    it 'is not allowed for non-moderators' do
+      response = post('/api/comment/1/restore', { site: 'test', path: '/' })
+      expect(response.status).to eq 401
+    end
+
+    it 'brings the comment back' do
+      response = post('/api/comment/1/restore', req)
+      expect(response.status).to eq 200
+      expect(comments).to match(
+        hash_including(comments: [hash_including(id: 1, body: 'a comment', deleted: false)])
+      )
+    end
+
+    it 'returns not found for comments which are not deleted' do
+      response = post('/api/comment/2/restore', req)
+      expect(response.status).to eq 404
+    end
+  end
+
+  context 'purging' do
+    it 'keeps tombstones which still have replies' do
+      expect(command('comments', 'purge', older_than: 0)).to match(/Purged 0 deleted comment/)
+    end
+
+    it 'removes deleted threads' do
+      delete('/api/comment/2', req)
+      expect(command('comments', 'purge', older_than: 0)).to match(/Purged 2 deleted comment/)
+    end
+  end
 end
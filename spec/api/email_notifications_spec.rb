@@ -0,0 +1,136 @@
+require 'socket'
+
+# A stand-in for an SMTP server which accepts every
+# message and remembers its recipient and content
+class SmtpSink
+  attr_reader :messages
+
+  def initialize(port)
+    @messages = []
+    @server = TCPServer.new('127.0.0.1', port)
+    @thread = Thread.new { loop { handle(@server.accept) } }
+  end
+
+  def stop
+    @thread.kill
+    @server.close
+  end
+
+  # Emails are sent in the background, so give them a moment to arrive
+  def wait_for(count)
+    50.times do
+      break if messages.size >= count
+
+      sleep 0.1
+    end
+    messages
+  end
+
+  private
+
+  def handle(client)
+    client.write("220 sink\r\n")
+    recipient = nil
+    while (line = client.gets)
+      case line
+      when /\A(EHLO|HELO)/i then client.write("250 sink\r\n")
+      when /\ARCPT TO:<(.+)>/i
+        recipient = Regexp.last_match(1)
+        client.write("250 ok\r\n")
+      when /\ADATA/i
+        client.write("354 go ahead\r\n")
+        data = +''
+        while (l = client.gets) && l != ".\r\n"
+          data << l
+        end
+        messages << { to: recipient, data: data.gsub("=\r\n", '') }
+        client.write("250 ok\r\n")
+      when /\AQUIT/i
+        client.write("221 bye\r\n")
+        break
+      else client.write("250 ok\r\n")
+      end
+    end
+  ensure
+    client.close
+  end
+end
+
+RSpec.describe 'Email notifications' do
+  let(:smtp) { SmtpSink.new(2525) }
+  let(:site) do
+    add_site(
+      'test', private: false, anonymous: true, moderated: true,
+      smtp_url: 'smtp://127.0.0.1:2525', email_from: 'blog@example.com',
+      moderator_emails: 'one@example.com, two@example.com', reply_notifications: true
+    )
+  end
+  let(:moderator) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:moderator_req) { { site: 'test', path: '/', user: moderator.first, signature: moderator.last } }
+
+  before do
+    smtp
+    site
+  end
+
+  after { smtp.stop }
+
+  it 'emails moderators about comments waiting for review' do
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello', name: 'Anna' } })
+
+    messages = smtp.wait_for(2)
+    expect(messages.map { |m| m[:to] }).to contain_exactly('one@example.com', 'two@example.com')
+    expect(messages.first[:data]).to match(/Subject: New comment awaiting review on test/)
+    expect(messages.first[:data]).to match(/Anna commented on https:\/\/test\//)
+  end
+
+  it 'rejects invalid email addresses' do
+    response = post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello', email: 'nope' } })
+
+    expect(response.status).to eq 422
+    expect(response.body).to eq 'Invalid email address'
+  end
+
+  context 'with a comment asking to be notified about replies' do
+    before do
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello', name: 'Anna', email: 'anna@example.com' } })
+      patch('/api/comment/1', moderator_req)
+      smtp.wait_for(2)
+      smtp.messages.clear
+    end
+
+    it 'emails the author about replies' do
+      post('/api/comment/1', moderator_req.merge(payload: { body: 'welcome' }))
+
+      messages = smtp.wait_for(1)
+      expect(messages.map { |m| m[:to] }).to eq ['anna@example.com']
+      expect(messages.first[:data]).to match(/Subject: moderator replied to your comment on test/)
+      expect(messages.first[:data]).to match(/welcome/)
+    end
+
+    it 'waits until replies are approved' do
+      post('/api/comment/1', { site: 'test', path: '/', payload: { body: 'anonymous reply' } })
+
+      expect(smtp.wait_for(2).map { |m| m[:to] }).to contain_exactly('one@example.com', 'two@example.com')
+      smtp.messages.clear
+
+      patch('/api/comment/2', moderator_req)
+      expect(smtp.wait_for(1).map { |m| m[:to] }).to eq ['anna@example.com']
+    end
+
+    it 'stops after unsubscribing' do
+      post('/api/comment/1', moderator_req.merge(payload: { body: 'welcome' }))
+      link = smtp.wait_for(1).first[:data][%r{http://\S+/api/unsubscribe\S+}].gsub('=3D', '=')
+      query = link.split('?', 2).last
+      smtp.messages.clear
+
+      forged = query.sub(/signature=[\w-]+/, 'signature=AAAA')
+      expect(Faraday.get("http://localhost:6353/api/unsubscribe?#{forged}").status).to eq 403
+      expect(Faraday.get("http://localhost:6353/api/unsubscribe?#{query}").status).to eq 200
+
+      post('/api/comment/1', moderator_req.merge(payload: { body: 'anyone there?' }))
+      sleep 1
+      expect(smtp.messages).to be_empty
+    end
+  end
+end
@@ -33,7 +33,7 @@ end
 
 RSpec.describe 'Multiple pages of comments' do
   before do
-    add_site('test', private: false, anonymous: true, moderated: false)
+    add_site('test', private: false, anonymous: true, moderated: false, rate_limit: 0)
 
     50.times do |i|
       post(
@@ -325,3 +325,53 @@ RSpec.describe 'listing unreviewed comments' do
     end
   end
 end
+
+RSpec.describe 'Sorting comments' do
+  def ids(sort = nil, cursor = nil)
+    endpoint = cursor ? "/api/comments?cursor=#{cursor}" : '/api/comments'
+    response = JSON.parse(post(endpoint, { site: 'test', path: '/', payload: { sort: sort } }).body, symbolize_names: true)
+    [response[:comments].map { |c| c[:id] }, response[:cursor]]
+  end
+
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false, rate_limit: 0)
+
+    45.times do |i|
+      post('/api/comment', { site: 'test', path: '/', payload: { body: "hello world #{i}" } })
+    end
+
+    [3, 3, 7].each do |parent|
+      post("/api/comment/#{parent}", { site: 'test', path: '/', payload: { body: 'reply' } })
+    end
+  end
+
+  it 'lists the oldest comments first' do
+    first, cursor = ids('oldest')
+    expect(first).to eq (1..42).to_a
+    expect(ids('oldest', cursor)).to eq [[43, 44, 45], nil]
+  end
+
+  it 'lists the comments with most replies first' do
+    first, cursor = ids('replies')
+    expect(first.take(4)).to eq [3, 7, 45, 44]
+
+    rest, = ids('replies', cursor)
+    expect(first + rest).to match_array (1..45).to_a
+  end
+
+  it 'uses the default order of the site' do
+    command('sites', 'update', 'test', default_sort: 'oldest')
+    expect(ids.first.take(3)).to eq [1, 2, 3]
+  end
+
+  it 'keeps the order of the cursor when none is given' do
+    _, cursor = ids('oldest')
+    expect(ids(nil, cursor)).to eq [[43, 44, 45], nil]
+  end
+
+  it 'does not accept a cursor from a different order' do
+    _, cursor = ids('oldest')
+    response = post("/api/comments?cursor=#{cursor}", { site: 'test', path: '/', payload: { sort: 'newest' } })
+    expect(response.status).to eq 400
+  end
+end
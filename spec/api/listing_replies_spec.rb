@@ -0,0 +1,63 @@
+def get_replies(id, cursor = nil)
+  endpoint = cursor ? "/api/comment/#{id}/replies?cursor=#{cursor}" : "/api/comment/#{id}/replies"
+  JSON.parse(post(endpoint, { site: 'test', path: '/' }).body, symbolize_names: true)
+end
+
+RSpec.describe 'Listing comments with many replies' do
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false, rate_limit: 0, max_depth: 2)
+
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'root' } })
+
+    25.times do |i|
+      post('/api/comment/1', { site: 'test', path: '/', payload: { body: "reply #{i}" } })
+    end
+
+    7.times do |i|
+      post('/api/comment/2', { site: 'test', path: '/', payload: { body: "nested reply #{i}" } })
+    end
+  end
+
+  it 'only lists the first few replies under each comment' do
+    comment = JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body, symbolize_names: true)[:comments].first
+
+    expect(comment[:reply_count]).to eq 25
+    expect(comment[:replies].map { |r| r[:id] }).to eq [2, 3, 4, 5, 6]
+    expect(comment[:replies].first[:reply_count]).to eq 7
+    expect(comment[:replies].first[:replies].map { |r| r[:id] }).to eq [27, 28, 29, 30, 31]
+  end
+
+  it 'pages through the replies of a comment' do
+    first = get_replies(1)
+    expect(first[:total]).to eq 25
+    expect(first[:replies].map { |r| r[:id] }).to eq (2..21).to_a
+    expect(first[:replies].first[:replies].length).to eq 5
+
+    second = get_replies(1, first[:cursor])
+    expect(second[:replies].map { |r| r[:id] }).to eq (22..26).to_a
+    expect(second[:cursor]).to be_nil
+  end
+
+  it 'returns 404 for a comment which does not exist' do
+    response = post('/api/comment/42/replies', { site: 'test', path: '/' })
+    expect(response.status).to eq 404
+  end
+end
+
+RSpec.describe 'Listing replies of an unreviewed comment' do
+  before do
+    add_site('test', private: false, anonymous: true, moderated: true)
+
+    @token = JSON.parse(
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'root' } }).body,
+      symbolize_names: true
+    )[:token]
+  end
+
+  it 'is only allowed for the author' do
+    expect(post('/api/comment/1/replies', { site: 'test', path: '/' }).status).to eq 404
+
+    response = post('/api/comment/1/replies', { site: 'test', path: '/', payload: { token: @token } })
+    expect(response.status).to eq 200
+  end
+end
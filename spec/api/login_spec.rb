@@ -22,3 +22,93 @@ RSpec.describe 'logging in' do
     end
   end
 end
+
+RSpec.describe 'Moderator sessions' do
+  def login
+    JSON.parse(post('/api/login', { name: 'test', password: 'test' }).body)['sid']
+  end
+
+  def moderate(sid)
+    post('/api/comments/unreviewed', { site: 'test', path: '/', sid: sid }).status
+  end
+
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false)
+    add_moderator
+  end
+
+  it 'keeps moderators logged in on more than one device' do
+    first = login
+    second = login
+
+    expect(moderate(first)).to eq 200
+    expect(moderate(second)).to eq 200
+  end
+
+  it 'logs out of a single session' do
+    first = login
+    second = login
+
+    expect(post('/api/logout', { sid: first }).status).to eq 200
+    expect(moderate(first)).to eq 401
+    expect(moderate(second)).to eq 200
+  end
+
+  it 'lists and revokes sessions from the command line' do
+    sid = login
+
+    expect(command('moderators', 'sessions', 'test')).to match(/Found 1 session\(s\)/)
+    expect(command('moderators', 'sessions', 'test', '--revoke-all')).to match(/Logged test out of 1 session\(s\)/)
+    expect(moderate(sid)).to eq 401
+  end
+
+  it 'logs out everywhere when the password changes' do
+    sid = login
+    command('moderators', 'update', 'test', password: 'changed')
+
+    expect(moderate(sid)).to eq 401
+  end
+end
+
+RSpec.describe 'Failed logins' do
+  def login(password)
+    post('/api/login', { name: 'test', password: password })
+  end
+
+  before { add_moderator }
+
+  it 'locks the moderator out after a few failures' do
+    3.times { expect(login('wrong').status).to eq 401 }
+    expect(login('wrong').status).to eq 401
+
+    response = login('test')
+    expect(response.status).to eq 429
+    expect(response.body).to match(/Too many failed login attempts/)
+    expect(response.headers['retry-after'].to_i).to be_between(1, 3)
+  end
+
+  it 'does not lock out after a successful login' do
+    3.times { login('wrong') }
+    expect(login('test').status).to eq 200
+    expect(login('wrong').status).to eq 401
+    expect(login('test').status).to eq 200
+  end
+
+  it 'does not count successful logins against the IP address' do
+    12.times { expect(login('test').status).to eq 200 }
+  end
+
+  it 'does not lock the moderator out of where they logged in before' do
+    expect(login('test').status).to eq 200
+    4.times { expect(login('wrong').status).to eq 401 }
+    expect(login('test').status).to eq 200
+  end
+
+  it 'lists and clears lockouts from the command line' do
+    4.times { login('wrong') }
+
+    expect(command('moderators', 'lockouts')).to match(/name test/)
+    expect(command('moderators', 'lockouts', 'test', '--clear')).to match(/Cleared 1 lockout\(s\)/)
+    expect(login('test').status).to eq 200
+  end
+end
@@ -0,0 +1,41 @@
+RSpec.describe 'Moderation audit log' do
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: true) }
+  let(:s) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:req) { { site: 'test', path: '/', user: s.first, signature: s.last } }
+
+  before do
+    site
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello world' } })
+  end
+
+  it 'records approvals' do
+    patch('/api/comment/1', req)
+    expect(command('audit', site: 'test')).to match(/approve by moderator/)
+  end
+
+  it 'records edits with the previous and new body' do
+    put('/api/comment/1', req.merge(payload: { body: 'edited' }))
+    log = command('audit', moderator: 'moderator')
+    expect(log).to match(/update by moderator/)
+    expect(log).to match(/before:\nhello world/)
+    expect(log).to match(/after:\nedited/)
+  end
+
+  it 'records deletions' do
+    delete('/api/comment/1', req)
+    expect(command('audit', site: 'test')).to match(/delete by moderator/)
+  end
+
+  it 'records locking and unlocking pages' do
+    patch('/api/pages', req)
+    patch('/api/pages', req)
+    log = command('audit', site: 'test')
+    expect(log).to match(/lock by moderator/)
+    expect(log).to match(/unlock by moderator/)
+  end
+
+  it 'filters by moderator' do
+    patch('/api/comment/1', req)
+    expect(command('audit', moderator: 'someone else')).to match(/Found 0 event/)
+  end
+end
@@ -0,0 +1,43 @@
+RSpec.describe 'Moderator roles' do
+  def as(role)
+    user, signature = sign({ name: "a #{role}", role: role }, @secret)
+    { site: 'test', path: '/', user: user, signature: signature }
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: true)
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'a comment' } })
+  end
+
+  it 'lets approvers approve comments only' do
+    expect(patch('/api/comment/1', as('approver')).status).to eq 200
+    expect(put('/api/comment/1', as('approver').merge(payload: { body: 'edited' })).status).to eq 403
+    expect(delete('/api/comment/1', as('approver')).status).to eq 403
+    expect(post('/api/comment/1/spam', as('approver')).status).to eq 403
+    expect(patch('/api/pages', as('approver')).status).to eq 403
+  end
+
+  it 'lets editors edit and delete comments' do
+    expect(put('/api/comment/1', as('editor').merge(payload: { body: 'edited' })).status).to eq 200
+    expect(delete('/api/comment/1', as('editor')).status).to eq 200
+    expect(patch('/api/pages', as('editor')).status).to eq 403
+  end
+
+  it 'lets admins lock pages' do
+    expect(patch('/api/pages', as('admin')).status).to eq 200
+  end
+
+  it 'treats signed moderators without a role as admins' do
+    user, signature = sign({ name: 'mod', moderator: true }, @secret)
+    expect(patch('/api/pages', { site: 'test', path: '/', user: user, signature: signature }).status).to eq 200
+  end
+
+  it 'applies to moderators logged in with a password' do
+    add_moderator('approver', 'test', role: 'approver')
+    sid = JSON.parse(post('/api/login', { name: 'approver', password: 'test' }).body, symbolize_names: true)[:sid]
+
+    expect(patch('/api/comment/1', { site: 'test', path: '/', sid: sid }).status).to eq 200
+    expect(patch('/api/pages', { site: 'test', path: '/', sid: sid }).status).to eq 403
+    expect(command('moderators', 'list')).to match(/role: approver/)
+  end
+end
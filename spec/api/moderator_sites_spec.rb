@@ -0,0 +1,48 @@
+RSpec.describe 'Moderators of a single site' do
+  def login(site = nil)
+    post('/api/login', { name: 'test', password: 'test', site: site })
+  end
+
+  before do
+    add_site('one', private: false, anonymous: true, moderated: true)
+    add_site('two', private: false, anonymous: true, moderated: true)
+    add_moderator(site: 'one')
+  end
+
+  it 'can log in to their own site only' do
+    expect(login('one').status).to eq 200
+    expect(login('two').status).to eq 403
+  end
+
+  it 'can only moderate their own site' do
+    sid = JSON.parse(login.body, symbolize_names: true)[:sid]
+
+    expect(post('/api/comments/unreviewed', { site: 'one', path: '/', sid: sid }).status).to eq 200
+    expect(post('/api/comments/unreviewed', { site: 'two', path: '/', sid: sid }).status).to eq 403
+  end
+
+  it 'is listed with their sites' do
+    expect(command('moderators', 'list')).to match(/sites: one/)
+  end
+
+  it 'can be given access to all sites' do
+    command('moderators', 'update', 'test', all_sites: true)
+    expect(login('two').status).to eq 200
+  end
+
+  it 'requires the sites to exist' do
+    expect(command('moderators', 'add', 'other', 'test', site: 'three')).to match(/Site three not found/)
+  end
+end
+
+RSpec.describe 'Moderators without any sites' do
+  before do
+    add_site('one', private: false, anonymous: true, moderated: true)
+    add_moderator
+  end
+
+  it 'can moderate every site' do
+    expect(post('/api/login', { name: 'test', password: 'test', site: 'one' }).status).to eq 200
+    expect(command('moderators', 'list')).to match(/sites: all/)
+  end
+end
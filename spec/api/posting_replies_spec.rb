@@ -70,12 +70,54 @@ RSpec.describe 'Posting a reply with bad data' do
       post("/api/comment/1", { site: 'test', path: '/', payload: { body: "hello" } })
     end
 
-    it 'returns not found' do
-      expect(response.status).to eq 404
+    it 'returns errors' do
+      expect(response.status).to eq 422
+      expect(response.body).to match(/Replies can't be nested any deeper/)
     end
   end
 end
 
+RSpec.describe 'Nested replies' do
+  let(:req) { { site: 'test', path: '/', payload: { body: 'a nested reply' } } }
+  let(:response) { post("/api/comment/2", req) }
+  let(:json) { JSON.parse(response.body, symbolize_names: true) }
+
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false, max_depth: 2)
+    post("/api/comment", { site: 'test', path: '/', payload: { body: 'a comment' } })
+    post("/api/comment/1", { site: 'test', path: '/', payload: { body: 'a reply' } })
+  end
+
+  it 'are allowed up to the max depth of the site' do
+    expect(json[:comment]).to match(hash_including(id: 3, parent_id: 2, body: 'a nested reply'))
+  end
+
+  it 'are not allowed beyond the max depth of the site' do
+    response
+    reply = post("/api/comment/3", req)
+    expect(reply.status).to eq 422
+    expect(reply.body).to match(/Replies can't be nested any deeper/)
+  end
+
+  it 'are listed as a tree' do
+    response
+    comments = JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body, symbolize_names: true)
+
+    expect(comments).to match(
+      hash_including(
+        comments: [
+          hash_including(id: 1, body: 'a comment', replies: [
+            hash_including(id: 2, body: 'a reply', replies: [
+              hash_including(id: 3, body: 'a nested reply', replies: [])
+            ])
+          ])
+        ],
+        total: 1
+      )
+    )
+  end
+end
+
 RSpec.describe 'Anonymous replying to comments' do
   let(:req) { { site: 'test', path: '/', payload: { body: 'a reply' } } }
   let(:response) { post("/api/comment/1", req) }
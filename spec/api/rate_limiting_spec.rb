@@ -0,0 +1,33 @@
+RSpec.describe 'Rate limiting comments' do
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: false, rate_limit: 2, rate_limit_window: 60) }
+  let(:comment) { { site: 'test', path: '/', payload: { body: 'hello' } } }
+
+  before { site }
+
+  it 'rejects comments over the limit with a retry-after header' do
+    2.times { expect(post('/api/comment', comment).status).to eq 200 }
+
+    response = post('/api/comment', comment)
+    expect(response.status).to eq 429
+    expect(response.body).to match(/You are posting too often/)
+    expect(response.headers['retry-after'].to_i).to be_between(1, 60)
+  end
+
+  it 'counts replies towards the limit' do
+    post('/api/comment', comment)
+    post('/api/comment/1', comment)
+    expect(post('/api/comment/1', comment).status).to eq 429
+  end
+
+  it 'does not limit moderators' do
+    s = sign({ name: 'moderator', moderator: true }, site)
+    3.times do
+      expect(post('/api/comment', comment.merge(user: s.first, signature: s.last)).status).to eq 200
+    end
+  end
+
+  it 'can be disabled' do
+    command('sites', 'update', 'test', rate_limit: 0)
+    3.times { expect(post('/api/comment', comment).status).to eq 200 }
+  end
+end
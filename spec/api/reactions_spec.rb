@@ -0,0 +1,87 @@
+def react(id, token = nil)
+  JSON.parse(post("/api/comment/#{id}/reactions", { site: 'test', path: '/', payload: { token: token } }).body, symbolize_names: true)
+end
+
+def list_comments(sort = nil, token = nil)
+  JSON.parse(post('/api/comments', { site: 'test', path: '/', payload: { token: token, sort: sort } }).body, symbolize_names: true)
+end
+
+RSpec.describe 'Reacting to comments' do
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: false)
+
+    3.times do |i|
+      post('/api/comment', { site: 'test', path: '/', payload: { body: "comment #{i}" } })
+    end
+  end
+
+  it 'gives an anonymous reader a token and counts their reaction once' do
+    response = react(1)
+    expect(response).to match(hash_including(reactions: 1, reacted: true))
+    expect(response[:token]).to_not be_nil
+
+    expect(react(1, response[:token])).to match(hash_including(reactions: 1, reacted: true))
+  end
+
+  it 'counts anonymous reactions once per IP address, whatever the token' do
+    react(1)
+    expect(react(1)).to match(hash_including(reactions: 1, reacted: false))
+  end
+
+  it 'limits how often a reader can react' do
+    statuses = 11.times.map { |i| post("/api/comment/#{i % 3 + 1}/reactions", { site: 'test', path: '/', payload: {} }).status }
+    expect(statuses.last).to eq 429
+  end
+
+  it 'removes a reaction' do
+    token = react(2)[:token]
+    response = JSON.parse(delete('/api/comment/2/reactions', { site: 'test', path: '/', payload: { token: token } }).body, symbolize_names: true)
+
+    expect(response).to match(hash_including(reactions: 0, reacted: false))
+  end
+
+  it 'shows reactions in the list of comments' do
+    token = react(2)[:token]
+
+    expect(list_comments(nil, token)[:comments]).to match([
+      hash_including(id: 3, reactions: 0, reacted: false),
+      hash_including(id: 2, reactions: 1, reacted: true),
+      hash_including(id: 1, reactions: 0, reacted: false)
+    ])
+
+    expect(list_comments[:comments]).to include(hash_including(id: 2, reactions: 1, reacted: false))
+  end
+
+  it 'sorts comments by the number of reactions' do
+    user, signature = sign({ name: 'some user' }, @secret)
+    post('/api/comment/1/reactions', { site: 'test', path: '/', user:, signature:, payload: {} })
+    react(1)
+    react(2)
+
+    expect(list_comments('top')[:comments].map { |c| c[:id] }).to eq [1, 2, 3]
+  end
+
+  it 'returns 404 for a comment which does not exist' do
+    response = post('/api/comment/42/reactions', { site: 'test', path: '/', payload: {} })
+    expect(response.status).to eq 404
+  end
+end
+
+RSpec.describe 'Reacting on a site without anonymous comments' do
+  before do
+    secret = add_site('test', private: false, anonymous: false, moderated: false)
+    @user, @signature = sign({ name: 'some user' }, secret)
+
+    post('/api/comment', { site: 'test', path: '/', user: @user, signature: @signature, payload: { body: 'comment' } })
+  end
+
+  it 'does not allow anonymous reactions' do
+    response = post('/api/comment/1/reactions', { site: 'test', path: '/', payload: {} })
+    expect(response.status).to eq 401
+  end
+
+  it 'allows signed users to react' do
+    response = post('/api/comment/1/reactions', { site: 'test', path: '/', user: @user, signature: @signature, payload: {} })
+    expect(JSON.parse(response.body, symbolize_names: true)).to match(hash_including(reactions: 1, reacted: true))
+  end
+end
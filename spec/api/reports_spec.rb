@@ -0,0 +1,93 @@
+RSpec.describe 'Reporting comments' do
+  let(:req) { { site: 'test', path: '/' } }
+
+  def as(name, **kwargs)
+    user, signature = sign({ name:, **kwargs }, @secret)
+    { site: 'test', path: '/', user:, signature: }
+  end
+
+  def report(id, reason, token = nil)
+    post("/api/comment/#{id}/report", req.merge(payload: { token:, reason: }))
+  end
+
+  def reported
+    JSON.parse(post('/api/comments/reported', as('mod', moderator: true)).body, symbolize_names: true)
+  end
+
+  def bodies
+    JSON.parse(post('/api/comments', req).body, symbolize_names: true)[:comments].map { |c| c[:body] }
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: false, report_threshold: 1)
+    post('/api/comment', req.merge(payload: { body: 'rude' }))
+    post('/api/comment', req.merge(payload: { body: 'polite' }))
+  end
+
+  it 'requires a reason' do
+    response = report(1, ' ')
+    expect(response.status).to eq 422
+    expect(response.body).to eq "Reason can't be blank"
+  end
+
+  it 'gives an anonymous reader a token' do
+    response = JSON.parse(report(1, 'spam').body, symbolize_names: true)
+    expect(response[:token]).to_not be_nil
+  end
+
+  it 'hides a comment once the threshold is reached' do
+    expect(report(1, 'insulting').status).to eq 200
+    expect(bodies).to eq %w[polite]
+    expect(command('audit', site: 'test')).to match(/unapprove by reports/)
+  end
+
+  it 'counts reports from the same address once' do
+    command('sites', 'update', 'test', report_threshold: 2)
+
+    report(1, 'insulting')
+    report(1, 'really insulting')
+    expect(bodies).to eq %w[polite rude]
+  end
+
+  it 'counts signed users once each' do
+    command('sites', 'update', 'test', report_threshold: 2)
+
+    post('/api/comment/1/report', as('alice').merge(payload: { reason: 'insulting' }))
+    post('/api/comment/1/report', as('bob').merge(payload: { reason: 'insulting' }))
+    expect(bodies).to eq %w[polite]
+  end
+
+  it 'does not hide comments unless the site asks for it' do
+    add_site('other', private: false, anonymous: true, moderated: false)
+    expect(command('sites', 'get', 'other')).to match(/report threshold: +disabled/)
+  end
+
+  it 'never hides comments when the threshold is zero' do
+    command('sites', 'update', 'test', report_threshold: 0)
+
+    report(1, 'insulting')
+    expect(bodies).to eq %w[polite rude]
+  end
+
+  it 'lists reported comments with their reasons to moderators' do
+    report(1, 'insulting')
+
+    expect(reported).to match([
+      hash_including(id: 1, body: 'rude', reviewed: false, reports: [hash_including(reason: 'insulting')])
+    ])
+    expect(post('/api/comments/reported', req).status).to eq 401
+  end
+
+  it 'dismisses reports when the comment is approved' do
+    report(1, 'insulting')
+    patch('/api/comment/1', as('mod', moderator: true))
+
+    expect(reported).to eq []
+    expect(bodies).to eq %w[polite rude]
+  end
+
+  it 'does not accept reports about hidden comments' do
+    report(1, 'insulting')
+    expect(report(1, 'insulting').status).to eq 404
+  end
+end
@@ -0,0 +1,108 @@
+RSpec.describe 'Filtering spam' do
+  let(:options) { {} }
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: false, **options) }
+  let(:response) { post('/api/comment', { site: 'test', path: '/', payload: }) }
+  let(:json) { JSON.parse(response.body, symbolize_names: true) }
+
+  before { site }
+
+  context 'with too many links' do
+    let(:options) { { max_links: 1 } }
+    let(:payload) { { body: 'see https://example.com and [this](https://example.org)' } }
+
+    it 'rejects the comment' do
+      expect(response.status).to eq 422
+      expect(response.body).to match(/Your comment contains too many links/)
+    end
+  end
+
+  context 'with banned words' do
+    let(:options) { { banned_words: 'casino, cheap pills' } }
+
+    context 'in the body' do
+      let(:payload) { { body: 'Best CASINO in town' } }
+
+      it 'rejects the comment' do
+        expect(response.status).to eq 422
+        expect(response.body).to match(/Your comment contains banned words/)
+      end
+    end
+
+    context 'in the name' do
+      let(:payload) { { body: 'hello', name: 'cheap pills' } }
+
+      it('rejects the comment') { expect(response.status).to eq 422 }
+    end
+
+    context 'as part of another word' do
+      let(:payload) { { body: 'occasionally' } }
+
+      it('accepts the comment') { expect(response.status).to eq 200 }
+    end
+  end
+
+  context 'with a filled in honeypot' do
+    let(:options) { { honeypot: true } }
+    let(:payload) { { body: 'hello', website: 'https://spam.example.com' } }
+
+    it 'rejects the comment' do
+      expect(response.status).to eq 422
+      expect(response.body).to match(/Your comment looks like spam/)
+    end
+  end
+
+  context 'with a minimum time to submit' do
+    let(:options) { { min_submit_time: 5 } }
+
+    context 'when written too fast' do
+      let(:payload) { { body: 'hello', time_to_submit: 1 } }
+
+      it('rejects the comment') { expect(response.status).to eq 422 }
+    end
+
+    context 'without a time' do
+      let(:payload) { { body: 'hello' } }
+
+      it('rejects the comment') { expect(response.status).to eq 422 }
+    end
+
+    context 'when written slowly enough' do
+      let(:payload) { { body: 'hello', time_to_submit: 10 } }
+
+      it('accepts the comment') { expect(response.status).to eq 200 }
+    end
+  end
+
+  context 'when the site sends spam for review' do
+    let(:options) { { banned_words: 'casino', banned_words_action: 'review' } }
+    let(:payload) { { body: 'Best casino in town' } }
+
+    it 'posts the comment as unreviewed' do
+      expect(response.status).to eq 200
+      expect(json).to match(hash_including(comment: hash_including(reviewed: false)))
+    end
+
+    context 'and it is caught by another filter' do
+      let(:options) { { banned_words: 'casino', banned_words_action: 'review', max_links: 1 } }
+      let(:payload) { { body: 'Best casino at https://example.com and https://example.org' } }
+
+      it('rejects the comment') { expect(response.status).to eq 422 }
+    end
+  end
+
+  context 'without any options' do
+    let(:payload) { { body: 'hello', website: 'https://spam.example.com' } }
+
+    it('ignores the honeypot') { expect(response.status).to eq 200 }
+  end
+
+  context 'as a moderator' do
+    let(:options) { { banned_words: 'casino' } }
+    let(:s) { sign({ name: 'moderator', moderator: true }, site) }
+    let(:response) do
+      post('/api/comment', { site: 'test', path: '/', user: s.first, signature: s.last, payload: { body: 'casino' } })
+    end
+
+    it('skips the filters') { expect(response.status).to eq 200 }
+  end
+end
@@ -0,0 +1,61 @@
+RSpec.describe 'Two-factor authentication' do
+  let(:enabled) { command('moderators', '2fa', 'enable', 'test') }
+  let(:uri) { enabled.lines.find { |l| l.start_with?('otpauth://') }.strip }
+  let(:recovery_codes) { enabled.lines.map(&:strip).grep(/\A[a-z2-7]{5}-[a-z2-7]{5}-[a-z2-7]{5}\z/) }
+
+  def login(**kwargs)
+    post('/api/login', { name: 'test', password: 'test', **kwargs })
+  end
+
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false)
+    add_moderator
+    enabled
+  end
+
+  it 'prints the otpauth URI and recovery codes' do
+    expect(uri).to start_with('otpauth://totp/Besedka:test?secret=')
+    expect(recovery_codes.length).to eq 10
+  end
+
+  it 'asks for a code after the right password' do
+    response = login
+    expect(response.status).to eq 401
+    expect(response.body).to eq 'Two-factor authentication code required'
+  end
+
+  it 'does not count asking for a code as a failed login' do
+    4.times { expect(login.status).to eq 401 }
+    expect(login(code: totp(uri)).status).to eq 200
+  end
+
+  it 'rejects wrong codes' do
+    expect(login(code: '000000').status).to eq 401
+    expect(login(password: 'wrong', code: totp(uri)).status).to eq 401
+  end
+
+  it 'logs in with a code from the authenticator app only once' do
+    code = totp(uri)
+    expect(login(code:).status).to eq 200
+    expect(login(code:).status).to eq 401
+  end
+
+  it 'logs in with each recovery code only once' do
+    code = recovery_codes.first
+    expect(login(code: code.upcase.delete('-')).status).to eq 200
+    expect(login(code:).status).to eq 401
+  end
+
+  it 'logs the moderator out when it gets enabled' do
+    command('moderators', '2fa', 'disable', 'test')
+    sid = JSON.parse(login.body)['sid']
+    command('moderators', '2fa', 'enable', 'test')
+
+    expect(post('/api/comments/unreviewed', { site: 'test', path: '/', sid: }).status).to eq 401
+  end
+
+  it 'can be disabled' do
+    expect(command('moderators', '2fa', 'disable', 'test')).to match(/Disabled two-factor authentication/)
+    expect(login.status).to eq 200
+  end
+end
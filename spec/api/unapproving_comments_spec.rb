@@ -0,0 +1,70 @@
+RSpec.describe 'Unapproving comments' do
+  let(:req) { { site: 'test', path: '/' } }
+
+  def as(name, **kwargs)
+    user, signature = sign({ name:, **kwargs }, @secret)
+    { site: 'test', path: '/', user:, signature: }
+  end
+
+  def unapprove(id, payload = nil, user = as('mod', moderator: true))
+    post("/api/comment/#{id}/unapprove", user.merge(payload:))
+  end
+
+  def comments(token = nil)
+    JSON.parse(post('/api/comments', req.merge(payload: { token: })).body, symbolize_names: true)[:comments]
+  end
+
+  before do
+    @secret = add_site('test', private: false, anonymous: true, moderated: false)
+    @token = JSON.parse(post('/api/comment', req.merge(payload: { body: 'hello' })).body)['token']
+  end
+
+  it 'hides a published comment from readers but not from its author' do
+    expect(unapprove(1).status).to eq 200
+
+    expect(comments).to eq []
+    expect(comments(@token)).to match([hash_including(id: 1, reviewed: false)])
+  end
+
+  it 'hides a comment from its author too' do
+    unapprove(1, { from_author: true })
+    expect(comments(@token)).to eq []
+  end
+
+  it 'tells hidden comments from never reviewed ones in the review queue' do
+    command('sites', 'update', 'test', moderated: true)
+    post('/api/comment', req.merge(payload: { body: 'new' }))
+    unapprove(1)
+
+    unreviewed = JSON.parse(post('/api/comments/unreviewed', as('mod', moderator: true)).body, symbolize_names: true)
+    expect(unreviewed).to match([hash_including(id: 1, hidden: true), hash_including(id: 2, hidden: false)])
+  end
+
+  it 'publishes the comment again once approved' do
+    unapprove(1, { from_author: true })
+    patch('/api/comment/1', as('mod', moderator: true))
+
+    expect(comments).to match([hash_including(id: 1, reviewed: true)])
+  end
+
+  it 'does not unapprove comments which have never been published' do
+    command('sites', 'update', 'test', moderated: true)
+    post('/api/comment', req.merge(payload: { body: 'new' }))
+
+    expect(unapprove(2).status).to eq 422
+  end
+
+  it 'requires a moderator' do
+    expect(unapprove(1, nil, req).status).to eq 401
+    expect(unapprove(1, nil, as('reader')).status).to eq 403
+  end
+
+  it 'unapproves comments from the command line' do
+    expect(command('comments', 'unapprove', '1', '--from-author')).to match(/Hid comment 1/)
+    expect(comments(@token)).to eq []
+    expect(command('audit', site: 'test')).to match(/unapprove by command line/)
+
+    patch('/api/comment/1', as('mod', moderator: true))
+    expect(comments).to match([hash_including(id: 1)])
+  end
+end
@@ -0,0 +1,144 @@
+require 'socket'
+
+# A stand-in for a webhook receiver which remembers every request
+# and answers with the queued statuses, then with 200
+class WebhookReceiver
+  attr_reader :requests, :statuses
+
+  def initialize(port)
+    @requests = []
+    @statuses = []
+    @server = TCPServer.new('127.0.0.1', port)
+    @thread = Thread.new { loop { handle(@server.accept) } }
+  end
+
+  def stop
+    @thread.kill
+    @server.close
+  end
+
+  # Events are delivered in the background, so give them a moment to arrive
+  def wait_for(count)
+    50.times do
+      break if requests.size >= count
+
+      sleep 0.1
+    end
+    requests
+  end
+
+  private
+
+  def handle(client)
+    path = client.gets.split[1]
+    headers = {}
+    while (line = client.gets) && line != "\r\n"
+      key, value = line.split(': ', 2)
+      headers[key.downcase] = value.strip
+    end
+    body = client.read(headers['content-length'].to_i)
+    requests << { path:, headers:, body: }
+
+    status = statuses.shift || 200
+    client.write("HTTP/1.1 #{status} Whatever\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+  ensure
+    client.close
+  end
+end
+
+RSpec.describe 'Webhooks' do
+  let(:receiver) { WebhookReceiver.new(6356) }
+  let(:site) { add_site('test', private: false, anonymous: true, moderated: false) }
+  let(:moderator) { sign({ name: 'moderator', moderator: true }, site) }
+  let(:moderator_req) { { site: 'test', path: '/', user: moderator.first, signature: moderator.last } }
+  let(:events) { receiver.requests.map { |r| r[:headers]['x-besedka-event'] } }
+
+  before do
+    receiver
+    site
+  end
+
+  after { receiver.stop }
+
+  context 'subscribed to all events' do
+    before { command('webhooks add', 'test', 'http://127.0.0.1:6356/hook') }
+
+    it 'posts signed comment events' do
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello' } })
+
+      request = receiver.wait_for(1).first
+      expect(request[:path]).to eq '/hook'
+      expect(JSON.parse(request[:body], symbolize_names: true)).to match(
+        hash_including(
+          event: 'comment.created',
+          site: 'test',
+          page: hash_including(path: '/', locked: false),
+          comment: hash_including(id: 1, body: 'hello', reviewed: true)
+        )
+      )
+
+      signed = "webhook:#{request[:body]}"
+      digest = OpenSSL::HMAC.digest(OpenSSL::Digest.new('sha256'), Base64.strict_decode64(site), signed)
+      expect(request[:headers]['x-besedka-signature']).to eq Base64.strict_encode64(digest)
+    end
+
+    it 'posts page events' do
+      patch('/api/pages', moderator_req)
+      patch('/api/pages', moderator_req)
+
+      receiver.wait_for(2)
+      expect(events).to eq %w[page.locked page.unlocked]
+    end
+
+    it 'posts moderation events' do
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello' } })
+      put('/api/comment/1', moderator_req.merge(payload: { body: 'edited' }))
+      delete('/api/comment/1', moderator_req)
+
+      receiver.wait_for(3)
+      expect(events).to eq %w[comment.created comment.updated comment.deleted]
+    end
+
+    it 'posts unapproved comments' do
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello' } })
+      post('/api/comment/1/unapprove', moderator_req)
+      patch('/api/comment/1', moderator_req)
+      post('/api/comments/moderate', moderator_req.merge(payload: { ids: [1], action: 'unapprove' }))
+
+      receiver.wait_for(4)
+      expect(events).to eq %w[comment.created comment.unapproved comment.approved comment.unapproved]
+      expect(JSON.parse(receiver.requests[1][:body], symbolize_names: true)[:comment]).to include(reviewed: false)
+    end
+
+    it 'retries failed deliveries and logs them' do
+      receiver.statuses << 500
+      post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello' } })
+      receiver.wait_for(1)
+      sleep 0.2
+
+      expect(command('webhooks deliveries', '1')).to match(/comment.created pending.*status:\s+500/m)
+    end
+  end
+
+  it 'only posts the chosen events' do
+    command('webhooks add', 'test', 'http://127.0.0.1:6356/hook', events: 'page.locked')
+    post('/api/comment', { site: 'test', path: '/', payload: { body: 'hello' } })
+    patch('/api/pages', moderator_req)
+
+    receiver.wait_for(1)
+    sleep 0.5
+    expect(events).to eq %w[page.locked]
+  end
+
+  it 'can be listed and removed' do
+    command('webhooks add', 'test', 'http://127.0.0.1:6356/hook', events: 'comment.created,comment.deleted')
+
+    expect(command('webhooks list', site: 'test')).to match(%r{#1 http://127.0.0.1:6356/hook.*events:\s+comment.created,comment.deleted}m)
+    expect(command('webhooks remove', '1')).to match(/Deleted webhook 1 for site test/)
+    expect(command('webhooks list')).to match(/Found 0 webhook\(s\)/)
+  end
+
+  it 'requires an existing site' do
+    expect(command('webhooks add', 'unknown', 'http://127.0.0.1:6356/hook')).to match(/Site unknown not found/)
+  end
+end
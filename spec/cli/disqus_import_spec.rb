@@ -0,0 +1,36 @@
+RSpec.describe 'Importing from Disqus' do
+  let(:file) { 'spec/fixtures/disqus.xml' }
+  let(:comments) do
+    JSON.parse(post('/api/comments', { site: 'blog.example.com', path: '/posts/hello-world/' }).body, symbolize_names: true)
+  end
+
+  before { add_site('blog.example.com', private: false, anonymous: true, moderated: false, max_depth: 3) }
+
+  it 'maps threads to pages and keeps the reply structure' do
+    expect(command('import disqus', file, site: 'blog.example.com')).to match(
+      /Imported 1 page\(s\) and 2 comment\(s\) from Disqus for site blog.example.com/
+    )
+    expect(comments).to match(
+      hash_including(
+        total: 1,
+        comments: [
+          hash_including(
+            name: 'Jane',
+            body: "First! **Great** post, see [this](https://example.com) & that\n\nSecond line",
+            avatar: 'https://disqus.com/api/users/avatars/jane.jpg',
+            replies: [hash_including(name: 'John', body: 'Thanks Jane', avatar: nil)]
+          )
+        ]
+      )
+    )
+  end
+
+  it 'can be imported more than once without duplicating comments' do
+    2.times { command('import disqus', file, site: 'blog.example.com') }
+    expect(comments).to match(hash_including(total: 1, comments: [hash_including(replies: [hash_including(name: 'John')])]))
+  end
+
+  it 'requires an existing site' do
+    expect(command('import disqus', file, site: 'unknown')).to match(/Site unknown not found/)
+  end
+end
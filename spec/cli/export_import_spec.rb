@@ -0,0 +1,33 @@
+RSpec.describe 'Exporting and importing a site' do
+  let(:file) { 'test-export.json' }
+  let(:export) { JSON.parse(File.read(file), symbolize_names: true) }
+  let(:comments) { JSON.parse(post('/api/comments', { site: 'test', path: '/' }).body, symbolize_names: true) }
+
+  before do
+    add_site('test', private: false, anonymous: true, moderated: false)
+    post('/api/comment', { site: 'test', path: '/', title: 'Home', payload: { body: 'a comment' } })
+    post('/api/comment/1', { site: 'test', path: '/', payload: { body: 'a reply' } })
+    command('export', 'test', output: file)
+  end
+
+  after { File.delete(file) if File.exist?(file) }
+
+  it 'writes a versioned export with the comment tree' do
+    expect(export).to match(
+      hash_including(
+        version: 1,
+        site: hash_including(site: 'test', anonymous: true),
+        pages: [
+          hash_including(path: '/', title: 'Home', comments: [
+            hash_including(body: 'a comment', replies: [hash_including(body: 'a reply')])
+          ])
+        ]
+      )
+    )
+  end
+
+  it 'can be imported more than once without duplicating comments' do
+    2.times { expect(command('import', file)).to match(/Imported 1 page\(s\) and 2 comment\(s\)/) }
+    expect(comments).to match(hash_including(total: 1, comments: [hash_including(replies: [hash_including(body: 'a reply')])]))
+  end
+end
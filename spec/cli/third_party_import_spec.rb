@@ -0,0 +1,81 @@
+RSpec.describe 'Importing from WordPress and Isso' do
+  let(:path) { '/posts/hello-world/' }
+  let(:comments) do
+    JSON.parse(post('/api/comments', { site: 'blog.example.com', path: }).body, symbolize_names: true)
+  end
+
+  before { add_site('blog.example.com', private: false, anonymous: true, moderated: false) }
+
+  context 'WordPress' do
+    let(:file) { 'spec/fixtures/wordpress.xml' }
+    let(:path) { '/2019/05/hello-world/' }
+
+    it 'shows a summary without importing anything on a dry run' do
+      expect(command('import wordpress', file, '--dry-run', site: 'blog.example.com')).to match(
+        /Found 1 page\(s\) and 3 comment\(s\) in the WordPress export \(1 replies, 1 awaiting review, 0 deleted\), skipping 1 spam comment\(s\)/
+      )
+      expect(comments).to match(hash_including(total: 0))
+    end
+
+    it 'keeps the reply structure and skips spam and pingbacks' do
+      2.times do
+        expect(command('import wordpress', file, site: 'blog.example.com')).to match(
+          /Imported 1 page\(s\) and 3 comment\(s\) from WordPress for site blog.example.com/
+        )
+      end
+
+      expect(comments).to match(
+        hash_including(
+          total: 1,
+          comments: [
+            hash_including(
+              name: 'Jane',
+              body: "First! **Great** post\n\nSecond line",
+              created_at: '2019-05-01T11:00:00Z',
+              replies: [hash_including(name: 'John', body: 'Thanks Jane')]
+            )
+          ]
+        )
+      )
+    end
+  end
+
+  context 'Isso' do
+    let(:file) { 'isso-test.db' }
+
+    before { `sqlite3 #{file} < spec/fixtures/isso.sql` }
+    after { File.delete(file) if File.exist?(file) }
+
+    it 'shows a summary without importing anything on a dry run' do
+      expect(command('import isso', file, '--dry-run', site: 'blog.example.com')).to match(
+        /Found 1 page\(s\) and 5 comment\(s\) in the Isso export \(2 replies, 1 awaiting review, 1 deleted\)/
+      )
+      expect(comments).to match(hash_including(total: 0))
+    end
+
+    it 'keeps the reply structure, pending and deleted comments' do
+      2.times do
+        expect(command('import isso', file, site: 'blog.example.com')).to match(
+          /Imported 1 page\(s\) and 5 comment\(s\) from Isso for site blog.example.com/
+        )
+      end
+
+      expect(comments).to match(
+        hash_including(
+          total: 2,
+          comments: [
+            hash_including(
+              deleted: true,
+              replies: [hash_including(name: 'Jill', body: 'Reply to a deleted comment')]
+            ),
+            hash_including(
+              name: 'Jane',
+              body: 'First! **Great** post',
+              replies: [hash_including(name: 'Anonymous', body: 'Thanks Jane')]
+            )
+          ]
+        )
+      )
+    end
+  end
+end
@@ -0,0 +1,18 @@
+CREATE TABLE threads (id INTEGER PRIMARY KEY, uri VARCHAR(256) UNIQUE, title VARCHAR(256));
+CREATE TABLE comments (
+  tid REFERENCES threads(id), id INTEGER PRIMARY KEY, parent INTEGER,
+  created FLOAT NOT NULL, modified FLOAT, mode INTEGER, remote_addr VARCHAR,
+  text VARCHAR, author VARCHAR, email VARCHAR, website VARCHAR,
+  likes INTEGER DEFAULT 0, dislikes INTEGER DEFAULT 0, voters BLOB NOT NULL,
+  notification INTEGER DEFAULT 0
+);
+
+INSERT INTO threads VALUES (1, '/posts/hello-world/', 'Hello World');
+
+INSERT INTO comments (tid, id, parent, created, modified, mode, text, author, voters)
+VALUES
+  (1, 1, NULL, 1556708400.5, NULL, 1, 'First! **Great** post', 'Jane', ''),
+  (1, 2, 1, 1556712000.0, NULL, 1, 'Thanks Jane', NULL, ''),
+  (1, 3, NULL, 1556715600.0, NULL, 2, 'Waiting for approval', 'Jim', ''),
+  (1, 4, NULL, 1556719200.0, 1556722800.0, 4, NULL, NULL, ''),
+  (1, 5, 4, 1556720000.0, NULL, 1, 'Reply to a deleted comment', 'Jill', '');
@@ -1,6 +1,7 @@
 require 'openssl'
 require 'base64'
 require 'json'
+require 'uri'
 
 module Utils
   def encode(data)
@@ -15,4 +16,17 @@ module Utils
 
     [encode(data), signature]
   end
+
+  # Generates the current two-factor authentication code
+  # for the secret in an otpauth URI
+  def totp(uri)
+    secret = URI.decode_www_form(URI(uri).query).to_h['secret']
+    bits = secret.each_char.map { |c| 'ABCDEFGHIJKLMNOPQRSTUVWXYZ234567'.index(c).to_s(2).rjust(5, '0') }.join
+    key = [bits[0, bits.length / 8 * 8]].pack('B*')
+    digest = OpenSSL::HMAC.digest(OpenSSL::Digest.new('sha1'), key, [Time.now.to_i / 30].pack('Q>'))
+    offset = digest.bytes.last & 0x0f
+    code = (digest[offset, 4].unpack1('N') & 0x7fffffff) % 1_000_000
+
+    code.to_s.rjust(6, '0')
+  end
 end
@@ -5,6 +5,14 @@ pub mod sites;
 pub mod pages;
 pub mod login;
 pub mod extractors;
+mod rate_limiter;
+pub mod spam;
+pub mod emails;
+pub mod reactions;
+pub mod bans;
+pub mod reports;
+
+use std::net::IpAddr;
 
 use axum::extract::FromRef;
 use chrono::{DateTime, Utc};
@@ -16,7 +24,13 @@ use base64::Engine;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: SqlitePool
+    pub db: SqlitePool,
+    pub limiter: RateLimiter,
+    pub notifier: Notifier,
+    pub webhooks: Webhooks,
+    pub akismet: Feedback,
+    /// Whether client IPs come from the X-Forwarded-For header
+    pub behind_proxy: bool,
 }
 
 impl FromRef<AppState> for SqlitePool {
@@ -25,15 +39,49 @@ impl FromRef<AppState> for SqlitePool {
     }
 }
 
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(app_state: &AppState) -> RateLimiter {
+        app_state.limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Notifier {
+    fn from_ref(app_state: &AppState) -> Notifier {
+        app_state.notifier.clone()
+    }
+}
+
+impl FromRef<AppState> for Webhooks {
+    fn from_ref(app_state: &AppState) -> Webhooks {
+        app_state.webhooks.clone()
+    }
+}
+
+impl FromRef<AppState> for Feedback {
+    fn from_ref(app_state: &AppState) -> Feedback {
+        app_state.akismet.clone()
+    }
+}
+
 pub use error::Error;
+pub use rate_limiter::RateLimiter;
 
-use crate::db::{self, sites::Site, moderators::{Moderator, self}, pages::Page};
+use crate::db::{self, sites::Site, moderators::{Moderator, self}, pages::Page, comments::Sort, sessions};
+use crate::{notifications::Notifier, webhooks::Webhooks};
+use spam::akismet::Feedback;
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Cursor {
     pub id: i64,
     pub created_at: DateTime<Utc>,
+    /// The order in which the page was listed
+    #[serde(default)]
+    pub sort: Sort,
+    /// Reply or reaction count of the last comment,
+    /// when comments are ranked by either of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
 }
 
 impl Cursor {
@@ -42,17 +90,50 @@ impl Cursor {
     }
 }
 
+/// Details about the client which sent a request
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub ip: IpAddr,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+}
+
+/// What a moderator is allowed to do. Each role
+/// can do everything the ones before it can
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Approves comments and marks them as not spam
+    Approver,
+    /// Edits, deletes and restores comments and marks them as spam
+    Editor,
+    /// Locks pages
+    Admin,
+}
+
+impl From<&str> for Role {
+    fn from(role: &str) -> Self {
+        match role {
+            "admin" => Self::Admin,
+            "editor" => Self::Editor,
+            _ => Self::Approver,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 struct SignedUser {
     name: Option<String>,
     avatar: Option<String>,
     moderator: Option<bool>,
+    role: Option<Role>,
     op: Option<bool>,
 }
 
 struct User {
     name: String,
     moderator: bool,
+    role: Option<Role>,
     op: bool,
     avatar: Option<String>,
 }
@@ -62,19 +143,29 @@ impl User {
         Self {
             name: moderator.name,
             moderator: true,
+            role: Some(Role::from(moderator.role.as_str())),
             op: moderator.op,
             avatar: moderator.avatar,
         }
     }
 
+    /// Signed moderators without a role are admins, and
+    /// signed users with a role are moderators
     fn from_signed_user(user: SignedUser) -> Self {
+        let moderator = user.moderator.unwrap_or(false) || user.role.is_some();
+
         Self {
             name: user.name.unwrap_or_else(|| String::from("Anonymous")),
-            moderator: user.moderator.unwrap_or(false),
+            moderator,
+            role: user.role.or(moderator.then_some(Role::Admin)),
             op: user.op.unwrap_or(false),
             avatar: user.avatar,
         }
     }
+
+    fn can(&self, role: Role) -> bool {
+        self.role.is_some_and(|r| r >= role)
+    }
 }
 
 #[derive(Deserialize)]
@@ -90,7 +181,7 @@ struct ApiRequest<T> {
 
 #[derive(Clone, Debug, sqlx::Type, PartialEq, Eq)]
 #[sqlx(transparent)]
-pub struct Base64(Vec<u8>);
+pub struct Base64(pub Vec<u8>);
 
 impl Serialize for Base64 {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -131,11 +222,20 @@ impl<T> ApiRequest<T> {
         let site = db::sites::find(db, &self.site).await
             .map_err(|_| Error::BadRequest("No configuration found for requested site"))?;
 
-        // logged in moderators always take precedence over 3rd party users
+        // logged in moderators always take precedence over 3rd party users,
+        // as long as they moderate the requested site
         if let Some(ref sid) = self.sid {
-            match moderators::find_by_sid(db, sid).await {
+            let session = sessions::find_active(db, sid).await.map_err(|_| Error::Unauthorized)?;
+            sessions::touch(db, &session, session_lifetime(), session_max_lifetime()).await?;
+
+            match moderators::find_by_name(db, &session.moderator).await {
                 Err(_) => return Err(Error::Unauthorized),
-                Ok(moderator) => return Ok((site, Some(User::from_moderator(moderator)))),
+                Ok(moderator) => {
+                    if !moderators::moderates(db, &moderator, &site.site).await? {
+                        return Err(Error::Forbidden)
+                    }
+                    return Ok((site, Some(User::from_moderator(moderator))))
+                },
             }
         }
 
@@ -160,6 +260,16 @@ impl<T> ApiRequest<T> {
     }
 }
 
+/// How long moderators stay logged in without using their session
+fn session_lifetime() -> chrono::Duration {
+    chrono::Duration::days(14)
+}
+
+/// How long moderators stay logged in at most, however often they use their session
+fn session_max_lifetime() -> chrono::Duration {
+    chrono::Duration::days(90)
+}
+
 pub fn generate_random_token() -> Base64 {
     let mut sid = [0_u8; 48];
     let rg = SystemRandom::new();
@@ -177,10 +287,10 @@ fn verify_read_permission(site: &Site, user: &Option<User>, page: Option<&Page>)
     Ok(())
 }
 
-fn require_moderator(user: &Option<User>) -> Result<()> {
+fn require_role(user: &Option<User>, role: Role) -> Result<()> {
     match user {
         None => return Err(Error::Unauthorized),
-        Some(u) => if !u.moderator { return Err(Error::Forbidden) }
+        Some(u) => if !u.can(role) { return Err(Error::Forbidden) }
     };
     Ok(())
 }
@@ -190,4 +300,7 @@ struct PageConfig {
     anonymous: bool,
     moderated: bool,
     locked: bool,
+    max_depth: i64,
+    reply_notifications: bool,
+    default_sort: Sort,
 }
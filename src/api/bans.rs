@@ -0,0 +1,133 @@
+use axum::{extract::{Path, State}, routing::{delete, post}, Json, Router};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::db::{audit::{self, Action, NewEvent}, bans::{self, Ban, Kind, NewBan}, comments, sites::Site};
+
+use super::{ApiRequest, AppState, Error, Result, Role, User, require_role};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/bans", post(index))
+        .route("/api/ban", post(create))
+        .route("/api/ban/:ban_id", delete(destroy))
+}
+
+#[derive(Deserialize)]
+struct BanData {
+    kind: Kind,
+    /// A signed user name, a base64 commenter token,
+    /// or an IP address or CIDR range
+    value: Option<String>,
+    /// Bans the author of this comment instead of a value
+    comment_id: Option<i64>,
+    reason: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    /// Lets the commenters post, but only they see their comments
+    #[serde(default)]
+    shadow: bool,
+}
+
+/// POST /api/bans lists the bans of a site which haven't expired
+async fn index(
+    State(db): State<SqlitePool>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<Json<Vec<Ban>>> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Admin)?;
+
+    Ok(Json(bans::active(&db, &site.site).await?))
+}
+
+/// POST /api/ban
+async fn create(
+    State(db): State<SqlitePool>,
+    Json(req): Json<ApiRequest<BanData>>,
+) -> Result<Json<Ban>> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Admin)?;
+
+    let data = req.payload.as_ref().ok_or(Error::UnprocessableEntity("Payload can't be blank"))?;
+
+    let value = match (data.comment_id, &data.value) {
+        (Some(comment_id), _) => author(&db, &site, comment_id, data.kind).await?,
+        (None, Some(value)) => value.trim().to_string(),
+        (None, None) => return Err(Error::UnprocessableEntity("Either a value or a comment to ban is required")),
+    };
+
+    if value.is_empty() { return Err(Error::UnprocessableEntity("Value can't be blank")) }
+    if data.kind == Kind::Ip && bans::ip_range(&value).is_none() {
+        return Err(Error::UnprocessableEntity("Invalid IP address or CIDR range"))
+    }
+
+    let ban = bans::create(&db, NewBan {
+        site: &site.site,
+        kind: data.kind,
+        value: &value,
+        reason: data.reason.as_deref().map(str::trim).filter(|r| !r.is_empty()),
+        created_by: user.as_ref().map(|u| u.name.as_str()),
+        expires_at: data.expires_at,
+        shadow: data.shadow,
+    }).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Ban, &ban).await?;
+
+    Ok(Json(ban))
+}
+
+/// DELETE /api/ban/42
+async fn destroy(
+    State(db): State<SqlitePool>,
+    Path(ban_id): Path<i64>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<String> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Admin)?;
+
+    let ban = bans::find(&db, ban_id).await?;
+    if ban.site != site.site { return Err(Error::NotFound) }
+
+    bans::delete(&db, ban_id).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Unban, &ban).await?;
+
+    Ok("Success".to_string())
+}
+
+/// Returns what to ban the author of a comment by
+async fn author(db: &SqlitePool, site: &Site, comment_id: i64, kind: Kind) -> Result<String> {
+    let comment = comments::find_on_site(db, &site.site, comment_id).await?;
+
+    Ok(match kind {
+        // names typed by anonymous readers aren't theirs to be banned by
+        Kind::Name => comment.signed_name
+            .ok_or(Error::UnprocessableEntity("This comment wasn't posted by a signed user"))?,
+        Kind::Token => STANDARD.encode(&comment.token.0),
+        // addresses are only kept for comments checked by Akismet
+        Kind::Ip => comments::metadata(db, comment_id)
+            .await
+            .map_err(|_| Error::UnprocessableEntity("The IP address of this comment isn't known"))?
+            .ip,
+    })
+}
+
+async fn record_event(db: &SqlitePool, site: &Site, user: Option<&User>, action: Action, ban: &Ban) -> Result<()> {
+    let Some(u) = user else { return Ok(()) };
+    let target = format!("{} {}", ban.kind, ban.value);
+
+    audit::record(db, NewEvent {
+        site: &site.site,
+        actor: &u.name,
+        moderator: u.moderator,
+        action,
+        comment_id: None,
+        page_id: None,
+        target: Some(&target),
+        body_before: None,
+        body_after: None,
+    }).await?;
+
+    Ok(())
+}
@@ -1,22 +1,35 @@
 use crate::{
-    api::{ApiRequest, Cursor, Error, AppState, Result},
+    api::{
+        ApiRequest, ClientInfo, Cursor, Error, AppState, Result,
+        spam::{self, Submission, Verdict, akismet::{Akismet, AkismetComment, Feedback}},
+    },
     db::{
-        comments::{Comment, self},
+        audit::{self, Action, Actor, NewEvent},
+        bans,
+        comments::{BulkAction, Comment, Metadata, Outcome, Owner, Sort, self},
         pages::{Page, self},
+        emails,
+        reactions::{self, Reactions},
         sites::Site,
     },
+    notifications::Notifier,
+    webhooks::{Event, Webhooks},
 };
 use axum::{extract::{State, Path}, routing::post, Json, Router};
 use chrono::{DateTime, Utc};
+use std::{collections::HashMap, net::IpAddr};
+use base64::{Engine, engine::general_purpose::STANDARD};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
-use super::{User, Base64, generate_random_token, verify_read_permission, require_moderator};
+use super::{User, Base64, Role, generate_random_token, verify_read_permission, require_role, reactions::reactor};
+use similar::TextDiff;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/api/comments", post(index))
         .route("/api/comments/unreviewed", post(unreviewed))
+        .route("/api/comments/moderate", post(moderate))
         .route("/api/comment", post(create))
         .route(
             "/api/comment/:comment_id",
@@ -25,6 +38,12 @@ pub fn router() -> Router<AppState> {
                 .delete(destroy)
                 .put(update)
         )
+        .route("/api/comment/:comment_id/restore", post(restore))
+        .route("/api/comment/:comment_id/unapprove", post(unapprove))
+        .route("/api/comment/:comment_id/replies", post(replies))
+        .route("/api/comment/:comment_id/revisions", post(revisions))
+        .route("/api/comment/:comment_id/spam", post(spam))
+        .route("/api/comment/:comment_id/ham", post(ham))
 }
 
 #[derive(Serialize)]
@@ -41,6 +60,12 @@ struct CommentWithReplies {
     moderator: bool,
     owned: bool,
     edited: bool,
+    deleted: bool,
+    reactions: i64,
+    reacted: bool,
+    /// Number of direct replies, some of
+    /// which may not be loaded yet
+    reply_count: i64,
     replies: Vec<OwnedComment>,
 }
 
@@ -59,6 +84,65 @@ struct OwnedComment {
     moderator: bool,
     owned: bool,
     edited: bool,
+    deleted: bool,
+    reactions: i64,
+    reacted: bool,
+    /// Number of direct replies, some of
+    /// which may not be loaded yet
+    reply_count: i64,
+    replies: Vec<OwnedComment>,
+}
+
+/// Hides everything but the position in the thread of a deleted comment
+fn tombstone(comment: Comment) -> Comment {
+    Comment {
+        name: String::from("[deleted]"),
+        body: String::from("[deleted]"),
+        html_body: String::from("<p>[deleted]</p>"),
+        avatar: None,
+        op: false,
+        moderator: false,
+        ..comment
+    }
+}
+
+impl OwnedComment {
+    fn new(
+        comment: Comment,
+        token: &Option<Base64>,
+        reactions: Reactions,
+        reply_count: i64,
+        replies: Vec<OwnedComment>,
+    ) -> Self {
+        let deleted = comment.deleted_at.is_some();
+        let reactions = if deleted { Reactions::default() } else { reactions };
+        let comment = if deleted { tombstone(comment) } else { comment };
+        let owned = match token {
+            None => false,
+            Some(t) => !deleted && t == &comment.token
+        };
+
+        Self {
+            id: comment.id,
+            parent_id: comment.parent_id,
+            name: comment.name,
+            html_body: comment.html_body,
+            body: comment.body,
+            avatar: comment.avatar,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+            reviewed: comment.reviewed,
+            edited: comment.created_at != comment.updated_at,
+            op: comment.op,
+            moderator: comment.moderator,
+            owned,
+            deleted,
+            reactions: reactions.count,
+            reacted: reactions.reacted,
+            reply_count,
+            replies,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -73,17 +157,64 @@ struct CommentData {
     body: String,
     name: Option<String>,
     token: Option<Base64>,
+    /// Honeypot, the widget hides this field from humans
+    website: Option<String>,
+    /// Seconds it took to write the comment
+    time_to_submit: Option<u64>,
+    /// Address at which to be notified about replies
+    email: Option<String>,
 }
 
 const COMMENTS_PER_PAGE: i64 = 42;
 
+/// Replies listed under each comment, the
+/// rest are loaded a page at a time
+const REPLIES_PER_COMMENT: i64 = 5;
+
+const REPLIES_PER_PAGE: i64 = 20;
+
+/// Recursively nests replies under their parent, keeping the order
+/// in which they were loaded. Deleted replies are only kept as
+/// tombstones if there is anything left underneath them
+fn reply_tree(
+    parent_id: i64,
+    all_replies: &[Comment],
+    token: &Option<Base64>,
+    reactions: &HashMap<i64, Reactions>,
+    reply_counts: &HashMap<i64, i64>,
+) -> Vec<OwnedComment> {
+    all_replies
+        .iter()
+        .filter(|r| r.parent_id == Some(parent_id))
+        .filter_map(|r| reply_with_replies(r.clone(), all_replies, token, reactions, reply_counts))
+        .collect()
+}
+
+/// Builds a reply along with everything loaded underneath it
+fn reply_with_replies(
+    comment: Comment,
+    all_replies: &[Comment],
+    token: &Option<Base64>,
+    reactions: &HashMap<i64, Reactions>,
+    reply_counts: &HashMap<i64, i64>,
+) -> Option<OwnedComment> {
+    let replies = reply_tree(comment.id, all_replies, token, reactions, reply_counts);
+    if comment.deleted_at.is_some() && replies.is_empty() { return None }
+
+    let comment_reactions = reactions.get(&comment.id).copied().unwrap_or_default();
+    let reply_count = reply_counts.get(&comment.id).copied().unwrap_or_default();
+    Some(OwnedComment::new(comment, token, comment_reactions, reply_count, replies))
+}
+
 fn comments_page(
     parents: Vec<Comment>,
     all_replies: Vec<Comment>,
     total: i64,
     token: &Option<Base64>,
+    reactions: &HashMap<i64, Reactions>,
+    reply_counts: &HashMap<i64, i64>,
+    cursor: Option<String>,
 ) -> CommentsPage {
-    let parents_len = parents.len() as i64;
     let mut comments = vec![];
     let mut count = 0_i64;
 
@@ -92,39 +223,18 @@ fn comments_page(
             break;
         }
 
-        let mut replies = vec![];
-
-        let comment_replies: Vec<Comment> = all_replies
-            .iter()
-            .filter(|r| r.parent_id == Some(parent.id))
-            .cloned()
-            .collect();
-
-        for r in comment_replies {
-            let owned = match token {
-                None => false,
-                Some(t) => t == &r.token
-            };
-            replies.push(OwnedComment {
-                id: r.id,
-                parent_id: r.parent_id,
-                name: r.name,
-                html_body: r.html_body,
-                body: r.body,
-                avatar: r.avatar,
-                created_at: r.created_at,
-                updated_at: r.updated_at,
-                reviewed: r.reviewed,
-                edited: r.created_at != r.updated_at,
-                op: r.op,
-                moderator: r.moderator,
-                owned,
-            })
-        }
+        let replies = reply_tree(parent.id, &all_replies, token, reactions, reply_counts);
+        let reply_count = reply_counts.get(&parent.id).copied().unwrap_or_default();
 
+        let deleted = parent.deleted_at.is_some();
+        let parent_reactions = match deleted {
+            true => Reactions::default(),
+            false => reactions.get(&parent.id).copied().unwrap_or_default(),
+        };
+        let parent = if deleted { tombstone(parent) } else { parent };
         let owned = match token {
             None => false,
-            Some(t) => t == &parent.token
+            Some(t) => !deleted && t == &parent.token
         };
 
         comments.push(CommentWithReplies {
@@ -140,24 +250,16 @@ fn comments_page(
             op: parent.op,
             moderator: parent.moderator,
             owned,
+            deleted,
+            reactions: parent_reactions.count,
+            reacted: parent_reactions.reacted,
+            reply_count,
             replies,
         });
 
         count += 1;
     }
 
-    let cursor = if COMMENTS_PER_PAGE < parents_len {
-        Some(
-            Cursor {
-                id: comments.last().unwrap().id,
-                created_at: comments.last().unwrap().created_at,
-            }
-            .encode(),
-        )
-    } else {
-        None
-    };
-
     CommentsPage {
         comments,
         cursor,
@@ -167,7 +269,8 @@ fn comments_page(
 
 #[derive(Deserialize)]
 struct ListCommentsRequest {
-    token: Option<Base64>
+    token: Option<Base64>,
+    sort: Option<Sort>,
 }
 
 /// POST /api/comments
@@ -184,36 +287,155 @@ async fn index(
 
     let show_only_reviewed = user
         .as_ref()
-        .map_or(true, |u| !u.moderator);
+        .is_none_or(|u| !u.moderator);
+    let owner = Owner {
+        token: req.payload.as_ref().and_then(|p| p.token.as_ref()),
+        name: user.as_ref().map(|u| u.name.as_str()),
+    };
+    let sort = sort_order(&site, req.payload.as_ref().and_then(|p| p.sort), &cursor);
     // We need the fetch limit + 1 in order
     // to work out if there is a next page or not
-    let (total, parents) = comments::root_comments(
+    let (total, mut parents) = comments::root_comments(
         &db,
         page.id,
         COMMENTS_PER_PAGE + 1,
         show_only_reviewed,
-        req.payload.as_ref().map_or(&None, |p| &p.token),
-        cursor
+        owner,
+        cursor,
+        sort,
     ).await?;
 
-    let replies = comments::replies(
+    let cursor = next_cursor(&db, &parents, COMMENTS_PER_PAGE, sort).await?;
+    parents.truncate(COMMENTS_PER_PAGE as usize);
+
+    let parent_ids: Vec<i64> = parents.iter().map(|p| p.id).collect();
+    let (replies, reply_counts) = comments::replies(
         &db,
+        page.id,
         show_only_reviewed,
-        req.payload.as_ref().map_or(&None, |p| &p.token),
-        &parents
+        owner,
+        &parent_ids,
+        sort,
+        REPLIES_PER_COMMENT,
     ).await?;
 
+    let ids: Vec<i64> = parents.iter().chain(replies.iter()).map(|c| c.id).collect();
+    let reactor = reactor(&user, req.payload.as_ref().map_or(&None, |p| &p.token));
+    let reactions = reactions::for_comments(&db, &ids, reactor.as_deref()).await?;
+
     Ok(Json(comments_page(
         parents,
         replies,
         total,
         req.payload.as_ref().map_or(&None, |p| &p.token),
+        &reactions,
+        &reply_counts,
+        cursor,
     )))
 }
 
+/// Comments are listed in the order asked for. Otherwise, following a cursor
+/// keeps the order it was made for, and a first page uses the site's default
+fn sort_order(site: &Site, requested: Option<Sort>, cursor: &Option<Cursor>) -> Sort {
+    requested
+        .or(cursor.as_ref().map(|c| c.sort))
+        .unwrap_or_else(|| Sort::from(site.default_sort.as_str()))
+}
+
+/// Points at the last comment on a page when there are more after it,
+/// which is why one more comment than fits on a page is fetched
+async fn next_cursor(db: &SqlitePool, comments: &[Comment], per_page: i64, sort: Sort) -> Result<Option<String>> {
+    if comments.len() as i64 <= per_page { return Ok(None) }
+
+    let last = &comments[per_page as usize - 1];
+
+    Ok(Some(Cursor {
+        id: last.id,
+        created_at: last.created_at,
+        sort,
+        score: comments::score(db, sort, last.id).await?,
+    }.encode()))
+}
+
 #[derive(Serialize)]
-struct UnreviewedComment {
-    id: i64,
+struct RepliesPage {
+    total: i64,
+    cursor: Option<String>,
+    replies: Vec<OwnedComment>,
+}
+
+/// POST /api/comment/42/replies
+async fn replies(
+    State(db): State<SqlitePool>,
+    Path(comment_id): Path<i64>,
+    cursor: Option<Cursor>,
+    Json(req): Json<ApiRequest<ListCommentsRequest>>,
+) -> Result<Json<RepliesPage>> {
+    let (site, user) = req.extract_verified(&db).await?;
+
+    // Deleted comments still list their replies under a tombstone
+    let parent = match comments::find_on_site(&db, &site.site, comment_id).await {
+        Err(sqlx::Error::RowNotFound) => comments::find_deleted_on_site(&db, &site.site, comment_id).await?,
+        result => result?,
+    };
+    let page = pages::find(&db, parent.page_id).await?;
+
+    verify_read_permission(&site, &user, Some(&page))?;
+
+    let show_only_reviewed = user
+        .as_ref()
+        .is_none_or(|u| !u.moderator);
+    let token = req.payload.as_ref().map_or(&None, |p| &p.token);
+    let owner = Owner {
+        token: token.as_ref(),
+        name: user.as_ref().map(|u| u.name.as_str()),
+    };
+
+    if show_only_reviewed && !owner.can_see(&parent) {
+        return Err(Error::NotFound)
+    }
+
+    let sort = sort_order(&site, req.payload.as_ref().and_then(|p| p.sort), &cursor);
+
+    let (total, mut direct) = comments::replies_page(
+        &db,
+        &parent,
+        show_only_reviewed,
+        owner,
+        cursor,
+        sort,
+        REPLIES_PER_PAGE + 1,
+    ).await?;
+
+    let cursor = next_cursor(&db, &direct, REPLIES_PER_PAGE, sort).await?;
+    direct.truncate(REPLIES_PER_PAGE as usize);
+
+    let direct_ids: Vec<i64> = direct.iter().map(|c| c.id).collect();
+    let (nested, reply_counts) = comments::replies(
+        &db,
+        page.id,
+        show_only_reviewed,
+        owner,
+        &direct_ids,
+        sort,
+        REPLIES_PER_COMMENT,
+    ).await?;
+
+    let ids: Vec<i64> = direct.iter().chain(nested.iter()).map(|c| c.id).collect();
+    let reactor = reactor(&user, token);
+    let reactions = reactions::for_comments(&db, &ids, reactor.as_deref()).await?;
+
+    let replies = direct
+        .into_iter()
+        .filter_map(|r| reply_with_replies(r, &nested, token, &reactions, &reply_counts))
+        .collect();
+
+    Ok(Json(RepliesPage { total, cursor, replies }))
+}
+
+#[derive(Serialize)]
+pub(super) struct UnreviewedComment {
+    pub(super) id: i64,
     parent_id: Option<i64>,
     name: String,
     html_body: String,
@@ -226,37 +448,22 @@ struct UnreviewedComment {
     owned: bool,
     edited: bool,
     reviewed: bool,
+    /// Taken back for review after it was published
+    hidden: bool,
     page_path: String,
     page_title: Option<String>,
 }
 
-/// POST /api/comments/unreviewed
-async fn unreviewed(
-    State(db): State<SqlitePool>,
-    Json(req): Json<ApiRequest<ListCommentsRequest>>,
-) -> Result<Json<Vec<UnreviewedComment>>> {
-    let (site, user) = req.extract_verified(&db).await?;
-
-    require_moderator(&user)?;
-
-    let mut results = vec![];
-
-    let token = req.payload.as_ref().map_or(&None, |p| &p.token);
-
-    let unreviewed_comments = comments::unreviewed(&db, &site).await?;
-
-    let pages = pages::find_all(&db, unreviewed_comments.iter().map(|c| c.page_id).collect()).await?;
-
-    for comment in unreviewed_comments {
+impl UnreviewedComment {
+    pub(super) fn new(comment: Comment, page: &Page, token: &Option<Base64>) -> Self {
         let owned = match &token {
             None => false,
             Some(t) => t == &comment.token
         };
 
         let edited = comment.updated_at == comment.created_at;
-        let page = pages.iter().find(|p| p.id == comment.page_id).unwrap();
 
-        results.push(UnreviewedComment {
+        Self {
             id: comment.id,
             parent_id: comment.parent_id,
             name: comment.name,
@@ -270,10 +477,35 @@ async fn unreviewed(
             owned,
             edited,
             reviewed: comment.reviewed,
+            hidden: comment.hidden,
             page_path: page.path.clone(),
             page_title: page.title.clone(),
-        });
+        }
     }
+}
+
+/// POST /api/comments/unreviewed
+async fn unreviewed(
+    State(db): State<SqlitePool>,
+    Json(req): Json<ApiRequest<ListCommentsRequest>>,
+) -> Result<Json<Vec<UnreviewedComment>>> {
+    let (site, user) = req.extract_verified(&db).await?;
+
+    require_role(&user, Role::Approver)?;
+
+    let token = req.payload.as_ref().map_or(&None, |p| &p.token);
+
+    let unreviewed_comments = comments::unreviewed(&db, &site).await?;
+
+    let pages = pages::find_all(&db, unreviewed_comments.iter().map(|c| c.page_id).collect()).await?;
+
+    let results = unreviewed_comments
+        .into_iter()
+        .map(|comment| {
+            let page = pages.iter().find(|p| p.id == comment.page_id).unwrap();
+            UnreviewedComment::new(comment, page, token)
+        })
+        .collect();
 
     Ok(Json(results))
 }
@@ -285,54 +517,120 @@ struct PostCommentResponse {
 }
 /// POST /api/comment
 async fn create(
-    State(db): State<SqlitePool>,
+    State(state): State<AppState>,
+    client: ClientInfo,
     Json(req): Json<ApiRequest<CommentData>>,
 ) -> Result<Json<PostCommentResponse>> {
-    post_comment(&db, req, None).await
+    post_comment(&state, &client, req, None).await
 }
 
 /// POST /api/comment/42
 async fn reply(
-    State(db): State<SqlitePool>,
+    State(state): State<AppState>,
+    client: ClientInfo,
     Path(comment_id): Path<i64>,
     Json(req): Json<ApiRequest<CommentData>>,
 ) -> Result<Json<PostCommentResponse>> {
-    post_comment(&db, req, Some(comment_id)).await
+    post_comment(&state, &client, req, Some(comment_id)).await
 }
 
-fn authorize_posting(site: &Site, user: &Option<User>, page: &Page) -> Result<()> {
+/// Checks whether the user can post to the page and returns whether
+/// they are shadow banned, in which case only they see their comments
+async fn authorize_posting(
+    db: &SqlitePool,
+    site: &Site,
+    user: &Option<User>,
+    page: &Page,
+    ip: IpAddr,
+    token: &Option<Base64>,
+) -> Result<bool> {
     verify_read_permission(site, user, Some(page))?;
     if user.is_none() && !site.anonymous { return Err(Error::Unauthorized) }
     if page.locked { return Err(Error::Forbidden) }
-    Ok(())
+
+    // moderators can't be banned
+    if user.as_ref().is_some_and(|u| u.moderator) { return Ok(false) }
+
+    let name = user.as_ref().map(|u| u.name.as_str());
+    let token = token.as_ref().map(|t| STANDARD.encode(&t.0));
+    let bans = bans::active(db, &site.site).await?;
+    let (shadow, banned): (Vec<_>, Vec<_>) = bans
+        .iter()
+        .filter(|ban| ban.matches(name, token.as_deref(), ip))
+        .partition(|ban| ban.shadow);
+
+    if !banned.is_empty() { return Err(Error::Forbidden) }
+
+    Ok(!shadow.is_empty())
 }
 
-fn get_markdown(data: &str) -> Result<String> {
+pub(crate) fn get_markdown(data: &str) -> Result<String> {
     markdown::to_html_with_options(data, &markdown::Options::gfm())
         .map_err(|_| Error::UnprocessableEntity("Your comment contains invalid markdown"))
 }
 
 
+/// Queues emails about a comment: moderators are told about comments
+/// waiting for review, and authors of comments about visible replies
+async fn notify(db: &SqlitePool, notifier: &Notifier, site: &Site, comment: &Comment) -> Result<()> {
+    // nobody hears about comments only their author can see
+    if comment.shadowed { return Ok(()) }
+
+    let page = pages::find(db, comment.page_id).await?;
+
+    if !comment.reviewed {
+        notifier.unreviewed_comment(db, site, &page, comment).await?;
+    } else if let Some(pid) = comment.parent_id.filter(|_| !comment.hidden) {
+        // replies to deleted comments notify nobody, and
+        // hidden ones were notified about when first published
+        if let Ok(parent) = comments::find(db, pid).await {
+            notifier.reply(db, site, &page, &parent, comment).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends an event about a comment to the site's webhooks
+async fn comment_event(db: &SqlitePool, webhooks: &Webhooks, site: &Site, event: Event, comment: &Comment) -> Result<()> {
+    if comment.shadowed { return Ok(()) }
+
+    let page = pages::find(db, comment.page_id).await?;
+    webhooks.comment(db, site, event, &page, comment).await?;
+    Ok(())
+}
+
 async fn post_comment(
-    db: &SqlitePool,
+    state: &AppState,
+    client: &ClientInfo,
     req: ApiRequest<CommentData>,
     parent_id: Option<i64>
 ) -> Result<Json<PostCommentResponse>> {
+    let db = &state.db;
     match req.payload {
         None => Err(Error::UnprocessableEntity("Payload can't be blank")),
         Some(ref data) => {
             if data.body.trim().is_empty() { return Err(Error::UnprocessableEntity("Comment can't be blank")) }
 
+            let email = data.email.as_deref().map(str::trim).filter(|e| !e.is_empty());
+            if email.is_some_and(|e| e.parse::<lettre::Address>().is_err()) {
+                return Err(Error::UnprocessableEntity("Invalid email address"))
+            }
+
             let (site, user) = req.extract_verified(db).await?;
-            let page = match parent_id {
-                None => pages::create_or_find_by_site_and_path(db, &req.site, &req.path, &req.title).await?,
+            let (page, depth) = match parent_id {
+                None => (pages::create_or_find_by_site_and_path(db, &req.site, &req.path, &req.title).await?, 0),
                 Some(pid) => {
-                    let parent = comments::find_root(db, pid).await?;
-                    pages::find(db, parent.page_id).await?
+                    let parent = comments::find_on_site(db, &site.site, pid).await?;
+                    (pages::find(db, parent.page_id).await?, comments::depth(db, pid).await? + 1)
                 }
             };
 
-            authorize_posting(&site, &user, &page)?;
+            let shadowed = authorize_posting(db, &site, &user, &page, client.ip, &data.token).await?;
+
+            if depth > site.max_depth {
+                return Err(Error::UnprocessableEntity("Replies can't be nested any deeper"))
+            }
 
             // Use the api user name (could be anonymous)
             // or set the name to Anonymous
@@ -347,7 +645,34 @@ async fn post_comment(
             // Auto review if the user is a moderator or an op or moderation is disabled
             let op = user.is_some() && user.as_ref().unwrap().op;
             let moderator = user.is_some() && user.as_ref().unwrap().moderator;
-            let reviewed = !site.moderated || op || moderator;
+            let mut reviewed = !site.moderated || op || moderator;
+
+            let html_body = get_markdown(&data.body)?;
+
+            // clients over the limit don't get to cost an Akismet request
+            state.limiter.ensure_allowed("comment", &site, &user, client.ip, &data.token)?;
+
+            if !op && !moderator {
+                let submission = Submission {
+                    client,
+                    path: &page.path,
+                    reply: parent_id.is_some(),
+                    name,
+                    body: &data.body,
+                    html_body: &html_body,
+                    honeypot: data.website.as_deref(),
+                    time_to_submit: data.time_to_submit,
+                };
+                match spam::check(&spam::filters(&site), &submission).await {
+                    Verdict::Reject(reason) => return Err(Error::UnprocessableEntity(reason)),
+                    Verdict::Review => reviewed = false,
+                    Verdict::Pass => (),
+                }
+            }
+
+            // rejected comments don't count against the limits,
+            // so the hit is only recorded once the filters pass
+            state.limiter.limit("comment", &site, &user, client.ip, &data.token)?;
 
             let comment = comments::create(
                 db,
@@ -355,37 +680,36 @@ async fn post_comment(
                 parent_id,
                 &avatar,
                 &name,
-                &get_markdown(&data.body)?,
+                &html_body,
                 &data.body,
                 reviewed,
                 op,
                 moderator,
                 data.token.as_ref().unwrap_or(&generate_random_token()),
+                shadowed,
+                user.as_ref().map(|u| u.name.as_str()),
             ).await?;
 
-            let owned = match &data.token {
-                None => false,
-                Some(t) => t == &comment.token
-            };
+            // keep what Akismet needs to learn from moderators' decisions
+            if Akismet::for_site(&site).is_some() {
+                comments::save_metadata(db, &Metadata {
+                    comment_id: comment.id,
+                    ip: client.ip.to_string(),
+                    user_agent: client.user_agent.clone(),
+                    referrer: client.referrer.clone(),
+                }).await?;
+            }
+
+            if let Some(email) = email.filter(|_| site.reply_notifications) {
+                emails::subscribe(db, comment.id, email).await?;
+            }
+            notify(db, &state.notifier, &site, &comment).await?;
+            comment_event(db, &state.webhooks, &site, Event::CommentCreated, &comment).await?;
 
             Ok(Json({
                 PostCommentResponse {
-                    token: comment.token,
-                    comment: OwnedComment {
-                        id: comment.id,
-                        parent_id: comment.parent_id,
-                        html_body: comment.html_body,
-                        name: comment.name,
-                        body: comment.body,
-                        avatar: comment.avatar,
-                        created_at: comment.created_at,
-                        updated_at: comment.updated_at,
-                        reviewed: comment.reviewed,
-                        edited: comment.created_at != comment.updated_at,
-                        op: comment.op,
-                        moderator: comment.moderator,
-                        owned,
-                    }
+                    token: comment.token.clone(),
+                    comment: OwnedComment::new(comment, &data.token, Reactions::default(), 0, vec![]),
                 }
             }))
         }
@@ -394,7 +718,7 @@ async fn post_comment(
 
 fn ensure_modifiable(user: Option<&User>, token: Option<&Base64>, comment: &Comment) -> Result<()> {
     match user {
-        Some(u) if u.moderator => Ok(()),
+        Some(u) if u.can(Role::Editor) => Ok(()),
         _ => match token {
             None => Err(Error::Forbidden),
             Some(t) => {
@@ -409,6 +733,36 @@ fn ensure_modifiable(user: Option<&User>, token: Option<&Base64>, comment: &Comm
     }
 }
 
+/// Records a moderating action taken on a comment. Comment authors
+/// modifying their own comments with a token are recorded by name
+async fn record_event(
+    db: &SqlitePool,
+    site: &Site,
+    user: Option<&User>,
+    action: Action,
+    comment: &Comment,
+    body_after: Option<&str>,
+) -> Result<()> {
+    let (actor, moderator) = match user {
+        Some(u) => (u.name.as_str(), u.moderator),
+        None => (comment.name.as_str(), false),
+    };
+
+    audit::record(db, NewEvent {
+        site: &site.site,
+        actor,
+        moderator,
+        action,
+        comment_id: Some(comment.id),
+        page_id: Some(comment.page_id),
+        target: None,
+        body_before: Some(&comment.body),
+        body_after,
+    }).await?;
+
+    Ok(())
+}
+
 #[derive(Serialize)]
 struct UpdateCommentResponse {
     body: String,
@@ -417,6 +771,7 @@ struct UpdateCommentResponse {
 /// PUT /api/comment/42
 async fn update(
     State(db): State<SqlitePool>,
+    State(webhooks): State<Webhooks>,
     Path(comment_id): Path<i64>,
     Json(req): Json<ApiRequest<CommentData>>,
 ) -> Result<Json<UpdateCommentResponse>> {
@@ -425,9 +780,9 @@ async fn update(
         Some(ref data) => {
             if data.body.trim().is_empty() { return Err(Error::UnprocessableEntity("Comment can't be blank")) }
 
-            let (_, user) = req.extract_verified(&db).await?;
+            let (site, user) = req.extract_verified(&db).await?;
 
-            let comment = comments::find(&db, comment_id).await?;
+            let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
 
             ensure_modifiable(
                 user.as_ref(),
@@ -437,6 +792,9 @@ async fn update(
 
             let updated_comment = comments::update(&db, comment_id, &get_markdown(&data.body)?, &data.body).await?;
 
+            record_event(&db, &site, user.as_ref(), Action::Update, &comment, Some(&updated_comment.body)).await?;
+            comment_event(&db, &webhooks, &site, Event::CommentUpdated, &updated_comment).await?;
+
             Ok(
                 Json(UpdateCommentResponse {
                     html_body: updated_comment.html_body,
@@ -450,26 +808,70 @@ async fn update(
 /// PATCH /api/comment/42
 async fn approve(
     State(db): State<SqlitePool>,
+    State(notifier): State<Notifier>,
+    State(webhooks): State<Webhooks>,
     Path(comment_id): Path<i64>,
     Json(req): Json<ApiRequest<()>>,
 ) -> Result<String> {
-    let (_, user) = req.extract_verified(&db).await?;
-    require_moderator(&user)?;
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Approver)?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
 
     comments::approve(&db, comment_id).await?;
 
+    record_event(&db, &site, user.as_ref(), Action::Approve, &comment, None).await?;
+    if !comment.reviewed {
+        let approved = Comment { reviewed: true, ..comment };
+        notify(&db, &notifier, &site, &approved).await?;
+        comment_event(&db, &webhooks, &site, Event::CommentApproved, &approved).await?;
+    }
+
+    Ok("Success".to_string())
+}
+
+#[derive(Deserialize, Default)]
+struct UnapproveData {
+    /// Hide the comment from its author as well
+    #[serde(default)]
+    from_author: bool,
+}
+
+/// POST /api/comment/42/unapprove
+async fn unapprove(
+    State(db): State<SqlitePool>,
+    State(webhooks): State<Webhooks>,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<UnapproveData>>,
+) -> Result<String> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Approver)?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
+    if !comment.reviewed && !comment.hidden { return Err(Error::UnprocessableEntity("Comment hasn't been published")) }
+
+    let data = req.payload.unwrap_or_default();
+    comments::unapprove(&db, comment_id, data.from_author).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Unapprove, &comment, None).await?;
+    if comment.reviewed {
+        let hidden = Comment { reviewed: false, hidden: true, ..comment };
+        comment_event(&db, &webhooks, &site, Event::CommentUnapproved, &hidden).await?;
+    }
+
     Ok("Success".to_string())
 }
 
 /// DELETE /api/comment/42
 async fn destroy(
     State(db): State<SqlitePool>,
+    State(webhooks): State<Webhooks>,
     Path(comment_id): Path<i64>,
     Json(req): Json<ApiRequest<Base64>>,
 ) -> Result<String> {
-    let comment = comments::find(&db, comment_id).await?;
+    let (site, user) = req.extract_verified(&db).await?;
 
-    let (_, user) = req.extract_verified(&db).await?;
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
 
     ensure_modifiable(
         user.as_ref(),
@@ -478,6 +880,214 @@ async fn destroy(
     )?;
 
     let _ = comments::delete(&db, comment_id).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Delete, &comment, None).await?;
+    comment_event(&db, &webhooks, &site, Event::CommentDeleted, &comment).await?;
+    Ok("Success".to_string())
+}
+
+/// POST /api/comment/42/restore
+async fn restore(
+    State(db): State<SqlitePool>,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<String> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Editor)?;
+
+    let comment = comments::find_deleted_on_site(&db, &site.site, comment_id).await?;
+
+    comments::restore(&db, comment_id).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Restore, &comment, None).await?;
+
     Ok("Success".to_string())
 }
 
+const MAX_BULK_COMMENTS: usize = 100;
+
+#[derive(Deserialize)]
+struct BulkModerationRequest {
+    ids: Vec<i64>,
+    action: BulkAction,
+}
+
+#[derive(Serialize)]
+struct BulkModerationResult {
+    id: i64,
+    outcome: Outcome,
+}
+
+/// POST /api/comments/moderate
+async fn moderate(
+    State(db): State<SqlitePool>,
+    State(notifier): State<Notifier>,
+    State(webhooks): State<Webhooks>,
+    Json(req): Json<ApiRequest<BulkModerationRequest>>,
+) -> Result<Json<Vec<BulkModerationResult>>> {
+    let (site, user) = req.extract_verified(&db).await?;
+    let Some(data) = req.payload else { return Err(Error::UnprocessableEntity("Payload can't be blank")) };
+
+    let role = match data.action {
+        BulkAction::Approve | BulkAction::Unapprove => Role::Approver,
+        BulkAction::Delete => Role::Editor,
+    };
+    require_role(&user, role)?;
+    let Some(u) = user else { return Err(Error::Unauthorized) };
+
+    if data.ids.len() > MAX_BULK_COMMENTS { return Err(Error::UnprocessableEntity("Too many comments")) }
+
+    let actor = Actor { name: &u.name, moderator: u.moderator };
+    let moderated = comments::moderate(&db, &site.site, &data.ids, data.action, actor).await?;
+
+    // notifications and webhooks go out once the changes are saved
+    let mut results = vec![];
+    for m in moderated {
+        if let (Outcome::Done, Some(comment)) = (m.outcome, m.comment) {
+            match data.action {
+                BulkAction::Approve => {
+                    let approved = Comment { reviewed: true, ..comment };
+                    notify(&db, &notifier, &site, &approved).await?;
+                    comment_event(&db, &webhooks, &site, Event::CommentApproved, &approved).await?;
+                },
+                BulkAction::Delete => comment_event(&db, &webhooks, &site, Event::CommentDeleted, &comment).await?,
+                // comments which readers could see until now
+                BulkAction::Unapprove if comment.reviewed => {
+                    let hidden = Comment { reviewed: false, hidden: true, ..comment };
+                    comment_event(&db, &webhooks, &site, Event::CommentUnapproved, &hidden).await?;
+                },
+                BulkAction::Unapprove => {},
+            }
+        }
+
+        results.push(BulkModerationResult { id: m.id, outcome: m.outcome });
+    }
+
+    Ok(Json(results))
+}
+
+/// Queues a moderator's decision about a comment to be sent back to Akismet
+async fn report_to_akismet(db: &SqlitePool, feedback: &Feedback, site: &Site, comment: &Comment, spam: bool) -> Result<()> {
+    let Some(akismet) = Akismet::for_site(site) else { return Ok(()) };
+    // comments posted before Akismet was enabled can't be reported
+    let Ok(metadata) = comments::metadata(db, comment.id).await else { return Ok(()) };
+    let page = pages::find(db, comment.page_id).await?;
+
+    let reported = AkismetComment {
+        ip: metadata.ip,
+        user_agent: metadata.user_agent,
+        referrer: metadata.referrer,
+        permalink: akismet.permalink(&page.path),
+        reply: comment.parent_id.is_some(),
+        author: comment.name.clone(),
+        content: comment.body.clone(),
+        created_at: comment.created_at,
+    };
+
+    feedback.submit(db, site, &reported, spam).await?;
+
+    Ok(())
+}
+
+/// POST /api/comment/42/spam
+async fn spam(
+    State(db): State<SqlitePool>,
+    State(webhooks): State<Webhooks>,
+    State(feedback): State<Feedback>,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<String> {
+    let (site, user) = req.extract_verified(&db).await?;
+    // spam is deleted, which is up to editors
+    require_role(&user, Role::Editor)?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
+
+    comments::delete(&db, comment_id).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Spam, &comment, None).await?;
+    comment_event(&db, &webhooks, &site, Event::CommentDeleted, &comment).await?;
+    report_to_akismet(&db, &feedback, &site, &comment, true).await?;
+
+    Ok("Success".to_string())
+}
+
+/// POST /api/comment/42/ham
+async fn ham(
+    State(db): State<SqlitePool>,
+    State(notifier): State<Notifier>,
+    State(webhooks): State<Webhooks>,
+    State(feedback): State<Feedback>,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<String> {
+    let (site, user) = req.extract_verified(&db).await?;
+    require_role(&user, Role::Approver)?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
+
+    comments::approve(&db, comment_id).await?;
+
+    record_event(&db, &site, user.as_ref(), Action::Ham, &comment, None).await?;
+    report_to_akismet(&db, &feedback, &site, &comment, false).await?;
+    if !comment.reviewed {
+        let approved = Comment { reviewed: true, ..comment };
+        notify(&db, &notifier, &site, &approved).await?;
+        comment_event(&db, &webhooks, &site, Event::CommentApproved, &approved).await?;
+    }
+
+    Ok("Success".to_string())
+}
+
+#[derive(Serialize)]
+struct CommentRevision {
+    body: String,
+    html_body: String,
+    reviewed: bool,
+    created_at: DateTime<Utc>,
+    diff: Option<String>,
+}
+
+/// POST /api/comment/42/revisions
+async fn revisions(
+    State(db): State<SqlitePool>,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<Json<Vec<CommentRevision>>> {
+    let (site, user) = req.extract_verified(&db).await?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
+    let page = pages::find(&db, comment.page_id).await?;
+
+    verify_read_permission(&site, &user, Some(&page))?;
+
+    let moderator = user.as_ref().is_some_and(|u| u.moderator);
+    if !moderator {
+        if !site.public_revisions { require_role(&user, Role::Approver)? }
+        if !comment.reviewed { return Err(Error::NotFound) }
+    }
+
+    let mut versions: Vec<(String, String, bool, DateTime<Utc>)> = comments::revisions(&db, comment_id)
+        .await?
+        .into_iter()
+        .map(|r| (r.body, r.html_body, r.reviewed, r.created_at))
+        .collect();
+
+    // the current version is always the last one
+    versions.push((comment.body, comment.html_body, comment.reviewed, comment.updated_at));
+
+    let mut results: Vec<CommentRevision> = vec![];
+
+    for (body, html_body, reviewed, created_at) in versions {
+        let diff = results.last().map(|previous| {
+            TextDiff::from_lines(&previous.body, &body)
+                .unified_diff()
+                .header("previous", "current")
+                .to_string()
+        });
+
+        results.push(CommentRevision { body, html_body, reviewed, created_at, diff });
+    }
+
+    Ok(Json(results))
+}
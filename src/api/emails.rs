@@ -0,0 +1,37 @@
+use axum::{extract::{Query, State}, routing::get, Router};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::{db::{emails, sites}, notifications::verify_unsubscribe_signature};
+
+use super::{AppState, Error, Result};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/api/unsubscribe", get(unsubscribe))
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeParams {
+    site: String,
+    email: String,
+    signature: String,
+}
+
+/// GET /api/unsubscribe?site=example.com&email=...&signature=...
+///
+/// Followed from links in reply notifications, so it's a GET
+/// and answers in plain text rather than JSON
+async fn unsubscribe(
+    State(db): State<SqlitePool>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Result<String> {
+    let site = sites::find(&db, &params.site).await?;
+
+    if !verify_unsubscribe_signature(&site, &params.email, &params.signature) {
+        return Err(Error::Forbidden)
+    }
+
+    emails::unsubscribe(&db, &site.site, &params.email).await?;
+
+    Ok(format!("{} will no longer receive emails from {}", params.email, site.site))
+}
@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse
 };
 
@@ -11,6 +11,10 @@ pub enum Error {
     /// Use for 401 responses
     #[error("Authentication required")]
     Unauthorized,
+    /// Use for 401 responses to logins with the right password
+    /// which still need a two-factor authentication code
+    #[error("Two-factor authentication code required")]
+    SecondFactorRequired,
     /// Use for 403 responses
     #[error("You are not allowed to perform this action")]
     Forbidden,
@@ -41,19 +45,28 @@ pub enum Error {
     #[error("{}", .0)]
     Json(#[from] serde_json::Error),
     #[error("{}", .0)]
-    BadRequest(&'static str)
+    BadRequest(&'static str),
+    /// Use for 429 responses, holds the number of
+    /// seconds after which the client can try again
+    #[error("You are posting too often, please try again later")]
+    TooManyRequests(u64),
+    /// Use for 429 responses to logins which are locked out
+    /// after failing, holds the number of seconds to wait
+    #[error("Too many failed login attempts, please try again later")]
+    TooManyLoginAttempts(u64),
 }
 
 impl Error {
     fn status_code(&self) -> StatusCode {
         match self {
-            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Unauthorized | Self::SecondFactorRequired => StatusCode::UNAUTHORIZED,
             Self::Forbidden => StatusCode::FORBIDDEN,
             Self::NotFound => StatusCode::NOT_FOUND,
             Self::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
             Self::Json(_) => StatusCode::BAD_REQUEST,
+            Self::TooManyRequests(_) | Self::TooManyLoginAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
             Self::Sqlx(e) => {
                 match e {
                     sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
@@ -75,6 +88,14 @@ impl IntoResponse for Error {
                 tracing::error!("Generic error: {:?}", e);
             },
 
+            Self::TooManyRequests(retry_after) | Self::TooManyLoginAttempts(retry_after) => {
+                return (
+                    self.status_code(),
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    self.to_string(),
+                ).into_response()
+            },
+
             _ => (),
         }
 
@@ -1,16 +1,16 @@
 use anyhow::anyhow;
 use axum::{
     async_trait,
-    extract::{FromRequestParts, Query},
-    http::{request::Parts, StatusCode},
+    extract::{ConnectInfo, FromRequestParts, Query},
+    http::{header, request::Parts, StatusCode},
     response::{IntoResponse, Response},
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, net::SocketAddr};
 use base64::Engine;
 
 use crate::api::{Error, Result};
 
-use super::Cursor;
+use super::{AppState, ClientInfo, Cursor};
 
 #[async_trait]
 impl<T: Send + Sync> FromRequestParts<T> for Cursor {
@@ -36,3 +36,31 @@ impl<T: Send + Sync> FromRequestParts<T> for Cursor {
         }
     }
 }
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(addr) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| err.into_response())?;
+
+        let header = |name| parts.headers
+            .get(name)
+            .and_then(|v: &header::HeaderValue| v.to_str().ok())
+            .map(String::from);
+
+        // the proxy appends the address it got the request from,
+        // anything before it could have been sent by the client
+        let forwarded = header(header::HeaderName::from_static("x-forwarded-for"))
+            .filter(|_| state.behind_proxy)
+            .and_then(|v| v.rsplit(',').next().and_then(|ip| ip.trim().parse().ok()));
+
+        Ok(ClientInfo {
+            ip: forwarded.unwrap_or(addr.ip()),
+            user_agent: header(header::USER_AGENT),
+            referrer: header(header::REFERER),
+        })
+    }
+}
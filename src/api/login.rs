@@ -1,31 +1,144 @@
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::{Json, Router, routing::post, extract::State};
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
 use crate::{
-    db::moderators::{find_by_name, Moderator},
-    api::{Error, AppState, Result},
+    db::{moderators::{self, find_by_name, Moderator}, sessions, login_failures},
+    api::{Base64, ClientInfo, Error, AppState, Result},
+    totp,
 };
 
-use super::generate_random_token;
+use super::{generate_random_token, session_lifetime};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/api/login", post(login))
+        .route("/api/logout", post(logout))
 }
 
 #[derive(Deserialize)]
 struct LoginRequest {
     name: String,
     password: String,
+    /// Site the moderator is logging in to
+    site: Option<String>,
+    /// Two-factor authentication code or a recovery code,
+    /// for moderators who have two-factor authentication enabled
+    code: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LoginResponse {
+    #[serde(flatten)]
+    moderator: Moderator,
+    /// Identifies the new session in later requests
+    sid: Base64,
+}
+
+/// Failed attempts allowed before a moderator name gets locked out
+const FREE_ATTEMPTS_PER_NAME: i64 = 3;
+/// IP addresses can be shared, so they get more attempts
+const FREE_ATTEMPTS_PER_IP: i64 = 10;
+/// Lockouts double with each failure up to this many seconds
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+
+/// Failure counters are forgotten after this long without failures
+fn failures_reset_after() -> chrono::Duration {
+    chrono::Duration::days(1)
+}
+
+/// IP addresses moderators logged in from are forgotten after this long without logins
+fn logins_forgotten_after() -> chrono::Duration {
+    chrono::Duration::days(90)
+}
+
+/// How long to lock out after the given number of failures
+fn lockout(failures: i64, free_attempts: i64) -> Option<chrono::Duration> {
+    if failures <= free_attempts { return None }
+
+    let exponent = (failures - free_attempts).min(32) as u32;
+    Some(chrono::Duration::seconds(2_i64.pow(exponent).min(MAX_LOCKOUT_SECONDS)))
 }
 
 async fn login(
     State(db): State<SqlitePool>,
+    client: ClientInfo,
     Json(req): Json<LoginRequest>,
-) -> Result<Json<Moderator>> {
-    let mut moderator = find_by_name(&db, &req.name)
+) -> Result<Json<LoginResponse>> {
+    let ip = client.ip.to_string();
+    let now = Utc::now();
+
+    login_failures::delete_stale(&db, now - failures_reset_after()).await?;
+
+    // Moderators aren't locked out of the IP addresses they logged in from
+    // before, or anyone could lock them out by failing to log in as them
+    let mut keys = vec![("ip", ip.as_str(), FREE_ATTEMPTS_PER_IP)];
+    if !moderators::logged_in_from(&db, &req.name, &ip).await? {
+        keys.push(("name", req.name.as_str(), FREE_ATTEMPTS_PER_NAME));
+    }
+
+    // Attempts are counted before they are checked, and each one past the
+    // free attempts has to take the lockout for itself, so that attempts
+    // made at the same time can't all get past it
+    for &(kind, value, free_attempts) in &keys {
+        let attempt = login_failures::record(&db, kind, value, now - failures_reset_after()).await?;
+        if let Some(until) = attempt.locked_until.filter(|until| *until > now) {
+            return Err(locked_out(until, now));
+        }
+
+        if let Some(duration) = lockout(attempt.failures, free_attempts) {
+            tracing::warn!("Locking out {} {} for {}s after {} failed logins", kind, value, duration.num_seconds(), attempt.failures - 1);
+            if !login_failures::lock(&db, kind, value, now + duration).await? {
+                return Err(locked_out(now + duration, now));
+            }
+        }
+    }
+
+    let moderator = match authenticate(&db, &req).await {
+        Ok(moderator) => moderator,
+        Err(Error::Unauthorized) => {
+            tracing::warn!("Failed login attempt for moderator {} from {}", req.name, ip);
+            return Err(Error::Unauthorized);
+        },
+        // the right password isn't a failure, the code is counted when it comes
+        Err(Error::SecondFactorRequired) => {
+            for (kind, value, _) in keys {
+                login_failures::forgive(&db, kind, value).await?;
+            }
+            return Err(Error::SecondFactorRequired);
+        },
+        Err(e) => return Err(e),
+    };
+
+    // several moderators can log in from behind the same address
+    login_failures::clear(&db, "name", &moderator.name).await?;
+    login_failures::clear(&db, "ip", &ip).await?;
+    moderators::record_login(&db, &moderator.name, &ip).await?;
+    moderators::forget_logins(&db, now - logins_forgotten_after()).await?;
+
+    if let Some(ref site) = req.site {
+        if !moderators::moderates(&db, &moderator, site).await? { return Err(Error::Forbidden) }
+    }
+
+    sessions::delete_expired(&db).await?;
+
+    let sid = generate_random_token();
+    sessions::create(&db, &moderator.name, &sid, client.user_agent.as_deref(), session_lifetime()).await?;
+
+    Ok(Json(LoginResponse { moderator, sid }))
+}
+
+fn locked_out(until: DateTime<Utc>, now: DateTime<Utc>) -> Error {
+    let retry_after = (until - now).num_seconds().max(0) as u64 + 1;
+    Error::TooManyLoginAttempts(retry_after)
+}
+
+/// Checks the name, password and the second factor if the moderator has
+/// one. A missing code is SecondFactorRequired, any failure is Unauthorized
+async fn authenticate(db: &SqlitePool, req: &LoginRequest) -> Result<Moderator> {
+    let moderator = find_by_name(db, &req.name)
         .await
         .map_err(|_| Error::Unauthorized)?;
 
@@ -36,8 +149,31 @@ async fn login(
         .verify_password(req.password.as_bytes(), &hash)
         .map_err(|_| Error::Unauthorized)?;
 
-    let sid = generate_random_token();
-    moderator.set_sid(&db, sid).await;
+    if let Some(ref secret) = moderator.totp_secret {
+        let Some(ref code) = req.code else { return Err(Error::SecondFactorRequired) };
+
+        let verified = match totp::verify(secret, code, Utc::now()) {
+            Some(step) => moderators::use_totp_step(db, &moderator.name, step).await?,
+            None => moderators::use_recovery_code(db, &moderator.name, code).await?,
+        };
+
+        if !verified { return Err(Error::Unauthorized) }
+    }
+
+    Ok(moderator)
+}
+
+#[derive(Deserialize)]
+struct LogoutRequest {
+    sid: Base64,
+}
+
+/// Ends the session, other devices stay logged in
+async fn logout(
+    State(db): State<SqlitePool>,
+    Json(req): Json<LogoutRequest>,
+) -> Result<String> {
+    sessions::delete_by_sid(&db, &req.sid).await?;
 
-    Ok(Json(moderator))
+    Ok("Success".to_string())
 }
@@ -1,8 +1,12 @@
-use crate::{api::{ApiRequest, AppState, Result}, db::pages::{create_or_find_by_site_and_path, self}};
+use crate::{
+    api::{ApiRequest, AppState, Result},
+    db::{pages::{create_or_find_by_site_and_path, self, Page}, audit::{self, Action, NewEvent}, comments::Sort},
+    webhooks::{Event, Webhooks},
+};
 use axum::{routing::patch, Json, Router, extract::State};
 use sqlx::SqlitePool;
 
-use super::{PageConfig, require_moderator};
+use super::{PageConfig, Role, require_role};
 
 pub fn router() -> Router<AppState> {
     Router::new().route("/api/pages", patch(toggle_lock))
@@ -10,19 +14,41 @@ pub fn router() -> Router<AppState> {
 
 async fn toggle_lock(
     State(db): State<SqlitePool>,
+    State(webhooks): State<Webhooks>,
     Json(req): Json<ApiRequest<()>>
 ) -> Result<Json<PageConfig>> {
     let (site, user) = req.extract_verified(&db).await?;
 
-    require_moderator(&user)?;
+    require_role(&user, Role::Admin)?;
 
     let page = create_or_find_by_site_and_path(&db, &req.site, &req.path, &req.title).await?;
 
     pages::toggle_lock(&db, page.id).await?;
 
+    if let Some(u) = &user {
+        audit::record(&db, NewEvent {
+            site: &site.site,
+            actor: &u.name,
+            moderator: u.moderator,
+            action: if page.locked { Action::Unlock } else { Action::Lock },
+            comment_id: None,
+            page_id: Some(page.id),
+            target: None,
+            body_before: None,
+            body_after: None,
+        }).await?;
+    }
+
+    let locked = !page.locked;
+    let event = if locked { Event::PageLocked } else { Event::PageUnlocked };
+    webhooks.page(&db, &site, event, &Page { locked, ..page }).await?;
+
     Ok(Json(PageConfig {
         anonymous: site.anonymous,
         moderated: site.moderated,
-        locked: !page.locked,
+        locked,
+        max_depth: site.max_depth,
+        reply_notifications: site.reply_notifications && !site.smtp_url.is_empty(),
+        default_sort: Sort::from(site.default_sort.as_str()),
     }))
 }
@@ -0,0 +1,132 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use crate::db::sites::Site;
+
+use super::{Base64, Error, User};
+
+/// How often keys without any unexpired hits are forgotten
+const SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Keeps track of recently posted comments in memory. Every key
+/// (e.g. an IP address or a comment token) holds the times at
+/// which its hits expire, oldest first
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    hits: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+}
+
+impl RateLimiter {
+    /// Records a hit for all keys unless any of them has reached the limit
+    /// within the window, in which case nothing is recorded and the time
+    /// until the oldest hit of the busiest key expires is returned
+    pub fn hit(&self, keys: &[String], limit: usize, window: Duration) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+
+        if let Some(wait) = retry_after(&mut hits, keys, limit, now) { return Err(wait) }
+
+        for key in keys {
+            hits.entry(key.clone()).or_default().push_back(now + window);
+        }
+
+        Ok(())
+    }
+
+    /// Tells whether any of the keys has reached the limit without recording a hit
+    pub fn check(&self, keys: &[String], limit: usize) -> Result<(), Duration> {
+        let mut hits = self.hits.lock().unwrap();
+
+        match retry_after(&mut hits, keys, limit, Instant::now()) {
+            Some(wait) => Err(wait),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts an action, e.g. posting a comment, against the rate limits of
+    /// the site for the client's IP, token and signed user name. Every
+    /// action is limited separately, and moderators are never limited
+    pub(super) fn limit(
+        &self,
+        action: &str,
+        site: &Site,
+        user: &Option<User>,
+        ip: IpAddr,
+        token: &Option<Base64>,
+    ) -> super::Result<()> {
+        if site.rate_limit == 0 || user.as_ref().is_some_and(|u| u.moderator) { return Ok(()) }
+
+        let window = Duration::from_secs(site.rate_limit_window as u64);
+        self.hit(&keys(action, site, user, ip, token), site.rate_limit as usize, window)
+            .map_err(|wait| Error::TooManyRequests(wait.as_secs() + 1))
+    }
+
+    /// Rejects a client which has already reached the limits of an action
+    /// without counting the attempt, so that expensive checks can be skipped
+    pub(super) fn ensure_allowed(
+        &self,
+        action: &str,
+        site: &Site,
+        user: &Option<User>,
+        ip: IpAddr,
+        token: &Option<Base64>,
+    ) -> super::Result<()> {
+        if site.rate_limit == 0 || user.as_ref().is_some_and(|u| u.moderator) { return Ok(()) }
+
+        self.check(&keys(action, site, user, ip, token), site.rate_limit as usize)
+            .map_err(|wait| Error::TooManyRequests(wait.as_secs() + 1))
+    }
+
+    /// Periodically forgets about clients who haven't posted in a while
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            self.hits.lock().unwrap().retain(|_, expiries| {
+                prune(expiries, now);
+                !expiries.is_empty()
+            });
+        }
+    }
+}
+
+fn keys(action: &str, site: &Site, user: &Option<User>, ip: IpAddr, token: &Option<Base64>) -> Vec<String> {
+    let mut keys = vec![format!("{}:{}:ip:{}", site.site, action, ip)];
+    if let Some(t) = token { keys.push(format!("{}:{}:token:{}", site.site, action, STANDARD.encode(&t.0))) }
+    if let Some(u) = user { keys.push(format!("{}:{}:name:{}", site.site, action, u.name)) }
+    keys
+}
+
+/// Returns the time until the oldest hit of the busiest key which
+/// has reached the limit expires. Only the keys at hand are pruned,
+/// `run` takes care of the rest
+fn retry_after(
+    hits: &mut HashMap<String, VecDeque<Instant>>,
+    keys: &[String],
+    limit: usize,
+    now: Instant,
+) -> Option<Duration> {
+    for key in keys {
+        if let Some(expiries) = hits.get_mut(key) {
+            prune(expiries, now);
+        }
+    }
+
+    keys
+        .iter()
+        .filter_map(|k| hits.get(k))
+        .filter(|expiries| expiries.len() >= limit)
+        .filter_map(|expiries| expiries.front().map(|e| *e - now))
+        .max()
+}
+
+fn prune(expiries: &mut VecDeque<Instant>, now: Instant) {
+    while expiries.front().is_some_and(|e| *e <= now) { expiries.pop_front(); }
+}
@@ -0,0 +1,108 @@
+use axum::{extract::{Path, State}, routing::post, Json, Router};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::db::{comments::{self, Owner}, pages, reactions};
+
+use super::{
+    ApiRequest, AppState, Base64, ClientInfo, Error, RateLimiter, Result, User,
+    generate_random_token, verify_read_permission,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/api/comment/:comment_id/reactions", post(create).delete(destroy))
+}
+
+/// Identifies who reacted: signed users by name,
+/// everyone else by their comment token
+pub(super) fn reactor(user: &Option<User>, token: &Option<Base64>) -> Option<String> {
+    match (user, token) {
+        (Some(u), _) => Some(format!("user:{}", u.name)),
+        (None, Some(t)) => Some(format!("token:{}", STANDARD.encode(&t.0))),
+        (None, None) => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct ReactionData {
+    token: Option<Base64>,
+}
+
+#[derive(Serialize)]
+struct ReactionResponse {
+    /// Set for anonymous readers, who need
+    /// a token to take their reaction back
+    token: Option<Base64>,
+    reactions: i64,
+    reacted: bool,
+}
+
+/// POST /api/comment/42/reactions
+async fn create(
+    State(db): State<SqlitePool>,
+    State(limiter): State<RateLimiter>,
+    client: ClientInfo,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<ReactionData>>,
+) -> Result<Json<ReactionResponse>> {
+    react(&db, &limiter, &client, comment_id, req, true).await
+}
+
+/// DELETE /api/comment/42/reactions
+async fn destroy(
+    State(db): State<SqlitePool>,
+    State(limiter): State<RateLimiter>,
+    client: ClientInfo,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<ReactionData>>,
+) -> Result<Json<ReactionResponse>> {
+    react(&db, &limiter, &client, comment_id, req, false).await
+}
+
+async fn react(
+    db: &SqlitePool,
+    limiter: &RateLimiter,
+    client: &ClientInfo,
+    comment_id: i64,
+    req: ApiRequest<ReactionData>,
+    add: bool,
+) -> Result<Json<ReactionResponse>> {
+    let (site, user) = req.extract_verified(db).await?;
+
+    let comment = comments::find_on_site(db, &site.site, comment_id).await?;
+    let page = pages::find(db, comment.page_id).await?;
+
+    verify_read_permission(&site, &user, Some(&page))?;
+    if user.is_none() && !site.anonymous { return Err(Error::Unauthorized) }
+    let owner = Owner {
+        token: req.payload.as_ref().and_then(|p| p.token.as_ref()),
+        name: user.as_ref().map(|u| u.name.as_str()),
+    };
+    // comments of shadow banned users don't exist for anyone else
+    if !user.as_ref().is_some_and(|u| u.moderator) && (!comment.reviewed || !owner.can_see(&comment)) {
+        return Err(Error::NotFound)
+    }
+    if page.locked { return Err(Error::Forbidden) }
+
+    let token = match user {
+        Some(_) => None,
+        None => Some(req.payload.and_then(|p| p.token).unwrap_or_else(generate_random_token)),
+    };
+    let reactor = reactor(&user, &token).unwrap();
+
+    limiter.limit("reaction", &site, &user, client.ip, &token)?;
+
+    if add {
+        // signed users are told apart by name, everyone else by token and IP
+        let ip = user.is_none().then(|| client.ip.to_string());
+        reactions::add(db, comment_id, &reactor, ip.as_deref()).await?;
+    } else {
+        reactions::remove(db, comment_id, &reactor).await?;
+    }
+
+    let counts = reactions::for_comments(db, &[comment_id], Some(&reactor)).await?;
+    let current = counts.get(&comment_id).copied().unwrap_or_default();
+
+    Ok(Json(ReactionResponse { token, reactions: current.count, reacted: current.reacted }))
+}
@@ -0,0 +1,139 @@
+use axum::{extract::{Path, State}, routing::post, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{audit::{self, Action, Actor, NewEvent}, comments::{self, Owner}, pages, reports},
+    notifications::Notifier,
+    webhooks::{Event, Webhooks},
+};
+
+use super::{
+    ApiRequest, AppState, Base64, ClientInfo, Error, RateLimiter, Result, Role,
+    comments::UnreviewedComment, generate_random_token, reactions::reactor, require_role, verify_read_permission,
+};
+
+const MAX_REASON_LENGTH: usize = 1000;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/api/comments/reported", post(index))
+        .route("/api/comment/:comment_id/report", post(create))
+}
+
+#[derive(Deserialize)]
+struct ReportData {
+    token: Option<Base64>,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct ReportResponse {
+    /// Set for anonymous readers, so that reporting
+    /// the same comment again is not counted twice
+    token: Option<Base64>,
+}
+
+/// POST /api/comment/42/report
+async fn create(
+    State(db): State<SqlitePool>,
+    State(notifier): State<Notifier>,
+    State(webhooks): State<Webhooks>,
+    State(limiter): State<RateLimiter>,
+    client: ClientInfo,
+    Path(comment_id): Path<i64>,
+    Json(req): Json<ApiRequest<ReportData>>,
+) -> Result<Json<ReportResponse>> {
+    let Some(ref data) = req.payload else { return Err(Error::UnprocessableEntity("Payload can't be blank")) };
+
+    let reason = data.reason.trim();
+    if reason.is_empty() { return Err(Error::UnprocessableEntity("Reason can't be blank")) }
+    if reason.chars().count() > MAX_REASON_LENGTH { return Err(Error::UnprocessableEntity("Reason is too long")) }
+
+    let (site, user) = req.extract_verified(&db).await?;
+
+    let comment = comments::find_on_site(&db, &site.site, comment_id).await?;
+    let page = pages::find(&db, comment.page_id).await?;
+
+    verify_read_permission(&site, &user, Some(&page))?;
+    if user.is_none() && !site.anonymous { return Err(Error::Unauthorized) }
+    let owner = Owner { token: data.token.as_ref(), name: user.as_ref().map(|u| u.name.as_str()) };
+    // comments of shadow banned users don't exist for anyone else
+    if !user.as_ref().is_some_and(|u| u.moderator) && (!comment.reviewed || !owner.can_see(&comment)) {
+        return Err(Error::NotFound)
+    }
+
+    let token = match user {
+        Some(_) => None,
+        None => Some(data.token.clone().unwrap_or_else(generate_random_token)),
+    };
+    let reporter = reactor(&user, &token).unwrap();
+
+    limiter.limit("report", &site, &user, client.ip, &token)?;
+
+    let reports = reports::add(&db, comment_id, &reporter, &client.ip.to_string(), reason).await?;
+
+    if comment.reviewed && site.report_threshold > 0 && reports >= site.report_threshold {
+        comments::unapprove(&db, comment_id, false).await?;
+        audit::record(&db, NewEvent {
+            site: &site.site,
+            actor: Actor::REPORTS.name,
+            moderator: Actor::REPORTS.moderator,
+            action: Action::Unapprove,
+            comment_id: Some(comment.id),
+            page_id: Some(comment.page_id),
+            target: None,
+            body_before: Some(&comment.body),
+            body_after: None,
+        }).await?;
+        notifier.reported_comment(&db, &site, &page, &comment, reports).await?;
+
+        if !comment.shadowed {
+            let hidden = comments::Comment { reviewed: false, hidden: true, ..comment };
+            webhooks.comment(&db, &site, Event::CommentUnapproved, &page, &hidden).await?;
+        }
+    }
+
+    Ok(Json(ReportResponse { token }))
+}
+
+#[derive(Serialize)]
+struct ReportReason {
+    reason: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct ReportedComment {
+    #[serde(flatten)]
+    comment: UnreviewedComment,
+    reports: Vec<ReportReason>,
+}
+
+/// POST /api/comments/reported
+async fn index(
+    State(db): State<SqlitePool>,
+    Json(req): Json<ApiRequest<()>>,
+) -> Result<Json<Vec<ReportedComment>>> {
+    let (site, user) = req.extract_verified(&db).await?;
+
+    require_role(&user, Role::Approver)?;
+
+    let mut results: Vec<ReportedComment> = vec![];
+
+    for report in reports::for_site(&db, &site.site).await? {
+        let page = report.page();
+        let reason = ReportReason { reason: report.reason, created_at: report.reported_at };
+
+        match results.iter_mut().find(|r| r.comment.id == report.comment.id) {
+            Some(reported) => reported.reports.push(reason),
+            None => results.push(ReportedComment {
+                comment: UnreviewedComment::new(report.comment, &page, &None),
+                reports: vec![reason],
+            }),
+        }
+    }
+
+    Ok(Json(results))
+}
@@ -1,4 +1,4 @@
-use crate::{api::{ApiRequest, AppState, Result}, db::pages::find_by_site_and_path};
+use crate::{api::{ApiRequest, AppState, Result}, db::{comments::Sort, pages::find_by_site_and_path}};
 use axum::{routing::post, Json, Router, extract::State};
 use sqlx::SqlitePool;
 use super::PageConfig;
@@ -22,5 +22,8 @@ async fn page_config(
         anonymous: site.anonymous,
         moderated: site.moderated,
         locked,
+        max_depth: site.max_depth,
+        reply_notifications: site.reply_notifications && !site.smtp_url.is_empty(),
+        default_sort: Sort::from(site.default_sort.as_str()),
     }))
 }
@@ -0,0 +1,195 @@
+pub mod akismet;
+
+use axum::async_trait;
+
+use crate::{api::ClientInfo, db::sites::Site};
+
+use akismet::Akismet;
+
+/// The outcome of checking a comment for spam, ordered
+/// from the most lenient to the strictest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    /// The comment can be posted as usual
+    Pass,
+    /// The comment must be reviewed by a moderator
+    /// before it's visible, even on unmoderated sites
+    Review,
+    /// The comment must not be posted at all
+    Reject(&'static str),
+}
+
+/// What a site does with comments caught by a filter
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Reject,
+    Review,
+}
+
+impl Action {
+    fn verdict(self, reason: &'static str) -> Verdict {
+        match self {
+            Self::Reject => Verdict::Reject(reason),
+            Self::Review => Verdict::Review,
+        }
+    }
+}
+
+impl From<&str> for Action {
+    fn from(action: &str) -> Self {
+        match action {
+            "review" => Self::Review,
+            _ => Self::Reject,
+        }
+    }
+}
+
+/// A comment which is about to be posted
+pub struct Submission<'a> {
+    pub client: &'a ClientInfo,
+    pub path: &'a str,
+    pub reply: bool,
+    pub name: &'a str,
+    pub body: &'a str,
+    pub html_body: &'a str,
+    /// Value of a form field which is hidden from humans
+    pub honeypot: Option<&'a str>,
+    /// Seconds it took to write the comment, as reported by the client
+    pub time_to_submit: Option<u64>,
+}
+
+#[async_trait]
+pub trait SpamFilter: Send + Sync {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict;
+}
+
+/// Catches comments with more than `max` links
+pub struct LinkLimit {
+    pub max: usize,
+    pub action: Action,
+}
+
+#[async_trait]
+impl SpamFilter for LinkLimit {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict {
+        // plain urls are turned into links when rendering markdown
+        if submission.html_body.matches("<a ").count() > self.max {
+            return self.action.verdict("Your comment contains too many links")
+        }
+        Verdict::Pass
+    }
+}
+
+/// Catches comments which contain any of the banned words
+/// or phrases, in either the name or the body
+pub struct BannedWords {
+    pub words: Vec<String>,
+    pub action: Action,
+}
+
+impl BannedWords {
+    fn contains(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).collect();
+
+        self.words.iter().any(|banned| {
+            if banned.contains(char::is_whitespace) {
+                text.contains(banned.as_str())
+            } else {
+                words.contains(&banned.as_str())
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl SpamFilter for BannedWords {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict {
+        if self.contains(submission.name) || self.contains(submission.body) {
+            return self.action.verdict("Your comment contains banned words")
+        }
+        Verdict::Pass
+    }
+}
+
+/// Catches bots which fill in every form field they find
+pub struct Honeypot {
+    pub action: Action,
+}
+
+#[async_trait]
+impl SpamFilter for Honeypot {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict {
+        if submission.honeypot.is_some_and(|h| !h.is_empty()) {
+            return self.action.verdict("Your comment looks like spam")
+        }
+        Verdict::Pass
+    }
+}
+
+/// Catches comments which were written faster than humanly possible
+pub struct MinSubmitTime {
+    pub seconds: u64,
+    pub action: Action,
+}
+
+#[async_trait]
+impl SpamFilter for MinSubmitTime {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict {
+        if submission.time_to_submit.is_none_or(|t| t < self.seconds) {
+            return self.action.verdict("Your comment looks like spam")
+        }
+        Verdict::Pass
+    }
+}
+
+/// Returns the filters enabled for a site
+pub fn filters(site: &Site) -> Vec<Box<dyn SpamFilter>> {
+    let mut filters: Vec<Box<dyn SpamFilter>> = vec![];
+
+    if site.max_links > 0 {
+        filters.push(Box::new(LinkLimit {
+            max: site.max_links as usize,
+            action: Action::from(site.max_links_action.as_str()),
+        }));
+    }
+
+    let words: Vec<String> = site.banned_words
+        .split(',')
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if !words.is_empty() {
+        filters.push(Box::new(BannedWords { words, action: Action::from(site.banned_words_action.as_str()) }));
+    }
+
+    if site.honeypot {
+        filters.push(Box::new(Honeypot { action: Action::from(site.honeypot_action.as_str()) }));
+    }
+
+    if site.min_submit_time > 0 {
+        filters.push(Box::new(MinSubmitTime {
+            seconds: site.min_submit_time as u64,
+            action: Action::from(site.min_submit_time_action.as_str()),
+        }));
+    }
+
+    // the only filter which makes a request, so it goes last
+    if let Some(akismet) = Akismet::for_site(site) {
+        filters.push(Box::new(akismet));
+    }
+
+    filters
+}
+
+/// Runs a comment through all filters and returns the strictest verdict
+pub async fn check(filters: &[Box<dyn SpamFilter>], submission: &Submission<'_>) -> Verdict {
+    let mut verdict = Verdict::Pass;
+
+    for filter in filters {
+        verdict = verdict.max(filter.check(submission).await);
+        if let Verdict::Reject(_) = verdict { break }
+    }
+
+    verdict
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{akismet, comments, queue::Table, sites::{self, Site}},
+    http,
+    queue::{Job, Worker},
+};
+
+use super::{Action, SpamFilter, Submission, Verdict};
+
+/// Akismet is asked about every new comment,
+/// so it must not keep commenters waiting
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long to remember where comments were posted from. Decisions
+/// about older comments can't be sent to Akismet anymore
+const METADATA_RETENTION_DAYS: i64 = 30;
+
+/// How often metadata past its retention is deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A comment as described to Akismet
+#[derive(Serialize, Deserialize)]
+pub struct AkismetComment {
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+    pub permalink: String,
+    pub reply: bool,
+    pub author: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Akismet's opinion about a comment
+pub enum Check {
+    Ham,
+    Spam,
+    /// Blatant spam which isn't even worth reviewing
+    Discard,
+}
+
+/// A client for the Akismet API, or any other service
+/// which implements the same protocol
+pub struct Akismet {
+    url: String,
+    key: String,
+    blog: String,
+    action: Action,
+}
+
+impl Akismet {
+    /// Returns a client if Akismet is enabled for the site
+    pub fn for_site(site: &Site) -> Option<Self> {
+        if site.akismet_key.is_empty() { return None }
+
+        Some(Self {
+            url: site.akismet_url.trim_end_matches('/').to_string(),
+            key: site.akismet_key.clone(),
+            blog: format!("https://{}", site.site),
+            action: Action::from(site.akismet_action.as_str()),
+        })
+    }
+
+    pub fn permalink(&self, path: &str) -> String {
+        format!("{}{}", self.blog, path)
+    }
+
+    async fn call(&self, method: &str, comment: &AkismetComment) -> anyhow::Result<reqwest::Response> {
+        let mut form = vec![
+            ("api_key", self.key.clone()),
+            ("blog", self.blog.clone()),
+            ("user_ip", comment.ip.clone()),
+            ("permalink", comment.permalink.clone()),
+            ("comment_type", String::from(if comment.reply { "reply" } else { "comment" })),
+            ("comment_author", comment.author.clone()),
+            ("comment_content", comment.content.clone()),
+            ("comment_date_gmt", comment.created_at.to_rfc3339()),
+        ];
+        if let Some(ref ua) = comment.user_agent { form.push(("user_agent", ua.clone())) }
+        if let Some(ref referrer) = comment.referrer { form.push(("referrer", referrer.clone())) }
+
+        Ok(http::client()
+            .post(format!("{}/1.1/{}", self.url, method))
+            .timeout(TIMEOUT)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?)
+    }
+
+    /// Asks Akismet whether a comment is spam
+    pub async fn comment_check(&self, comment: &AkismetComment) -> anyhow::Result<Check> {
+        let response = self.call("comment-check", comment).await?;
+        let discard = response.headers().get("X-akismet-pro-tip").is_some_and(|tip| tip == "discard");
+
+        match response.text().await?.trim() {
+            "true" if discard => Ok(Check::Discard),
+            "true" => Ok(Check::Spam),
+            "false" => Ok(Check::Ham),
+            other => anyhow::bail!("Unexpected response from Akismet: {}", other),
+        }
+    }
+
+    /// Tells Akismet about spam which it didn't catch
+    pub async fn submit_spam(&self, comment: &AkismetComment) -> anyhow::Result<()> {
+        self.call("submit-spam", comment).await?;
+        Ok(())
+    }
+
+    /// Tells Akismet about comments which it wrongly marked as spam
+    pub async fn submit_ham(&self, comment: &AkismetComment) -> anyhow::Result<()> {
+        self.call("submit-ham", comment).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SpamFilter for Akismet {
+    async fn check(&self, submission: &Submission<'_>) -> Verdict {
+        let comment = AkismetComment {
+            ip: submission.client.ip.to_string(),
+            user_agent: submission.client.user_agent.clone(),
+            referrer: submission.client.referrer.clone(),
+            permalink: self.permalink(submission.path),
+            reply: submission.reply,
+            author: submission.name.to_string(),
+            content: submission.body.to_string(),
+            created_at: Utc::now(),
+        };
+
+        match self.comment_check(&comment).await {
+            Ok(Check::Ham) => Verdict::Pass,
+            Ok(Check::Spam) => self.action.verdict("Your comment looks like spam"),
+            Ok(Check::Discard) => Verdict::Reject("Your comment looks like spam"),
+            // don't stop people from commenting while Akismet is unavailable
+            Err(e) => {
+                tracing::warn!("Akismet check failed: {:?}", e);
+                Verdict::Pass
+            },
+        }
+    }
+}
+
+/// Queues moderators' decisions about comments and
+/// submits them to Akismet in the background
+#[derive(Clone, Default)]
+pub struct Feedback {
+    worker: Worker,
+}
+
+impl Feedback {
+    /// Queues a comment to be submitted as spam, or as ham if it isn't
+    pub async fn submit(&self, db: &SqlitePool, site: &Site, comment: &AkismetComment, spam: bool) -> sqlx::Result<()> {
+        let comment = serde_json::to_string(comment).expect("Can't serialize Akismet comment");
+        akismet::enqueue(db, &site.site, spam, &comment).await?;
+
+        self.worker.wake();
+        Ok(())
+    }
+
+    /// Submits queued decisions for as long as the server runs
+    pub async fn run(self, db: SqlitePool) {
+        self.worker.run::<akismet::Submission>(db).await
+    }
+
+    /// Periodically forgets where comments past their retention came from
+    pub async fn purge_metadata(db: SqlitePool) {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let before = Utc::now() - chrono::Duration::days(METADATA_RETENTION_DAYS);
+            if let Err(e) = comments::purge_metadata(&db, before).await {
+                tracing::error!("Failed purging comment metadata: {:?}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Job for akismet::Submission {
+    const TABLE: Table = Table { name: "akismet_submissions", done_at: "sent_at" };
+
+    fn id(&self) -> i64 { self.id }
+
+    fn attempts(&self) -> i64 { self.attempts }
+
+    async fn perform(&self, db: &SqlitePool) -> anyhow::Result<()> {
+        let site = sites::find(db, &self.site).await.context("Site not found")?;
+        let akismet = Akismet::for_site(&site).context("Akismet is disabled for the site")?;
+        let comment: AkismetComment = serde_json::from_str(&self.comment)?;
+
+        match self.spam {
+            true => akismet.submit_spam(&comment).await,
+            false => akismet.submit_ham(&comment).await,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use sqlx::SqlitePool;
+
+use crate::db::audit::{search, Event};
+
+use super::AuditArgs;
+
+pub async fn list(db: &SqlitePool, args: AuditArgs) {
+    match search(db, args.site.as_deref(), args.moderator.as_deref(), args.since, args.until).await {
+        Err(e) => println!("{}", e),
+        Ok(events) => {
+            println!("Found {} event(s)", events.len());
+            for event in events {
+                print_event(event);
+            }
+        }
+    }
+}
+
+fn print_event(event: Event) {
+    let heading = format!("{} {} by {}", event.created_at.format("%Y-%m-%d %H:%M:%S"), event.action, event.actor);
+
+    println!(r#"
+{}
+{}
+site:                {}
+moderator:           {}
+page:                {}
+comment:             {}
+target:              {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        event.site,
+        event.moderator,
+        event.page_id.map_or(String::from("-"), |id| id.to_string()),
+        event.comment_id.map_or(String::from("-"), |id| id.to_string()),
+        event.target.as_deref().unwrap_or("-"),
+    );
+
+    if let Some(before) = event.body_before {
+        println!("before:\n{}", before);
+    }
+
+    if let Some(after) = event.body_after {
+        println!("after:\n{}", after);
+    }
+}
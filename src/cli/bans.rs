@@ -0,0 +1,83 @@
+use sqlx::SqlitePool;
+
+use crate::db::{sites, bans::{self, Ban, Kind, NewBan}};
+
+use super::BansAddCommandArgs;
+
+pub async fn list(db: &SqlitePool, site: Option<&str>) {
+    match bans::all(db, site).await {
+        Err(e) => println!("{}", e),
+        Ok(bans) => {
+            println!("Found {} ban(s)", bans.len());
+            for ban in bans {
+                print_ban(&ban);
+            }
+        }
+    }
+}
+
+pub async fn create(db: &SqlitePool, args: BansAddCommandArgs) {
+    if sites::find(db, &args.site).await.is_err() {
+        return println!("Site {} not found. Try adding it first:\n$ besedka site add {}", &args.site, &args.site)
+    }
+
+    if args.kind == Kind::Ip && bans::ip_range(&args.value).is_none() {
+        return println!("{} is not a valid IP address or CIDR range", &args.value)
+    }
+
+    let ban = NewBan {
+        site: &args.site,
+        kind: args.kind,
+        value: &args.value,
+        reason: args.reason.as_deref(),
+        created_by: None,
+        expires_at: args.expires,
+        shadow: args.shadow.unwrap_or(false),
+    };
+
+    match bans::create(db, ban).await {
+        Err(e) => println!("{}", e),
+        Ok(ban) => {
+            println!("Success!");
+            print_ban(&ban);
+        }
+    }
+}
+
+pub async fn remove(db: &SqlitePool, id: i64) {
+    match bans::find(db, id).await {
+        Err(_) => println!("Ban {} not found.", id),
+        Ok(ban) => match bans::delete(db, id).await {
+            Err(e) => println!("{}", e),
+            Ok(_) => println!("Lifted ban {} on {} {} for site {}", id, ban.kind, ban.value, ban.site),
+        },
+    }
+}
+
+fn print_ban(ban: &Ban) {
+    let heading = format!("#{} {} {}", ban.id, ban.kind, ban.value);
+    let expires = match ban.expires_at {
+        None => String::from("never"),
+        Some(at) if ban.is_active() => at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        Some(at) => format!("{} (expired)", at.format("%Y-%m-%d %H:%M:%S")),
+    };
+
+    println!(r#"
+{}
+{}
+site:                {}
+shadow:              {}
+reason:              {}
+banned by:           {}
+expires:             {}
+created:             {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        ban.site,
+        ban.shadow,
+        ban.reason.as_deref().unwrap_or("-"),
+        ban.created_by.as_deref().unwrap_or("-"),
+        expires,
+        ban.created_at.format("%Y-%m-%d %H:%M:%S"),
+    )
+}
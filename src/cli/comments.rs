@@ -0,0 +1,55 @@
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{audit::{self, Action, Actor, NewEvent}, comments::{self, Comment}, pages, sites},
+    webhooks::{Event, Webhooks},
+};
+
+pub async fn purge(db: &SqlitePool, older_than: u32) {
+    let deleted_before = Utc::now() - Duration::days(older_than.into());
+
+    match comments::purge(db, deleted_before).await {
+        Err(e) => println!("{}", e),
+        Ok(purged) => println!("Purged {} deleted comment(s)", purged),
+    }
+}
+
+pub async fn unapprove(db: &SqlitePool, id: i64, from_author: bool) {
+    match comments::find(db, id).await {
+        Err(_) => println!("Comment {} not found.", id),
+        Ok(c) if !c.reviewed && !c.hidden => println!("Comment {} hasn't been published.", id),
+        Ok(c) => match hide(db, &c, from_author).await {
+            Err(e) => println!("{}", e),
+            Ok(_) => println!("Hid comment {}{}", id, if from_author { " from everyone including its author" } else { "" }),
+        },
+    }
+}
+
+/// Unapproves a comment the same way moderators do through the API. Webhook
+/// deliveries are only queued here and sent by the server once it picks them up
+async fn hide(db: &SqlitePool, comment: &Comment, from_author: bool) -> sqlx::Result<()> {
+    comments::unapprove(db, comment.id, from_author).await?;
+
+    let page = pages::find(db, comment.page_id).await?;
+    let site = sites::find(db, &page.site).await?;
+
+    audit::record(db, NewEvent {
+        site: &site.site,
+        actor: Actor::COMMAND_LINE.name,
+        moderator: Actor::COMMAND_LINE.moderator,
+        action: Action::Unapprove,
+        comment_id: Some(comment.id),
+        page_id: Some(comment.page_id),
+        target: None,
+        body_before: Some(&comment.body),
+        body_after: None,
+    }).await?;
+
+    if comment.reviewed && !comment.shadowed {
+        let hidden = Comment { reviewed: false, hidden: true, ..comment.clone() };
+        Webhooks::default().comment(db, &site, Event::CommentUnapproved, &page, &hidden).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::{
+    api::Base64,
+    db::{comments::{self, Comment}, pages, sites},
+};
+
+use super::ExportArgs;
+
+/// Bump this whenever the format changes in
+/// a way older versions can't import
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Export {
+    pub version: u32,
+    pub site: ExportedSite,
+    pub pages: Vec<ExportedPage>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedSite {
+    pub site: String,
+    pub secret: Base64,
+    pub private: bool,
+    pub anonymous: bool,
+    pub moderated: bool,
+    pub max_depth: i64,
+    pub public_revisions: bool,
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit: i64,
+    #[serde(default = "default_rate_limit_window")]
+    pub rate_limit_window: i64,
+    #[serde(default)]
+    pub max_links: i64,
+    #[serde(default)]
+    pub max_links_action: Option<String>,
+    #[serde(default)]
+    pub banned_words: String,
+    #[serde(default)]
+    pub banned_words_action: Option<String>,
+    #[serde(default = "default_honeypot")]
+    pub honeypot: bool,
+    #[serde(default)]
+    pub honeypot_action: Option<String>,
+    #[serde(default)]
+    pub min_submit_time: i64,
+    #[serde(default)]
+    pub min_submit_time_action: Option<String>,
+    /// Older exports have one action for all filters,
+    /// used for the ones without their own
+    #[serde(default, skip_serializing)]
+    pub spam_action: Option<String>,
+    #[serde(default)]
+    pub akismet_key: String,
+    #[serde(default = "default_akismet_url")]
+    pub akismet_url: String,
+    #[serde(default)]
+    pub akismet_action: Option<String>,
+    #[serde(default)]
+    pub smtp_url: String,
+    #[serde(default)]
+    pub email_from: String,
+    #[serde(default)]
+    pub moderator_emails: String,
+    #[serde(default)]
+    pub reply_notifications: bool,
+    #[serde(default = "default_sort")]
+    pub default_sort: String,
+    #[serde(default = "default_report_threshold")]
+    pub report_threshold: i64,
+}
+
+fn default_rate_limit() -> i64 { 10 }
+
+fn default_rate_limit_window() -> i64 { 60 }
+
+fn default_honeypot() -> bool { false }
+
+fn default_report_threshold() -> i64 { 0 }
+
+fn default_akismet_url() -> String { String::from("https://rest.akismet.com") }
+
+fn default_sort() -> String { String::from("newest") }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedPage {
+    pub path: String,
+    pub title: Option<String>,
+    pub locked: bool,
+    pub comments: Vec<ExportedComment>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedComment {
+    pub name: String,
+    pub avatar: Option<String>,
+    pub body: String,
+    pub html_body: String,
+    pub reviewed: bool,
+    pub moderator: bool,
+    pub op: bool,
+    /// Posted by a shadow banned commenter
+    #[serde(default)]
+    pub shadowed: bool,
+    /// Name of the signed user who posted it, which lets
+    /// shadow banned commenters keep seeing their comments
+    #[serde(default)]
+    pub signed_name: Option<String>,
+    /// Taken back for review by a moderator after it was published
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub hidden_from_author: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub token: Base64,
+    pub replies: Vec<ExportedComment>,
+}
+
+fn comment_tree(parent_id: Option<i64>, all: &[Comment]) -> Vec<ExportedComment> {
+    all.iter()
+        .filter(|c| c.parent_id == parent_id)
+        .map(|c| ExportedComment {
+            name: c.name.clone(),
+            avatar: c.avatar.clone(),
+            body: c.body.clone(),
+            html_body: c.html_body.clone(),
+            reviewed: c.reviewed,
+            moderator: c.moderator,
+            op: c.op,
+            shadowed: c.shadowed,
+            signed_name: c.signed_name.clone(),
+            hidden: c.hidden,
+            hidden_from_author: c.hidden_from_author,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+            deleted_at: c.deleted_at,
+            token: c.token.clone(),
+            replies: comment_tree(Some(c.id), all),
+        })
+        .collect()
+}
+
+async fn build(db: &SqlitePool, site: &str) -> sqlx::Result<Export> {
+    let site = sites::find(db, site).await?;
+    let mut exported_pages = vec![];
+
+    for page in pages::all_for_site(db, &site.site).await? {
+        let all = comments::all_for_page(db, page.id).await?;
+        exported_pages.push(ExportedPage {
+            path: page.path,
+            title: page.title,
+            locked: page.locked,
+            comments: comment_tree(None, &all),
+        });
+    }
+
+    Ok(Export {
+        version: EXPORT_VERSION,
+        site: ExportedSite {
+            site: site.site,
+            secret: Base64(site.secret),
+            private: site.private,
+            anonymous: site.anonymous,
+            moderated: site.moderated,
+            max_depth: site.max_depth,
+            public_revisions: site.public_revisions,
+            rate_limit: site.rate_limit,
+            rate_limit_window: site.rate_limit_window,
+            max_links: site.max_links,
+            max_links_action: Some(site.max_links_action),
+            banned_words: site.banned_words,
+            banned_words_action: Some(site.banned_words_action),
+            honeypot: site.honeypot,
+            honeypot_action: Some(site.honeypot_action),
+            min_submit_time: site.min_submit_time,
+            min_submit_time_action: Some(site.min_submit_time_action),
+            spam_action: None,
+            akismet_key: site.akismet_key,
+            akismet_url: site.akismet_url,
+            akismet_action: Some(site.akismet_action),
+            smtp_url: site.smtp_url,
+            email_from: site.email_from,
+            moderator_emails: site.moderator_emails,
+            reply_notifications: site.reply_notifications,
+            default_sort: site.default_sort,
+            report_threshold: site.report_threshold,
+        },
+        pages: exported_pages,
+    })
+}
+
+pub async fn export(db: &SqlitePool, args: ExportArgs) {
+    let output = args.output.unwrap_or_else(|| format!("{}.json", args.site));
+
+    match build(db, &args.site).await {
+        Err(sqlx::Error::RowNotFound) => println!("Site {} not found.", args.site),
+        Err(e) => println!("{}", e),
+        Ok(export) => {
+            let json = serde_json::to_string_pretty(&export).unwrap();
+            match std::fs::write(&output, json) {
+                Err(e) => println!("Couldn't write {}: {}", output, e),
+                Ok(_) => println!("Exported {} page(s) of site {} to {}", export.pages.len(), export.site.site, output),
+            }
+        }
+    }
+}
@@ -0,0 +1,356 @@
+pub mod disqus;
+pub mod isso;
+pub mod wordpress;
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use ring::digest;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    api::Base64,
+    db::{comments::{self, ImportedComment}, pages, sites},
+};
+
+use super::{
+    export::{Export, ExportedComment, ExportedPage, EXPORT_VERSION},
+    SitesCommandArgs, ThirdPartyImportArgs,
+};
+
+/// Totals of what has been imported
+#[derive(Default)]
+pub struct Summary {
+    pub pages: usize,
+    pub comments: usize,
+}
+
+/// Comments read from another commenting system
+pub struct Parsed {
+    pub pages: Vec<ExportedPage>,
+    pub spam: usize,
+}
+
+/// A comment from another system before it's nested under its parent
+pub struct FlatComment {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub comment: ExportedComment,
+}
+
+/// Builds the reply tree of flat comments, keeping their order.
+/// Replies to comments which aren't there become top level comments
+pub fn nest(comments: Vec<FlatComment>) -> Vec<ExportedComment> {
+    let ids: Vec<String> = comments.iter().map(|c| c.id.clone()).collect();
+    let mut children: HashMap<Option<String>, Vec<FlatComment>> = HashMap::new();
+
+    for c in comments {
+        let parent = c.parent_id.clone().filter(|p| ids.contains(p));
+        children.entry(parent).or_default().push(c);
+    }
+
+    take_replies(None, &mut children)
+}
+
+fn take_replies(
+    parent_id: Option<String>,
+    children: &mut HashMap<Option<String>, Vec<FlatComment>>,
+) -> Vec<ExportedComment> {
+    children
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| ExportedComment { replies: take_replies(Some(c.id), children), ..c.comment })
+        .collect()
+}
+
+#[derive(Default)]
+struct Preview {
+    comments: usize,
+    replies: usize,
+    pending: usize,
+    deleted: usize,
+}
+
+impl Preview {
+    fn count(&mut self, comments: &[ExportedComment], replies: bool) {
+        for c in comments {
+            self.comments += 1;
+            if replies { self.replies += 1 }
+            if !c.reviewed { self.pending += 1 }
+            if c.deleted_at.is_some() { self.deleted += 1 }
+            self.count(&c.replies, true);
+        }
+    }
+}
+
+/// Prints what is about to be imported from another commenting system
+/// and writes it to the site unless it's a dry run
+pub async fn import_parsed(
+    db: &SqlitePool,
+    args: &ThirdPartyImportArgs,
+    source: &str,
+    parsed: anyhow::Result<Parsed>,
+) {
+    let site = match sites::find(db, &args.site).await {
+        Err(_) => return println!("Site {} not found. Try adding it first:\n$ besedka site add {}", args.site, args.site),
+        Ok(site) => site,
+    };
+
+    let parsed = match parsed {
+        Err(e) => return println!("{:#}", e),
+        Ok(parsed) => parsed,
+    };
+
+    let mut preview = Preview::default();
+    for page in &parsed.pages {
+        preview.count(&page.comments, false);
+    }
+
+    println!(
+        "Found {} page(s) and {} comment(s) in the {} export ({} replies, {} awaiting review, {} deleted), skipping {} spam comment(s)",
+        parsed.pages.len(), preview.comments, source, preview.replies, preview.pending, preview.deleted, parsed.spam
+    );
+
+    if args.dry_run {
+        return println!("Dry run, nothing has been imported")
+    }
+
+    match import_pages(db, &site.site, &parsed.pages).await {
+        Err(e) => println!("{}", e),
+        Ok(summary) => println!(
+            "Imported {} page(s) and {} comment(s) from {} for site {}",
+            summary.pages, summary.comments, source, site.site
+        ),
+    }
+}
+
+async fn import_comments(
+    conn: &mut SqliteConnection,
+    page_id: i64,
+    comments: &[ExportedComment],
+) -> sqlx::Result<usize> {
+    let mut imported = 0;
+    // parents are always imported before their replies
+    let mut queue: Vec<(Option<i64>, &ExportedComment)> = comments.iter().map(|c| (None, c)).collect();
+
+    while let Some((parent_id, c)) = queue.pop() {
+        let id = comments::upsert(&mut *conn, &ImportedComment {
+            page_id,
+            parent_id,
+            avatar: c.avatar.as_deref(),
+            name: &c.name,
+            html_body: &c.html_body,
+            body: &c.body,
+            reviewed: c.reviewed,
+            moderator: c.moderator,
+            op: c.op,
+            shadowed: c.shadowed,
+            signed_name: c.signed_name.as_deref(),
+            hidden: c.hidden,
+            hidden_from_author: c.hidden_from_author,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+            deleted_at: c.deleted_at,
+            token: &c.token,
+        }).await?;
+
+        imported += 1;
+        queue.extend(c.replies.iter().map(|r| (Some(id), r)));
+    }
+
+    Ok(imported)
+}
+
+/// Creates or updates the pages of a site together with their comments.
+/// Nothing is imported unless everything is
+pub async fn import_pages(db: &SqlitePool, site: &str, pages: &[ExportedPage]) -> sqlx::Result<Summary> {
+    let mut tx = db.begin().await?;
+    let summary = write_pages(&mut tx, site, pages).await?;
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+async fn write_pages(conn: &mut SqliteConnection, site: &str, pages: &[ExportedPage]) -> sqlx::Result<Summary> {
+    let mut summary = Summary::default();
+
+    for p in pages {
+        let page = pages::create_or_find_by_site_and_path(&mut *conn, site, &p.path, &p.title).await?;
+        pages::update(&mut *conn, page.id, &p.title, p.locked).await?;
+
+        summary.pages += 1;
+        summary.comments += import_comments(conn, page.id, &p.comments).await?;
+    }
+
+    Ok(summary)
+}
+
+async fn run(db: &SqlitePool, export: Export) -> anyhow::Result<Summary> {
+    let exported = &export.site;
+    let action = |a: &Option<String>| a.clone().or(exported.spam_action.clone());
+    let args = SitesCommandArgs {
+        site: exported.site.clone(),
+        private: Some(exported.private),
+        anonymous: Some(exported.anonymous),
+        moderated: Some(exported.moderated),
+        max_depth: Some(exported.max_depth),
+        public_revisions: Some(exported.public_revisions),
+        rate_limit: Some(exported.rate_limit),
+        rate_limit_window: Some(exported.rate_limit_window),
+        max_links: Some(exported.max_links),
+        max_links_action: action(&exported.max_links_action),
+        banned_words: Some(exported.banned_words.clone()),
+        banned_words_action: action(&exported.banned_words_action),
+        honeypot: Some(exported.honeypot),
+        honeypot_action: action(&exported.honeypot_action),
+        min_submit_time: Some(exported.min_submit_time),
+        min_submit_time_action: action(&exported.min_submit_time_action),
+        akismet_key: Some(exported.akismet_key.clone()),
+        akismet_url: Some(exported.akismet_url.clone()),
+        akismet_action: action(&exported.akismet_action),
+        smtp_url: Some(exported.smtp_url.clone()),
+        email_from: Some(exported.email_from.clone()),
+        moderator_emails: Some(exported.moderator_emails.clone()),
+        reply_notifications: Some(exported.reply_notifications),
+        default_sort: Some(exported.default_sort.clone()),
+        report_threshold: Some(exported.report_threshold),
+    };
+
+    // the site and its pages are imported all at once, or not at all
+    let mut tx = db.begin().await?;
+
+    let site = match sites::find(&mut tx, &args.site).await {
+        Ok(existing) => sites::update(&mut tx, existing, args).await?,
+        Err(sqlx::Error::RowNotFound) => sites::insert(&mut tx, &args).await?,
+        Err(e) => return Err(e.into()),
+    };
+    sites::set_secret(&mut tx, &site.site, &exported.secret.0).await?;
+
+    let summary = write_pages(&mut tx, &site.site, &export.pages).await?;
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+fn read(file: &str) -> anyhow::Result<Export> {
+    let json = std::fs::read(file).with_context(|| format!("Couldn't read {}", file))?;
+    let export: Export = serde_json::from_slice(&json).context("Invalid export file")?;
+
+    if export.version != EXPORT_VERSION {
+        anyhow::bail!("Unsupported export version {}, expected {}", export.version, EXPORT_VERSION)
+    }
+
+    Ok(export)
+}
+
+pub async fn import(db: &SqlitePool, file: &str) {
+    let export = match read(file) {
+        Err(e) => return println!("{:#}", e),
+        Ok(export) => export,
+    };
+
+    let site = export.site.site.clone();
+
+    match run(db, export).await {
+        Err(e) => println!("{:#}", e),
+        Ok(summary) => println!(
+            "Imported {} page(s) and {} comment(s) for site {}",
+            summary.pages, summary.comments, site
+        ),
+    }
+}
+
+/// Derives a stable comment token from an id in another system,
+/// so that importing the same comment twice updates it instead
+/// of creating a duplicate
+pub fn token_for(source: &str, id: &str) -> Base64 {
+    let hash = digest::digest(&digest::SHA384, format!("{}:{}", source, id).as_bytes());
+    Base64(hash.as_ref().to_vec())
+}
+
+/// Returns the path (and query) part of a url, or
+/// the value as it is if it's already a path
+pub fn url_path(url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    if without_scheme == url && url.starts_with('/') { return url.to_string() }
+
+    let path = without_scheme.find('/').map_or("/", |i| &without_scheme[i..]);
+    path.split('#').next().unwrap_or("/").to_string()
+}
+
+/// Converts the html of comments coming from other systems into
+/// markdown, so that they can be rendered and edited like the rest.
+/// Only basic formatting is kept, all other tags are dropped
+pub fn html_to_markdown(html: &str) -> String {
+    let mut markdown = String::new();
+    let mut links: Vec<String> = vec![];
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        markdown.push_str(&decode_entities(&rest[..start]));
+
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break
+        };
+
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        match (name.as_str(), closing) {
+            ("br", _) => markdown.push('\n'),
+            ("p", true) | ("div", true) => markdown.push_str("\n\n"),
+            ("b", _) | ("strong", _) => markdown.push_str("**"),
+            ("i", _) | ("em", _) => markdown.push('_'),
+            ("code", _) => markdown.push('`'),
+            ("a", false) => {
+                links.push(attribute(tag, "href").unwrap_or_default());
+                markdown.push('[');
+            },
+            ("a", true) => {
+                let href = links.pop().unwrap_or_default();
+                markdown.push_str(&format!("]({})", href));
+            },
+            _ => (),
+        }
+    }
+
+    markdown.push_str(&decode_entities(rest));
+
+    let mut result = markdown.trim().to_string();
+    while result.contains("\n\n\n") {
+        result = result.replace("\n\n\n", "\n\n");
+    }
+    result
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let value = &tag[start..];
+    let quote = value.chars().next()?;
+
+    if quote == '"' || quote == '\'' {
+        value[1..].split(quote).next().map(decode_entities)
+    } else {
+        value.split_whitespace().next().map(decode_entities)
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#039;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
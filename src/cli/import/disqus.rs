@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node};
+use sqlx::SqlitePool;
+
+use crate::{
+    api::comments::get_markdown,
+    cli::{export::{ExportedComment, ExportedPage}, ThirdPartyImportArgs},
+};
+
+use super::{html_to_markdown, import_parsed, nest, token_for, url_path, FlatComment, Parsed};
+
+const DSQ: &str = "http://disqus.com/disqus-internals";
+
+struct Thread {
+    link: String,
+    title: Option<String>,
+    closed: bool,
+}
+
+struct Post {
+    id: String,
+    thread_id: String,
+    parent_id: Option<String>,
+    message: String,
+    created_at: DateTime<Utc>,
+    deleted: bool,
+    approved: bool,
+    name: String,
+    username: Option<String>,
+}
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(|t| t.trim().to_string())
+}
+
+fn flag(node: Node, name: &str) -> bool {
+    text(node, name).is_some_and(|t| t == "true")
+}
+
+fn dsq_id(node: Node) -> Option<String> {
+    node.attribute((DSQ, "id")).map(String::from)
+}
+
+fn parse(xml: &str) -> anyhow::Result<(HashMap<String, Thread>, Vec<Post>, usize)> {
+    let doc = Document::parse(xml).context("Invalid Disqus export")?;
+    let mut threads = HashMap::new();
+    let mut posts = vec![];
+    let mut spam = 0;
+
+    for node in doc.root_element().children().filter(|n| n.is_element()) {
+        match node.tag_name().name() {
+            "thread" => {
+                let Some(id) = dsq_id(node) else { continue };
+                threads.insert(id, Thread {
+                    link: text(node, "link").unwrap_or_else(|| String::from("/")),
+                    title: text(node, "title").filter(|t| !t.is_empty()),
+                    closed: flag(node, "isClosed"),
+                });
+            },
+            "post" => {
+                if flag(node, "isSpam") {
+                    spam += 1;
+                    continue
+                }
+
+                let (Some(id), Some(thread_id)) = (dsq_id(node), child(node, "thread").and_then(dsq_id)) else { continue };
+                let created_at = text(node, "createdAt").context("Post without a createdAt date")?;
+                let author = child(node, "author");
+                let anonymous = author.is_none_or(|a| flag(a, "isAnonymous"));
+
+                posts.push(Post {
+                    id,
+                    thread_id,
+                    parent_id: child(node, "parent").and_then(dsq_id),
+                    message: text(node, "message").unwrap_or_default(),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)
+                        .with_context(|| format!("Invalid date {}", created_at))?
+                        .with_timezone(&Utc),
+                    deleted: flag(node, "isDeleted"),
+                    approved: text(node, "isApproved").is_none_or(|t| t != "false"),
+                    name: author
+                        .and_then(|a| text(a, "name"))
+                        .filter(|n| !n.is_empty())
+                        .unwrap_or_else(|| String::from("Anonymous")),
+                    username: author.and_then(|a| text(a, "username")).filter(|_| !anonymous),
+                });
+            },
+            _ => (),
+        }
+    }
+
+    posts.sort_by_key(|p| p.created_at);
+
+    Ok((threads, posts, spam))
+}
+
+fn flat_comment(post: &Post) -> anyhow::Result<FlatComment> {
+    let body = html_to_markdown(&post.message);
+
+    Ok(FlatComment {
+        id: post.id.clone(),
+        parent_id: post.parent_id.clone(),
+        comment: ExportedComment {
+            html_body: get_markdown(&body).with_context(|| format!("Couldn't render post {}", post.id))?,
+            body,
+            name: post.name.clone(),
+            avatar: post.username.as_ref().map(|u| format!("https://disqus.com/api/users/avatars/{}.jpg", u)),
+            reviewed: post.approved,
+            moderator: false,
+            op: false,
+            shadowed: false,
+            signed_name: None,
+            hidden: false,
+            hidden_from_author: false,
+            created_at: post.created_at,
+            updated_at: post.created_at,
+            deleted_at: if post.deleted { Some(post.created_at) } else { None },
+            token: token_for("disqus", &post.id),
+            replies: vec![],
+        },
+    })
+}
+
+/// Maps Disqus threads to pages and their posts to comments
+fn pages(threads: &HashMap<String, Thread>, posts: &[Post]) -> anyhow::Result<Vec<ExportedPage>> {
+    let mut by_thread: HashMap<&str, Vec<&Post>> = HashMap::new();
+    for post in posts {
+        by_thread.entry(&post.thread_id).or_default().push(post);
+    }
+
+    // several threads (e.g. http and https links) can point to the same page
+    let mut pages: BTreeMap<String, ExportedPage> = BTreeMap::new();
+
+    for (thread_id, thread_posts) in by_thread {
+        let Some(thread) = threads.get(thread_id) else { continue };
+
+        let comments = thread_posts.into_iter().map(flat_comment).collect::<anyhow::Result<Vec<_>>>()?;
+
+        let path = url_path(&thread.link);
+        let page = pages.entry(path.clone()).or_insert_with(|| ExportedPage {
+            path,
+            title: thread.title.clone(),
+            locked: thread.closed,
+            comments: vec![],
+        });
+        page.comments.extend(nest(comments));
+    }
+
+    Ok(pages.into_values().collect())
+}
+
+fn read(file: &str) -> anyhow::Result<Parsed> {
+    let xml = std::fs::read_to_string(file).with_context(|| format!("Couldn't read {}", file))?;
+    let (threads, posts, spam) = parse(&xml)?;
+
+    Ok(Parsed { pages: pages(&threads, &posts)?, spam })
+}
+
+pub async fn import(db: &SqlitePool, args: ThirdPartyImportArgs) {
+    let parsed = read(&args.file);
+    import_parsed(db, &args, "Disqus", parsed).await
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{query_as, sqlite::SqliteConnectOptions, FromRow, SqlitePool};
+
+use crate::{
+    api::comments::get_markdown,
+    cli::{export::{ExportedComment, ExportedPage}, ThirdPartyImportArgs},
+};
+
+use super::{import_parsed, nest, token_for, FlatComment, Parsed};
+
+/// Isso moderation modes
+const ACCEPTED: i64 = 1;
+const PENDING: i64 = 2;
+const DELETED: i64 = 4;
+
+#[derive(FromRow)]
+struct IssoComment {
+    id: i64,
+    parent: Option<i64>,
+    created: f64,
+    modified: Option<f64>,
+    mode: i64,
+    text: Option<String>,
+    author: Option<String>,
+    uri: String,
+    title: Option<String>,
+}
+
+fn timestamp(seconds: f64) -> DateTime<Utc> {
+    let date = NaiveDateTime::from_timestamp_opt(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32)
+        .unwrap_or_default();
+    DateTime::from_utc(date, Utc)
+}
+
+fn flat_comment(c: &IssoComment) -> anyhow::Result<FlatComment> {
+    // comments are already written in markdown
+    let body = c.text.clone().unwrap_or_default();
+    let created_at = timestamp(c.created);
+    let updated_at = c.modified.map_or(created_at, timestamp);
+
+    Ok(FlatComment {
+        id: c.id.to_string(),
+        parent_id: c.parent.map(|p| p.to_string()),
+        comment: ExportedComment {
+            html_body: get_markdown(&body).with_context(|| format!("Couldn't render comment {}", c.id))?,
+            body,
+            name: c.author.clone().filter(|a| !a.is_empty()).unwrap_or_else(|| String::from("Anonymous")),
+            avatar: None,
+            reviewed: c.mode != PENDING,
+            moderator: false,
+            op: false,
+            shadowed: false,
+            signed_name: None,
+            hidden: false,
+            hidden_from_author: false,
+            created_at,
+            updated_at,
+            deleted_at: if c.mode == DELETED { Some(updated_at) } else { None },
+            token: token_for("isso", &c.id.to_string()),
+            replies: vec![],
+        },
+    })
+}
+
+async fn read(file: &str) -> anyhow::Result<Parsed> {
+    let options = SqliteConnectOptions::new().filename(file).read_only(true);
+    let isso = SqlitePool::connect_with(options).await.with_context(|| format!("Couldn't open {}", file))?;
+
+    let comments = query_as::<_, IssoComment>(
+        r#"
+        SELECT c.id, c.parent, c.created, c.modified, c.mode, c.text, c.author, t.uri, t.title
+        FROM comments c
+        INNER JOIN threads t ON t.id = c.tid
+        WHERE c.mode IN (?, ?, ?)
+        ORDER BY c.created
+        "#
+    )
+        .bind(ACCEPTED)
+        .bind(PENDING)
+        .bind(DELETED)
+        .fetch_all(&isso)
+        .await
+        .context("Invalid Isso database")?;
+
+    isso.close().await;
+
+    let mut by_uri: BTreeMap<&str, Vec<&IssoComment>> = BTreeMap::new();
+    for c in &comments {
+        by_uri.entry(&c.uri).or_default().push(c);
+    }
+
+    let mut pages = vec![];
+    for (uri, thread) in by_uri {
+        let flat = thread.iter().map(|c| flat_comment(c)).collect::<anyhow::Result<Vec<_>>>()?;
+
+        pages.push(ExportedPage {
+            path: uri.to_string(),
+            title: thread[0].title.clone().filter(|t| !t.is_empty()),
+            locked: false,
+            comments: nest(flat),
+        });
+    }
+
+    // Isso doesn't keep spam, it's either accepted or waiting for moderation
+    Ok(Parsed { pages, spam: 0 })
+}
+
+pub async fn import(db: &SqlitePool, args: ThirdPartyImportArgs) {
+    let parsed = read(&args.file).await;
+    import_parsed(db, &args, "Isso", parsed).await
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use roxmltree::{Document, Node};
+use sqlx::SqlitePool;
+
+use crate::{
+    api::comments::get_markdown,
+    cli::{export::{ExportedComment, ExportedPage}, ThirdPartyImportArgs},
+};
+
+use super::{html_to_markdown, import_parsed, nest, token_for, url_path, FlatComment, Parsed};
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(|t| t.trim().to_string())
+}
+
+/// Comment dates are in the "YYYY-MM-DD HH:MM:SS" format. Unpublished posts
+/// may have a zeroed GMT date, in which case the local one is used instead
+fn date(node: Node) -> anyhow::Result<DateTime<Utc>> {
+    let date = text(node, "comment_date_gmt")
+        .filter(|d| !d.starts_with("0000"))
+        .or_else(|| text(node, "comment_date"))
+        .context("Comment without a date")?;
+
+    let date = NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M:%S")
+        .with_context(|| format!("Invalid date {}", date))?;
+
+    Ok(DateTime::from_utc(date, Utc))
+}
+
+/// Converts a comment to a flat comment, or returns None if it's
+/// spam or a pingback, neither of which are worth importing
+fn flat_comment(node: Node, post_id: &str) -> anyhow::Result<Option<FlatComment>> {
+    let kind = text(node, "comment_type").unwrap_or_default();
+    if kind == "pingback" || kind == "trackback" { return Ok(None) }
+
+    let status = text(node, "comment_approved").unwrap_or_default();
+    if status == "spam" { return Ok(None) }
+
+    let id = text(node, "comment_id").context("Comment without an id")?;
+    let created_at = date(node)?;
+    // WordPress separates paragraphs with blank lines and adds the tags when rendering
+    let body = html_to_markdown(&text(node, "comment_content").unwrap_or_default());
+
+    Ok(Some(FlatComment {
+        parent_id: text(node, "comment_parent").filter(|p| p != "0"),
+        comment: ExportedComment {
+            html_body: get_markdown(&body).with_context(|| format!("Couldn't render comment {}", id))?,
+            body,
+            name: text(node, "comment_author")
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| String::from("Anonymous")),
+            avatar: None,
+            reviewed: status != "0",
+            moderator: false,
+            op: false,
+            shadowed: false,
+            signed_name: None,
+            hidden: false,
+            hidden_from_author: false,
+            created_at,
+            updated_at: created_at,
+            deleted_at: if status == "trash" { Some(created_at) } else { None },
+            token: token_for("wordpress", &format!("{}:{}", post_id, id)),
+            replies: vec![],
+        },
+        id,
+    }))
+}
+
+fn read(file: &str) -> anyhow::Result<Parsed> {
+    let xml = std::fs::read_to_string(file).with_context(|| format!("Couldn't read {}", file))?;
+    let doc = Document::parse(&xml).context("Invalid WordPress export")?;
+    let channel = child(doc.root_element(), "channel").context("Invalid WordPress export")?;
+
+    let mut pages: BTreeMap<String, ExportedPage> = BTreeMap::new();
+    let mut spam = 0;
+
+    for item in channel.children().filter(|n| n.has_tag_name("item")) {
+        let post_id = text(item, "post_id").unwrap_or_default();
+        let mut comments = vec![];
+
+        for node in item.children().filter(|n| n.has_tag_name("comment")) {
+            if text(node, "comment_approved").is_some_and(|s| s == "spam") { spam += 1 }
+            comments.extend(flat_comment(node, &post_id)?);
+        }
+
+        if comments.is_empty() { continue }
+        comments.sort_by_key(|c| c.comment.created_at);
+
+        let path = url_path(&text(item, "link").unwrap_or_else(|| String::from("/")));
+        let page = pages.entry(path.clone()).or_insert_with(|| ExportedPage {
+            path,
+            title: text(item, "title").filter(|t| !t.is_empty()),
+            locked: text(item, "comment_status").is_some_and(|s| s == "closed"),
+            comments: vec![],
+        });
+        page.comments.extend(nest(comments));
+    }
+
+    Ok(Parsed { pages: pages.into_values().collect(), spam })
+}
+
+pub async fn import(db: &SqlitePool, args: ThirdPartyImportArgs) {
+    let parsed = read(&args.file);
+    import_parsed(db, &args, "WordPress", parsed).await
+}
@@ -1,6 +1,7 @@
 use sqlx::SqlitePool;
 
-use crate::db::moderators::{self, Moderator, all, insert_moderator, find_by_name, delete};
+use crate::totp;
+use crate::db::{moderators::{self, Moderator, all, insert_moderator, find_by_name, delete}, sessions::{self, Session}, sites, login_failures::{self, LoginFailure}};
 
 use super::{ModeratorsAddCommandArgs, ModeratorsUpdateCommandArgs};
 
@@ -10,13 +11,27 @@ pub async fn list(db: &SqlitePool) {
         Ok(moderators) => {
             println!("Moderators: Found {}", moderators.len());
             for moderator in moderators {
-                print_moderator(moderator);
+                print_moderator(db, moderator).await;
             }
         }
     }
 }
 
+/// Returns the first of the given sites which doesn't exist
+async fn missing_site<'a>(db: &SqlitePool, names: &'a [String]) -> Option<&'a str> {
+    for name in names {
+        if sites::find(db, name).await.is_err() { return Some(name) }
+    }
+    None
+}
+
 pub async fn create(db: &SqlitePool, moderator: ModeratorsAddCommandArgs) {
+    if let Some(site) = missing_site(db, &moderator.sites).await {
+        return println!("Site {} not found. Try adding it first:\n$ besedka site add {}", site, site)
+    }
+
+    let sites = moderator.sites.clone();
+
     match insert_moderator(db, moderator).await {
         Err(error) => {
             match error {
@@ -26,9 +41,15 @@ pub async fn create(db: &SqlitePool, moderator: ModeratorsAddCommandArgs) {
                 _ => println!("{}", error),
             }
         },
-        Ok(result) => {
+        Ok(mut result) => {
+            if !sites.is_empty() {
+                if let Err(e) = moderators::set_sites(db, &result.name, &sites).await {
+                    return println!("{}", e)
+                }
+                result.all_sites = false;
+            }
             println!("Success!");
-            print_moderator(result);
+            print_moderator(db, result).await;
         }
     }
 }
@@ -46,29 +67,203 @@ pub async fn remove(db: &SqlitePool, name: &str) {
 }
 
 pub async fn update(db: &SqlitePool, args: ModeratorsUpdateCommandArgs) {
+    if let Some(site) = missing_site(db, &args.sites).await {
+        return println!("Site {} not found. Try adding it first:\n$ besedka site add {}", site, site)
+    }
+
     match find_by_name(db, &args.name).await {
         Err(_) => {
             println!("Moderator {} not found.", &args.name)
         },
         Ok(_) => {
-            let updated = moderators::update(&db, &args.name, args.op, args.avatar, args.password).await;
+            if !args.sites.is_empty() || args.all_sites == Some(true) {
+                if let Err(e) = moderators::set_sites(db, &args.name, &args.sites).await {
+                    return println!("{}", e)
+                }
+            }
+            let updated = moderators::update(&db, &args.name, args.op, args.avatar, args.password, args.role).await;
             println!("Success!");
-            print_moderator(updated.unwrap());
+            print_moderator(db, updated.unwrap()).await;
+        }
+    }
+}
+
+pub async fn sessions(db: &SqlitePool, name: &str, revoke: Option<i64>, revoke_all: bool) {
+    if find_by_name(db, name).await.is_err() {
+        return println!("Moderator {} not found.", name)
+    }
+
+    if revoke_all {
+        return match sessions::delete_for_moderator(db, name).await {
+            Err(e) => println!("{}", e),
+            Ok(count) => println!("Logged {} out of {} session(s)", name, count),
+        }
+    }
+
+    if let Some(id) = revoke {
+        return match sessions::find(db, id).await {
+            Ok(session) if session.moderator == name => match sessions::delete(db, id).await {
+                Err(e) => println!("{}", e),
+                Ok(_) => println!("Logged {} out of session {}", name, id),
+            },
+            _ => println!("Session {} not found.", id),
+        }
+    }
+
+    match sessions::for_moderator(db, name).await {
+        Err(e) => println!("{}", e),
+        Ok(sessions) => {
+            println!("Found {} session(s)", sessions.len());
+            for session in sessions {
+                print_session(&session);
+            }
         }
     }
 }
 
-fn print_moderator(moderator: Moderator) {
+fn print_session(session: &Session) {
+    let heading = format!("#{} {}", session.id, session.user_agent.as_deref().unwrap_or("unknown device"));
+
+    println!(r#"
+{}
+{}
+logged in:           {}
+last seen:           {}
+expires:             {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        session.created_at.format("%Y-%m-%d %H:%M:%S"),
+        session.last_seen_at.format("%Y-%m-%d %H:%M:%S"),
+        session.expires_at.format("%Y-%m-%d %H:%M:%S"),
+    )
+}
+
+async fn print_moderator(db: &SqlitePool, moderator: Moderator) {
+    let sites = match moderator.all_sites {
+        true => String::from("all"),
+        false => moderators::sites(db, &moderator.name).await.unwrap_or_default().join(", "),
+    };
+
     println!(r#"
 {}
 {}
 op: {}
+role: {}
 avatar: {}
+sites: {}
+2fa: {}
 "#,
         moderator.name,
         "-".repeat(moderator.name.len()),
         moderator.op,
-        match moderator.avatar { Some(a) => a, None => String::from("false") }
+        moderator.role,
+        match moderator.avatar { Some(a) => a, None => String::from("false") },
+        sites,
+        if moderator.totp_secret.is_some() { "enabled" } else { "disabled" },
     )
 }
 
+pub async fn lockouts(db: &SqlitePool, name: Option<&str>, ip: Option<&str>, clear: bool) {
+    let key = name.map(|name| ("name", name)).or(ip.map(|ip| ("ip", ip)));
+
+    if clear {
+        let result = match key {
+            Some((kind, value)) => login_failures::clear(db, kind, value).await,
+            None => login_failures::clear_all(db).await,
+        };
+
+        return match result {
+            Err(e) => println!("{}", e),
+            Ok(count) => println!("Cleared {} lockout(s)", count),
+        }
+    }
+
+    let result = match key {
+        Some((kind, value)) => login_failures::find(db, kind, value).await.map(|f| f.into_iter().collect()),
+        None => login_failures::locked(db).await,
+    };
+
+    match result {
+        Err(e) => println!("{}", e),
+        Ok(failures) => {
+            println!("Failed logins: Found {}", failures.len());
+            for failure in failures {
+                print_login_failure(&failure);
+            }
+        }
+    }
+}
+
+fn print_login_failure(failure: &LoginFailure) {
+    let heading = format!("{} {}", failure.kind, failure.value);
+    let locked_until = failure.locked_until
+        .filter(|until| *until > chrono::Utc::now())
+        .map_or("not locked".to_string(), |until| until.format("%Y-%m-%d %H:%M:%S").to_string());
+
+    println!(r#"
+{}
+{}
+failed logins:       {}
+last failed:         {}
+locked until:        {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        failure.failures,
+        failure.last_failed_at.format("%Y-%m-%d %H:%M:%S"),
+        locked_until,
+    )
+}
+
+pub async fn enable_two_factor(db: &SqlitePool, name: &str) {
+    let moderator = match find_by_name(db, name).await {
+        Err(_) => return println!("Moderator {} not found.", name),
+        Ok(moderator) => moderator,
+    };
+
+    if moderator.totp_secret.is_some() {
+        println!("Two-factor authentication was already enabled for {}, the old codes won't work anymore.", name);
+    }
+
+    let secret = totp::generate_secret();
+    match moderators::enable_two_factor(db, name, &secret).await {
+        Err(e) => println!("{}", e),
+        Ok(codes) => {
+            println!("Enabled two-factor authentication for {}. Add this URI to an authenticator app:\n", name);
+            println!("{}", totp::uri(&secret, name));
+            print_recovery_codes(&codes);
+        }
+    }
+}
+
+pub async fn disable_two_factor(db: &SqlitePool, name: &str) {
+    if find_by_name(db, name).await.is_err() {
+        return println!("Moderator {} not found.", name)
+    }
+
+    match moderators::disable_two_factor(db, name).await {
+        Err(e) => println!("{}", e),
+        Ok(_) => println!("Disabled two-factor authentication for {}", name),
+    }
+}
+
+pub async fn recovery_codes(db: &SqlitePool, name: &str) {
+    match find_by_name(db, name).await {
+        Err(_) => return println!("Moderator {} not found.", name),
+        Ok(moderator) if moderator.totp_secret.is_none() => {
+            return println!("Two-factor authentication is not enabled for {}. Try enabling it first:\n$ besedka moderators 2fa enable {}", name, name)
+        },
+        Ok(_) => (),
+    }
+
+    match moderators::regenerate_recovery_codes(db, name).await {
+        Err(e) => println!("{}", e),
+        Ok(codes) => print_recovery_codes(&codes),
+    }
+}
+
+fn print_recovery_codes(codes: &[String]) {
+    println!("\nRecovery codes, each of which can be used once instead of a code from the app:\n");
+    for code in codes {
+        println!("{}", code);
+    }
+}
@@ -68,6 +68,20 @@ secret:              {}
 private:             {}
 anonymous:           {}
 moderated:           {}
+max depth:           {}
+public revisions:    {}
+rate limit:          {}
+max links:           {}
+banned words:        {}
+honeypot:            {}
+min submit time:     {}
+akismet:             {}
+smtp:                {}
+email from:          {}
+moderator emails:    {}
+reply notifications: {}
+default sort:        {}
+report threshold:    {}
 "#,
         cfg.site,
         "-".repeat(cfg.site.len()),
@@ -75,5 +89,34 @@ moderated:           {}
         cfg.private,
         cfg.anonymous,
         cfg.moderated,
+        cfg.max_depth,
+        cfg.public_revisions,
+        rate_limit(cfg),
+        spam_filter(cfg.max_links > 0, &cfg.max_links, &cfg.max_links_action),
+        spam_filter(!cfg.banned_words.is_empty(), &cfg.banned_words, &cfg.banned_words_action),
+        spam_filter(cfg.honeypot, &"enabled", &cfg.honeypot_action),
+        spam_filter(cfg.min_submit_time > 0, &format!("{}s", cfg.min_submit_time), &cfg.min_submit_time_action),
+        spam_filter(!cfg.akismet_key.is_empty(), &cfg.akismet_url, &cfg.akismet_action),
+        if cfg.smtp_url.is_empty() { "disabled" } else { "enabled" },
+        cfg.email_from,
+        cfg.moderator_emails,
+        cfg.reply_notifications,
+        cfg.default_sort,
+        report_threshold(cfg),
     );
 }
+
+fn rate_limit(cfg: &Site) -> String {
+    if cfg.rate_limit == 0 { return String::from("disabled") }
+    format!("{} comment(s) per {} second(s)", cfg.rate_limit, cfg.rate_limit_window)
+}
+
+fn report_threshold(cfg: &Site) -> String {
+    if cfg.report_threshold == 0 { return String::from("disabled") }
+    format!("{} report(s)", cfg.report_threshold)
+}
+
+fn spam_filter(enabled: bool, setting: &dyn std::fmt::Display, action: &str) -> String {
+    if !enabled { return String::from("disabled") }
+    format!("{} ({})", setting, action)
+}
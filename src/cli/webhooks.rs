@@ -0,0 +1,100 @@
+use sqlx::SqlitePool;
+
+use crate::{db::{sites, webhooks::{self, Delivery, Webhook}}, queue::MAX_ATTEMPTS};
+
+use super::WebhooksAddCommandArgs;
+
+pub async fn list(db: &SqlitePool, site: Option<&str>) {
+    match webhooks::all(db, site).await {
+        Err(e) => println!("{}", e),
+        Ok(hooks) => {
+            println!("Found {} webhook(s)", hooks.len());
+            for hook in hooks {
+                print_webhook(&hook);
+            }
+        }
+    }
+}
+
+pub async fn create(db: &SqlitePool, args: WebhooksAddCommandArgs) {
+    if sites::find(db, &args.site).await.is_err() {
+        return println!("Site {} not found. Try adding it first:\n$ besedka site add {}", &args.site, &args.site)
+    }
+
+    let events: Vec<&str> = args.events.iter().map(|e| e.as_str()).collect();
+
+    match webhooks::create(db, &args.site, &args.url, &events.join(",")).await {
+        Err(e) => println!("{}", e),
+        Ok(hook) => {
+            println!("Success!");
+            print_webhook(&hook);
+        }
+    }
+}
+
+pub async fn remove(db: &SqlitePool, id: i64) {
+    match webhooks::find(db, id).await {
+        Err(_) => println!("Webhook {} not found.", id),
+        Ok(hook) => match webhooks::delete(db, id).await {
+            Err(e) => println!("{}", e),
+            Ok(_) => println!("Deleted webhook {} for site {}", id, hook.site),
+        },
+    }
+}
+
+pub async fn deliveries(db: &SqlitePool, id: i64, limit: i64) {
+    if webhooks::find(db, id).await.is_err() {
+        return println!("Webhook {} not found.", id)
+    }
+
+    match webhooks::deliveries(db, id, limit).await {
+        Err(e) => println!("{}", e),
+        Ok(deliveries) => {
+            println!("Showing the last {} deliveries", deliveries.len());
+            for delivery in deliveries {
+                print_delivery(&delivery);
+            }
+        }
+    }
+}
+
+fn print_webhook(hook: &Webhook) {
+    let heading = format!("#{} {}", hook.id, hook.url);
+
+    println!(r#"
+{}
+{}
+site:                {}
+events:              {}
+created:             {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        hook.site,
+        if hook.events.is_empty() { "all" } else { &hook.events },
+        hook.created_at.format("%Y-%m-%d %H:%M:%S"),
+    )
+}
+
+fn print_delivery(delivery: &Delivery) {
+    let state = match delivery.delivered_at {
+        Some(at) => format!("delivered {}", at.format("%Y-%m-%d %H:%M:%S")),
+        None if delivery.attempts >= MAX_ATTEMPTS => String::from("failed"),
+        None => format!("pending, next attempt {}", delivery.send_after.format("%Y-%m-%d %H:%M:%S")),
+    };
+    let heading = format!("#{} {} {}", delivery.id, delivery.event, state);
+
+    println!(r#"
+{}
+{}
+created:             {}
+attempts:            {}
+status:              {}
+error:               {}"#,
+        heading,
+        "-".repeat(heading.len()),
+        delivery.created_at.format("%Y-%m-%d %H:%M:%S"),
+        delivery.attempts,
+        delivery.last_status.map_or(String::from("-"), |s| s.to_string()),
+        delivery.last_error.as_deref().unwrap_or("-"),
+    )
+}
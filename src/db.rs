@@ -2,5 +2,15 @@ pub mod comments;
 pub mod pages;
 pub mod sites;
 pub mod moderators;
+pub mod audit;
+pub mod emails;
+pub mod webhooks;
+pub mod reactions;
+pub mod sessions;
+pub mod login_failures;
+pub mod bans;
+pub mod reports;
+pub mod akismet;
+pub mod queue;
 
 const UTC_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, query};
+
+/// A moderator's decision about a comment, waiting
+/// in the queue to be sent to Akismet or already sent
+#[derive(FromRow, Debug)]
+pub struct Submission {
+    pub id: i64,
+    pub site: String,
+    pub spam: bool,
+    pub comment: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub send_after: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Adds a submission to the queue to be sent as soon as possible
+pub async fn enqueue(db: &SqlitePool, site: &str, spam: bool, comment: &str) -> sqlx::Result<()> {
+    query!(
+        "INSERT INTO akismet_submissions (site, spam, comment) VALUES (?, ?, ?)",
+        site, spam, comment
+    ).execute(db).await?;
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, Sqlite, SqlitePool, FromRow, query_as, query};
+
+use super::UTC_DATETIME_FORMAT;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Approve,
+    Unapprove,
+    Update,
+    Delete,
+    Restore,
+    Lock,
+    Unlock,
+    Spam,
+    Ham,
+    Ban,
+    Unban,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Approve => "approve",
+            Self::Unapprove => "unapprove",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+            Self::Lock => "lock",
+            Self::Unlock => "unlock",
+            Self::Spam => "spam",
+            Self::Ham => "not_spam",
+            Self::Ban => "ban",
+            Self::Unban => "unban",
+        }
+    }
+}
+
+#[derive(FromRow, Debug)]
+pub struct Event {
+    pub id: i64,
+    pub site: String,
+    pub actor: String,
+    pub moderator: bool,
+    pub action: String,
+    pub comment_id: Option<i64>,
+    pub page_id: Option<i64>,
+    /// What the event was about when it wasn't a comment or a page,
+    /// like the kind and value of a ban
+    pub target: Option<String>,
+    pub body_before: Option<String>,
+    pub body_after: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whoever takes a moderating action
+#[derive(Debug, Clone, Copy)]
+pub struct Actor<'a> {
+    pub name: &'a str,
+    pub moderator: bool,
+}
+
+impl Actor<'static> {
+    /// Hides comments which enough readers reported
+    pub const REPORTS: Self = Self { name: "reports", moderator: false };
+    /// Moderates comments from the command line
+    pub const COMMAND_LINE: Self = Self { name: "command line", moderator: true };
+}
+
+/// A moderation event which is about to be recorded
+pub struct NewEvent<'a> {
+    pub site: &'a str,
+    pub actor: &'a str,
+    pub moderator: bool,
+    pub action: Action,
+    pub comment_id: Option<i64>,
+    pub page_id: Option<i64>,
+    pub target: Option<&'a str>,
+    pub body_before: Option<&'a str>,
+    pub body_after: Option<&'a str>,
+}
+
+pub async fn record<'e>(db: impl Executor<'e, Database = Sqlite>, event: NewEvent<'_>) -> sqlx::Result<()> {
+    query(
+        r#"
+            INSERT INTO moderation_events
+            (site, actor, moderator, action, comment_id, page_id, target, body_before, body_after)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(event.site)
+    .bind(event.actor)
+    .bind(event.moderator)
+    .bind(event.action.as_str())
+    .bind(event.comment_id)
+    .bind(event.page_id)
+    .bind(event.target)
+    .bind(event.body_before)
+    .bind(event.body_after)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns recorded events, newest first, optionally
+/// narrowed down by site, actor and a date range
+pub async fn search(
+    db: &SqlitePool,
+    site: Option<&str>,
+    actor: Option<&str>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> sqlx::Result<Vec<Event>> {
+    let mut select = String::from("SELECT * FROM moderation_events WHERE 1 = 1");
+
+    if site.is_some() { select.push_str(" AND site = ?") }
+    if actor.is_some() { select.push_str(" AND actor = ?") }
+    if since.is_some() { select.push_str(" AND created_at >= ?") }
+    if until.is_some() { select.push_str(" AND created_at < ?") }
+
+    select.push_str(" ORDER BY created_at DESC, id DESC");
+
+    let mut results = query_as::<_, Event>(&select);
+
+    if let Some(s) = site { results = results.bind(s) }
+    if let Some(a) = actor { results = results.bind(a) }
+    if let Some(d) = since { results = results.bind(format!("{}", d.format(UTC_DATETIME_FORMAT))) }
+    if let Some(d) = until { results = results.bind(format!("{}", d.format(UTC_DATETIME_FORMAT))) }
+
+    results.fetch_all(db).await
+}
@@ -0,0 +1,191 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqlitePool, FromRow, query_as, query};
+
+use super::UTC_DATETIME_FORMAT;
+
+/// What a ban matches commenters on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Kind {
+    /// Name of a signed user
+    Name,
+    /// Token of an anonymous commenter
+    Token,
+    /// IP address or CIDR range
+    Ip,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Token => "token",
+            Self::Ip => "ip",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "token" => Ok(Self::Token),
+            "ip" => Ok(Self::Ip),
+            _ => Err(format!("{} is not name, token or ip", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Stops commenters from posting to a site
+#[derive(FromRow, Debug, Serialize)]
+pub struct Ban {
+    pub id: i64,
+    pub site: String,
+    pub kind: Kind,
+    /// A signed user name, a base64 commenter token,
+    /// or an IP address or CIDR range
+    pub value: String,
+    pub reason: Option<String>,
+    pub created_by: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Lets the commenters post, but nobody else sees their comments
+    pub shadow: bool,
+}
+
+/// A ban which is about to be added
+pub struct NewBan<'a> {
+    pub site: &'a str,
+    pub kind: Kind,
+    pub value: &'a str,
+    pub reason: Option<&'a str>,
+    pub created_by: Option<&'a str>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub shadow: bool,
+}
+
+impl Ban {
+    pub fn is_active(&self) -> bool {
+        self.expires_at.is_none_or(|at| at > Utc::now())
+    }
+
+    /// Whether a commenter with the given signed user name,
+    /// token and IP address is banned by this ban
+    pub fn matches(&self, name: Option<&str>, token: Option<&str>, ip: IpAddr) -> bool {
+        match self.kind {
+            Kind::Name => name == Some(self.value.as_str()),
+            Kind::Token => token == Some(self.value.as_str()),
+            Kind::Ip => ip_range(&self.value).is_some_and(|(network, prefix)| in_range(ip, network, prefix)),
+        }
+    }
+}
+
+/// Parses an IP address or a CIDR range into the network
+/// address and prefix length. Addresses have full length prefixes
+pub fn ip_range(s: &str) -> Option<(IpAddr, u32)> {
+    let (address, prefix) = match s.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u32>().ok()?)),
+        None => (s, None),
+    };
+    let address = address.parse::<IpAddr>().ok()?;
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+
+    if prefix > max { return None }
+
+    Some((address, prefix))
+}
+
+fn in_range(ip: IpAddr, network: IpAddr, prefix: u32) -> bool {
+    match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        },
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        },
+        // IPv4 clients of servers listening on IPv6
+        (IpAddr::V4(_), IpAddr::V6(ip)) => ip
+            .to_ipv4_mapped()
+            .is_some_and(|ip| in_range(IpAddr::V4(ip), network, prefix)),
+        _ => false,
+    }
+}
+
+pub async fn create(db: &SqlitePool, ban: NewBan<'_>) -> sqlx::Result<Ban> {
+    let expires_at = ban.expires_at.map(|at| format!("{}", at.format(UTC_DATETIME_FORMAT)));
+
+    let id = query!(
+        "INSERT INTO bans (site, kind, value, reason, created_by, expires_at, shadow) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        ban.site, ban.kind, ban.value, ban.reason, ban.created_by, expires_at, ban.shadow
+    ).execute(db).await?.last_insert_rowid();
+
+    find(db, id).await
+}
+
+pub async fn find(db: &SqlitePool, id: i64) -> sqlx::Result<Ban> {
+    query_as!(
+        Ban,
+        r#"
+            SELECT id, site, kind as "kind: Kind", value, reason, created_by,
+            expires_at as "expires_at: DateTime<Utc>",
+            created_at as "created_at: DateTime<Utc>",
+            shadow
+            FROM bans WHERE id = ?
+        "#,
+        id
+    ).fetch_one(db).await
+}
+
+/// Returns all bans, or only those of a site, including expired ones
+pub async fn all(db: &SqlitePool, site: Option<&str>) -> sqlx::Result<Vec<Ban>> {
+    let mut select = String::from("SELECT * FROM bans");
+
+    if site.is_some() { select.push_str(" WHERE site = ?") }
+
+    select.push_str(" ORDER BY site, id");
+
+    let mut results = query_as::<_, Ban>(&select);
+
+    if let Some(s) = site { results = results.bind(s) }
+
+    results.fetch_all(db).await
+}
+
+/// Returns the bans of a site which haven't expired
+pub async fn active(db: &SqlitePool, site: &str) -> sqlx::Result<Vec<Ban>> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query_as!(
+        Ban,
+        r#"
+            SELECT id, site, kind as "kind: Kind", value, reason, created_by,
+            expires_at as "expires_at: DateTime<Utc>",
+            created_at as "created_at: DateTime<Utc>",
+            shadow
+            FROM bans
+            WHERE site = ? AND (expires_at IS NULL OR expires_at > ?)
+            ORDER BY id
+        "#,
+        site, now
+    ).fetch_all(db).await
+}
+
+pub async fn delete(db: &SqlitePool, id: i64) -> sqlx::Result<()> {
+    query!("DELETE FROM bans WHERE id = ?", id).execute(db).await?;
+    Ok(())
+}
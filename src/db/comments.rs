@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
-use sqlx::{SqlitePool, FromRow, query_as, query, Row};
+use sqlx::{Acquire, Sqlite, SqlitePool, FromRow, query_as, query, query_scalar, Row};
+
+use serde::{Deserialize, Serialize};
 
-use crate::api::{Base64, Result, Cursor};
+use crate::api::{Base64, Cursor, Error, Result};
 
-use super::{UTC_DATETIME_FORMAT, sites::Site};
+use super::{UTC_DATETIME_FORMAT, audit::{self, Action, Actor, NewEvent}, sites::Site};
 
 #[derive(FromRow, Clone, Debug)]
 pub struct Comment {
@@ -17,9 +21,98 @@ pub struct Comment {
     pub reviewed: bool,
     pub moderator: bool,
     pub op: bool,
+    /// Posted by a shadow banned commenter, only they can see it
+    pub shadowed: bool,
+    /// Name of the signed user who posted the comment
+    pub signed_name: Option<String>,
+    /// Taken back for review by a moderator after it was published
+    pub hidden: bool,
+    /// Hidden from its author as well
+    pub hidden_from_author: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub token: Base64,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A previous version of a comment body
+#[derive(FromRow, Clone, Debug)]
+pub struct Revision {
+    pub id: i64,
+    pub comment_id: i64,
+    pub body: String,
+    pub html_body: String,
+    pub reviewed: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a comment was posted from, kept
+/// for reporting spam to Akismet
+#[derive(FromRow, Clone, Debug)]
+pub struct Metadata {
+    pub comment_id: i64,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+}
+
+/// A comment brought over from an export or another
+/// commenting system, with all of its attributes
+pub struct ImportedComment<'a> {
+    pub page_id: i64,
+    pub parent_id: Option<i64>,
+    pub avatar: Option<&'a str>,
+    pub name: &'a str,
+    pub html_body: &'a str,
+    pub body: &'a str,
+    pub reviewed: bool,
+    pub moderator: bool,
+    pub op: bool,
+    pub shadowed: bool,
+    pub signed_name: Option<&'a str>,
+    pub hidden: bool,
+    pub hidden_from_author: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub token: &'a Base64,
+}
+
+/// Whoever is listing comments, so that they can see their
+/// own comments which are hidden from everyone else
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Owner<'a> {
+    /// Token the comments were posted with
+    pub token: Option<&'a Base64>,
+    /// Name of a signed user, which only matches
+    /// the comments of shadow banned users
+    pub name: Option<&'a str>,
+}
+
+impl Owner<'_> {
+    /// Whether the comment can be seen by this owner when
+    /// only reviewed comments are visible to everyone else
+    pub fn can_see(&self, comment: &Comment) -> bool {
+        if comment.reviewed && !comment.shadowed { return true }
+        if comment.hidden_from_author { return false }
+
+        self.token == Some(&comment.token)
+            || (comment.shadowed && self.name.is_some() && self.name == comment.signed_name.as_deref())
+    }
+}
+
+/// Limits comments to those visible to the owner, unless everything
+/// is visible: reviewed comments, except the ones by shadow banned
+/// commenters, and the owner's own comments unless they were hidden
+/// from their author
+fn visible_to(reviewed_only: bool, owner: Owner) -> String {
+    if !reviewed_only { return String::new() }
+
+    let mut conditions = vec!["(reviewed = 1 AND shadowed = 0)"];
+    if owner.token.is_some() { conditions.push("(token = ? AND hidden_from_author = 0)") }
+    if owner.name.is_some() { conditions.push("(shadowed = 1 AND signed_name = ? AND hidden_from_author = 0)") }
+
+    format!(" AND ({}) ", conditions.join(" OR "))
 }
 
 pub async fn find(db: &SqlitePool, id: i64) -> sqlx::Result<Comment> {
@@ -28,21 +121,70 @@ pub async fn find(db: &SqlitePool, id: i64) -> sqlx::Result<Comment> {
         r#"
             SELECT
             id, page_id, parent_id, avatar, name,
-            html_body, body, reviewed, moderator, op,
+            html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
             created_at as "created_at: DateTime<Utc>",
             updated_at as "updated_at: DateTime<Utc>",
-            token as "token: Base64"
-            FROM comments WHERE id = ?
+            token as "token: Base64",
+            deleted_at as "deleted_at: DateTime<Utc>"
+            FROM comments WHERE id = ? AND deleted_at IS NULL
         "#,
         id
     ).fetch_one(db).await
 }
 
+/// Finds a comment on one of the pages of a site, so that
+/// requests for one site can't touch the comments of another
+pub async fn find_on_site(db: &SqlitePool, site: &str, id: i64) -> sqlx::Result<Comment> {
+    query_as!(
+        Comment,
+        r#"
+            SELECT
+            c.id, c.page_id, c.parent_id, c.avatar, c.name,
+            c.html_body, c.body, c.reviewed, c.moderator, c.op, c.shadowed, c.signed_name, c.hidden, c.hidden_from_author,
+            c.created_at as "created_at: DateTime<Utc>",
+            c.updated_at as "updated_at: DateTime<Utc>",
+            c.token as "token: Base64",
+            c.deleted_at as "deleted_at: DateTime<Utc>"
+            FROM comments c
+            JOIN pages p ON p.id = c.page_id
+            WHERE c.id = ? AND p.site = ? AND c.deleted_at IS NULL
+        "#,
+        id, site
+    ).fetch_one(db).await
+}
+
+/// Like `find_on_site`, but only finds deleted comments
+pub async fn find_deleted_on_site(db: &SqlitePool, site: &str, id: i64) -> sqlx::Result<Comment> {
+    query_as!(
+        Comment,
+        r#"
+            SELECT
+            c.id, c.page_id, c.parent_id, c.avatar, c.name,
+            c.html_body, c.body, c.reviewed, c.moderator, c.op, c.shadowed, c.signed_name, c.hidden, c.hidden_from_author,
+            c.created_at as "created_at: DateTime<Utc>",
+            c.updated_at as "updated_at: DateTime<Utc>",
+            c.token as "token: Base64",
+            c.deleted_at as "deleted_at: DateTime<Utc>"
+            FROM comments c
+            JOIN pages p ON p.id = c.page_id
+            WHERE c.id = ? AND p.site = ? AND c.deleted_at IS NOT NULL
+        "#,
+        id, site
+    ).fetch_one(db).await
+}
+
+const APPROVE: &str = "UPDATE comments SET reviewed = 1, hidden = 0, hidden_from_author = 0 WHERE id = ?";
+
+const UNAPPROVE: &str = "UPDATE comments SET reviewed = 0, hidden = 1, hidden_from_author = ? WHERE id = ?";
+
 pub async fn approve(db: &SqlitePool, id: i64) -> sqlx::Result<()> {
     let mut tx = db.begin().await?;
 
+    let _ = query(APPROVE).bind(id).execute(&mut tx).await?;
+
+    // approved comments start over, reports about them have been dealt with
     let _ = query(
-        "UPDATE comments SET reviewed = 1 WHERE id = ?"
+        "DELETE FROM reports WHERE comment_id = ?"
     ).bind(id).execute(&mut tx).await?;
 
     tx.commit().await?;
@@ -50,26 +192,282 @@ pub async fn approve(db: &SqlitePool, id: i64) -> sqlx::Result<()> {
     Ok(())
 }
 
+/// Sends a published comment back to the unreviewed queue, hiding it
+/// from readers and, if asked to, from its author
+pub async fn unapprove(db: &SqlitePool, id: i64, from_author: bool) -> sqlx::Result<()> {
+    let _ = query(UNAPPROVE).bind(from_author).bind(id).execute(db).await?;
+
+    Ok(())
+}
+
+/// Marks a comment as deleted. The row is kept so that replies
+/// can still be displayed under a tombstone until it is purged
 pub async fn delete(db: &SqlitePool, id: i64) -> sqlx::Result<sqlx::sqlite::SqliteQueryResult> {
-    query!("DELETE FROM comments WHERE id = ?", id)
+    query!("UPDATE comments SET deleted_at = (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')) WHERE id = ?", id)
         .execute(db)
         .await
 }
 
-pub async fn find_root(db: &SqlitePool, id: i64) -> sqlx::Result<Comment> {
-    query_as!(
-        Comment,
+pub async fn restore(db: &SqlitePool, id: i64) -> sqlx::Result<sqlx::sqlite::SqliteQueryResult> {
+    query!("UPDATE comments SET deleted_at = NULL WHERE id = ?", id)
+        .execute(db)
+        .await
+}
+
+/// What a moderator can do to many comments at once
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkAction {
+    Approve,
+    Unapprove,
+    Delete,
+}
+
+/// What happened to one of the comments moderated at once
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Done,
+    /// The comment already was in the requested state
+    Unchanged,
+    /// There's no such comment on the site, or it has been deleted
+    NotFound,
+}
+
+/// A comment moderated together with others
+pub struct Moderated {
+    pub id: i64,
+    pub outcome: Outcome,
+    /// The comment as it was before the change, unless it wasn't found
+    pub comment: Option<Comment>,
+}
+
+/// Applies an action to comments of a site and records it in the audit log
+/// in a single transaction, so that an error leaves every comment as it was.
+/// Comments which aren't found or already are in the requested state are
+/// skipped and reported as such, the others are changed
+pub async fn moderate(
+    db: &SqlitePool,
+    site: &str,
+    ids: &[i64],
+    action: BulkAction,
+    actor: Actor<'_>,
+) -> sqlx::Result<Vec<Moderated>> {
+    let audited = match action {
+        BulkAction::Approve => Action::Approve,
+        BulkAction::Unapprove => Action::Unapprove,
+        BulkAction::Delete => Action::Delete,
+    };
+
+    let mut tx = db.begin().await?;
+    let mut results = vec![];
+
+    for &id in ids {
+        let comment = query_as!(
+            Comment,
+            r#"
+                SELECT
+                comments.id, page_id, parent_id, avatar, name,
+                html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
+                created_at as "created_at: DateTime<Utc>",
+                updated_at as "updated_at: DateTime<Utc>",
+                token as "token: Base64",
+                deleted_at as "deleted_at: DateTime<Utc>"
+                FROM comments
+                JOIN pages ON pages.id = comments.page_id
+                WHERE comments.id = ? AND pages.site = ? AND deleted_at IS NULL
+            "#,
+            id,
+            site
+        ).fetch_optional(&mut tx).await?;
+
+        let Some(comment) = comment else {
+            results.push(Moderated { id, outcome: Outcome::NotFound, comment: None });
+            continue
+        };
+
+        let changed = match action {
+            BulkAction::Approve if !comment.reviewed => {
+                query(APPROVE).bind(id).execute(&mut tx).await?;
+                query("DELETE FROM reports WHERE comment_id = ?").bind(id).execute(&mut tx).await?;
+                true
+            },
+            // like a single unapprove, this shows hidden comments to their author again
+            BulkAction::Unapprove if comment.reviewed || comment.hidden => {
+                query(UNAPPROVE).bind(false).bind(id).execute(&mut tx).await?;
+                comment.reviewed || comment.hidden_from_author
+            },
+            BulkAction::Delete => {
+                query("UPDATE comments SET deleted_at = (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')) WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut tx)
+                    .await?;
+                true
+            },
+            _ => false,
+        };
+
+        if changed {
+            audit::record(&mut *tx, NewEvent {
+                site,
+                actor: actor.name,
+                moderator: actor.moderator,
+                action: audited,
+                comment_id: Some(comment.id),
+                page_id: Some(comment.page_id),
+                target: None,
+                body_before: Some(&comment.body),
+                body_after: None,
+            }).await?;
+        }
+
+        let outcome = if changed { Outcome::Done } else { Outcome::Unchanged };
+        results.push(Moderated { id, outcome, comment: Some(comment) });
+    }
+
+    tx.commit().await?;
+
+    Ok(results)
+}
+
+/// Permanently removes comments deleted before the given time.
+/// Tombstones which still have replies are kept, so that purging
+/// never cascades to comments which haven't been deleted
+pub async fn purge(db: &SqlitePool, deleted_before: DateTime<Utc>) -> sqlx::Result<u64> {
+    let deleted_before = format!("{}", deleted_before.format(UTC_DATETIME_FORMAT));
+    let mut purged = 0;
+
+    loop {
+        let result = query!(
+            r#"
+                DELETE FROM comments
+                WHERE deleted_at < ?
+                AND NOT EXISTS (SELECT 1 FROM comments AS replies WHERE replies.parent_id = comments.id)
+            "#,
+            deleted_before
+        ).execute(db).await?;
+
+        if result.rows_affected() == 0 { break }
+
+        purged += result.rows_affected();
+    }
+
+    Ok(purged)
+}
+
+/// Returns how deeply a comment is nested,
+/// root comments have a depth of 0
+pub async fn depth(db: &SqlitePool, id: i64) -> sqlx::Result<i64> {
+    query(
         r#"
-            SELECT
-            id, page_id, parent_id, avatar, name,
-            html_body, body, reviewed, moderator, op,
-            created_at as "created_at: DateTime<Utc>",
-            updated_at as "updated_at: DateTime<Utc>",
-            token as "token: Base64"
-            FROM comments WHERE parent_id IS NULL AND id = ?
+            WITH RECURSIVE ancestors(id, parent_id, depth) AS (
+                SELECT id, parent_id, 0 FROM comments WHERE id = ?
+                UNION ALL
+                SELECT comments.id, comments.parent_id, ancestors.depth + 1
+                FROM comments
+                JOIN ancestors ON comments.id = ancestors.parent_id
+            )
+            SELECT max(depth) FROM ancestors
+        "#
+    )
+    .bind(id)
+    .fetch_one(db)
+    .await?
+    .try_get::<Option<i64>, _>(0)?
+    .ok_or(sqlx::Error::RowNotFound)
+}
+
+/// How comments are ordered
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    /// Most replies first, newest first among equals
+    Replies,
+    /// Most reactions first, newest first among equals
+    Top,
+}
+
+impl From<&str> for Sort {
+    fn from(sort: &str) -> Self {
+        match sort {
+            "oldest" => Self::Oldest,
+            "replies" => Self::Replies,
+            "top" => Self::Top,
+            _ => Self::Newest,
+        }
+    }
+}
+
+impl Sort {
+    /// What comments are ranked by before their age, for use in
+    /// queries on the comments table. Orders by age alone have none
+    fn score(&self) -> Option<&'static str> {
+        match self {
+            Self::Newest | Self::Oldest => None,
+            Self::Replies => Some(REPLY_COUNT),
+            Self::Top => Some(REACTION_COUNT),
+        }
+    }
+}
+
+/// Number of reactions to a comment
+const REACTION_COUNT: &str = "(SELECT count(*) FROM reactions WHERE reactions.comment_id = comments.id)";
+
+/// Number of visible direct replies to a comment
+const REPLY_COUNT: &str = r#"(
+    SELECT count(*) FROM comments AS children
+    WHERE children.parent_id = comments.id AND children.reviewed = 1
+    AND children.shadowed = 0 AND children.deleted_at IS NULL
+)"#;
+
+/// Returns the score of a comment in the given order, which
+/// is needed to pick up where a page of comments ended
+pub async fn score(db: &SqlitePool, sort: Sort, id: i64) -> sqlx::Result<Option<i64>> {
+    let Some(score) = sort.score() else { return Ok(None) };
+
+    query(&format!("SELECT {} FROM comments WHERE id = ?", score))
+        .bind(id)
+        .fetch_one(db)
+        .await?
+        .try_get(0)
+        .map(Some)
+}
+
+/// Deleted comments on a page which still have replies underneath them
+/// which are visible to the owner. They are listed as tombstones which
+/// keep those replies in place
+async fn tombstones(db: &SqlitePool, page_id: i64, reviewed_only: bool, owner: Owner<'_>) -> sqlx::Result<Vec<i64>> {
+    let select = format!(
+        r#"
+            WITH RECURSIVE live(id, parent_id, deleted) AS (
+                SELECT id, parent_id, 0 FROM comments WHERE page_id = ? AND deleted_at IS NULL {}
+                UNION
+                SELECT comments.id, comments.parent_id, 1 FROM comments
+                JOIN live ON comments.id = live.parent_id
+                WHERE comments.deleted_at IS NOT NULL
+            )
+            SELECT id FROM live WHERE deleted = 1
         "#,
-        id
-    ).fetch_one(db).await
+        visible_to(reviewed_only, owner),
+    );
+
+    let mut results = query_scalar(&select).bind(page_id);
+
+    if reviewed_only {
+        if let Some(t) = owner.token { results = results.bind(t) }
+        if let Some(n) = owner.name { results = results.bind(n) }
+    }
+
+    results.fetch_all(db).await
+}
+
+/// Limits comments to the ones which are not deleted and the given tombstones
+fn listed(tombstones: &[i64]) -> String {
+    let ids: Vec<String> = tombstones.iter().map(|id| id.to_string()).collect();
+    format!(" AND (deleted_at IS NULL OR id IN ({})) ", ids.join(","))
 }
 
 pub async fn root_comments(
@@ -77,75 +475,81 @@ pub async fn root_comments(
     page_id: i64,
     limit: i64,
     reviewed_only: bool,
-    token: &Option<Base64>,
+    owner: Owner<'_>,
     cursor: Option<Cursor>,
+    sort: Sort,
 ) -> Result<(i64, Vec<Comment>)> {
     let mut select = String::from(r#"
         SELECT
         id, page_id, parent_id, avatar, name,
-        html_body, body, reviewed, moderator, op,
-        created_at, updated_at, token
+        html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
+        created_at, updated_at, token, deleted_at
     "#);
 
     let mut count = String::from("SELECT count(*)");
 
-    let common = " FROM comments WHERE page_id = ? ";
+    let common = format!(
+        "FROM comments WHERE page_id = ? AND parent_id IS NULL {}",
+        listed(&tombstones(db, page_id, reviewed_only, owner).await?),
+    );
 
-    select.push_str(common);
-    select.push_str(" AND parent_id IS NULL ");
-    count.push_str(common);
-    count.push_str(" AND parent_id IS NULL ");
+    select.push_str(&common);
+    count.push_str(&common);
 
-    if reviewed_only {
-        if token.is_some() {
-            select.push_str(" AND (reviewed = 1 OR token = ?) ");
-            count.push_str(" AND (reviewed = 1 OR token = ?) ");
-        } else {
-            select.push_str(" AND reviewed = 1 ");
-            count.push_str(" AND reviewed = 1 ");
+    select.push_str(&visible_to(reviewed_only, owner));
+    count.push_str(&visible_to(reviewed_only, owner));
+
+    if let Some(ref cur) = cursor {
+        if cur.sort != sort || cur.score.is_some() != sort.score().is_some() {
+            return Err(Error::BadRequest("Invalid cursor"))
+        }
+
+        let after = match sort {
+            Sort::Oldest => "(created_at > ? OR (created_at = ? AND id > ?))",
+            _ => "(created_at < ? OR (created_at = ? AND id < ?))",
+        };
+
+        match sort.score() {
+            None => select.push_str(&format!(" AND {}", after)),
+            Some(score) => select.push_str(&format!(
+                " AND ({score} < ? OR ({score} = ? AND {after}))",
+                score = score,
+                after = after,
+            )),
         }
     }
 
-    let results = match cursor {
-        Some(cur) => {
-            select.push_str(r#"
-                AND (created_at < ? OR (created_at = ? AND id < ?))
-                ORDER BY created_at DESC, id DESC
-                LIMIT ?
-            "#);
-
-            let mut results = query_as::<_, Comment>(&select).bind(page_id);
-
-            if reviewed_only {
-                if let Some(t) = token { results = results.bind(t) }
-            }
-
-            results
-                .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
-                .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
-                .bind(cur.id)
-                .bind(limit)
-        },
-        None => {
-            select.push_str(r#"
-                ORDER BY created_at DESC, id DESC
-                LIMIT ?
-            "#);
+    match sort.score() {
+        None if sort == Sort::Oldest => select.push_str(" ORDER BY created_at, id LIMIT ?"),
+        None => select.push_str(" ORDER BY created_at DESC, id DESC LIMIT ?"),
+        Some(score) => select.push_str(&format!(" ORDER BY {} DESC, created_at DESC, id DESC LIMIT ?", score)),
+    }
 
-            let mut results = query_as::<_, Comment>(&select).bind(page_id);
+    let mut results = query_as::<_, Comment>(&select).bind(page_id);
 
-            if reviewed_only {
-                if let Some(t) = token { results = results.bind(t) }
-            }
+    if reviewed_only {
+        if let Some(t) = owner.token { results = results.bind(t) }
+        if let Some(n) = owner.name { results = results.bind(n) }
+    }
 
-            results.bind(limit)
+    if let Some(cur) = cursor {
+        if let Some(score) = cur.score {
+            results = results.bind(score).bind(score);
         }
-    };
+
+        results = results
+            .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
+            .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
+            .bind(cur.id);
+    }
+
+    results = results.bind(limit);
 
     let mut total = query(&count).bind(page_id);
 
     if reviewed_only {
-        if let Some(t) = token { total = total.bind(t) }
+        if let Some(t) = owner.token { total = total.bind(t) }
+        if let Some(n) = owner.name { total = total.bind(n) }
     }
 
     Ok((total.fetch_one(db).await?.get(0), results.fetch_all(db).await?))
@@ -157,14 +561,17 @@ pub async fn unreviewed(db: &SqlitePool, site: &Site) -> sqlx::Result<Vec<Commen
         r#"
             SELECT
             comments.id, page_id, parent_id, avatar, name,
-            html_body, body, reviewed, moderator, op,
+            html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
             created_at as "created_at: DateTime<Utc>",
             updated_at as "updated_at: DateTime<Utc>",
-            token as "token: Base64"
+            token as "token: Base64",
+            deleted_at as "deleted_at: DateTime<Utc>"
             FROM comments
             LEFT JOIN pages
             ON pages.id = comments.page_id
             WHERE comments.reviewed = ?
+            AND comments.shadowed = 0
+            AND comments.deleted_at IS NULL
             AND pages.site = ?
             ORDER BY created_at
         "#,
@@ -173,46 +580,176 @@ pub async fn unreviewed(db: &SqlitePool, site: &Site) -> sqlx::Result<Vec<Commen
     ).fetch_all(db).await
 }
 
+/// Replies which can be listed under a comment: ones visible to the
+/// requester, and deleted ones with something left underneath them
+fn visible_replies(tombstones: &[i64], reviewed_only: bool, owner: Owner) -> String {
+    format!("{}{}", listed(tombstones), visible_to(reviewed_only, owner))
+}
+
+/// Replies keep the flow of the conversation and are listed
+/// oldest first, unless they are ranked by replies or reactions
+fn reply_order(sort: Sort) -> String {
+    format!("{} created_at, id", sort.score().map_or(String::new(), |s| format!("{} DESC,", s)))
+}
+
+/// How many levels of replies are loaded together with the comments
+/// they reply to. Deeper ones are loaded through their parent on demand
+const NESTED_REPLY_LEVELS: i64 = 3;
+
+/// Loads the reply tree under the given parents with at most `per_parent`
+/// replies under each comment, down to `NESTED_REPLY_LEVELS` levels.
+/// Replies which are not visible to the
+/// requester are skipped together with everything nested under them.
+/// Deleted replies are included so that their replies can be displayed
+/// under a tombstone. Also returns how many replies each comment has
+/// in total, to tell whether there are more to load
 pub async fn replies(
     db: &SqlitePool,
+    page_id: i64,
     reviewed_only: bool,
-    token: &Option<Base64>,
-    parents: &Vec<Comment>,
-) -> Result<Vec<Comment>> {
-    let parent_ids: Vec<String> = parents.iter().map(|p| p.id.to_string()).collect();
-    let ids = parent_ids.join(",");
+    owner: Owner<'_>,
+    parent_ids: &[i64],
+    sort: Sort,
+    per_parent: i64,
+) -> Result<(Vec<Comment>, HashMap<i64, i64>)> {
+    if parent_ids.is_empty() { return Ok((vec![], HashMap::new())) }
+
+    let tombstones = tombstones(db, page_id, reviewed_only, owner).await?;
+    let ids: Vec<String> = parent_ids.iter().map(|id| id.to_string()).collect();
+
+    // SQLite doesn't allow window functions in the recursive part of a
+    // query, so the replies on the page are ranked among their siblings
+    // first and the tree is only walked through the ones which fit
+    let select = format!(
+        r#"
+            WITH RECURSIVE ranked AS (
+                SELECT
+                id, page_id, parent_id, avatar, name,
+                html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
+                created_at, updated_at, token, deleted_at,
+                row_number() OVER (PARTITION BY parent_id ORDER BY {order}) AS position,
+                count(*) OVER (PARTITION BY parent_id) AS siblings
+                FROM comments
+                WHERE page_id = ? AND parent_id IS NOT NULL
+                {condition}
+            ),
+            tree AS (
+                SELECT ranked.*, 1 AS level FROM ranked WHERE parent_id IN ({ids}) AND position <= ?
+                UNION ALL
+                SELECT ranked.*, tree.level + 1 FROM ranked
+                JOIN tree ON ranked.parent_id = tree.id
+                WHERE tree.level <= ?
+                AND ranked.position <= CASE WHEN tree.level < ? THEN ? ELSE 1 END
+            )
+            SELECT * FROM tree
+            ORDER BY parent_id, position
+        "#,
+        order = reply_order(sort),
+        ids = ids.join(","),
+        condition = visible_replies(&tombstones, reviewed_only, owner),
+    );
+
+    let mut results = query(&select).bind(page_id);
 
-    let mut condition = "".to_string();
     if reviewed_only {
-        if token.is_some() {
-            condition.push_str("AND (reviewed = 1 OR token = ?)");
-        } else {
-            condition.push_str("AND reviewed = 1");
+        if let Some(t) = owner.token { results = results.bind(t) }
+        if let Some(n) = owner.name { results = results.bind(n) }
+    }
+
+    // one reply is loaded a level deeper than the rest, only to count its siblings
+    let rows = results
+        .bind(per_parent)
+        .bind(NESTED_REPLY_LEVELS)
+        .bind(NESTED_REPLY_LEVELS)
+        .bind(per_parent)
+        .fetch_all(db)
+        .await?;
+
+    let mut all = vec![];
+    let mut counts = HashMap::new();
+    for row in rows {
+        let comment = Comment::from_row(&row)?;
+        if let Some(parent_id) = comment.parent_id {
+            counts.insert(parent_id, row.try_get("siblings")?);
         }
+        if row.try_get::<i64, _>("level")? <= NESTED_REPLY_LEVELS { all.push(comment) }
     }
 
-    let query = format!(
-        r#"
-            SELECT
-                id, page_id, parent_id, avatar, name,
-                html_body, body, reviewed, moderator, op,
-                created_at, updated_at, token
-            FROM comments
-            WHERE parent_id IN({ids})
-            {condition}
-            ORDER BY created_at, id
-        "#,
-        condition = condition,
-        ids = ids,
+    Ok((all, counts))
+}
+
+/// Returns a page of direct replies to a comment
+/// along with the total number of them
+pub async fn replies_page(
+    db: &SqlitePool,
+    parent: &Comment,
+    reviewed_only: bool,
+    owner: Owner<'_>,
+    cursor: Option<Cursor>,
+    sort: Sort,
+    limit: i64,
+) -> Result<(i64, Vec<Comment>)> {
+    let common = format!(
+        "FROM comments WHERE parent_id = ? {}",
+        visible_replies(&tombstones(db, parent.page_id, reviewed_only, owner).await?, reviewed_only, owner),
     );
 
-    let mut results = query_as::<_, Comment>(&query);
+    let mut select = format!(r#"
+        SELECT
+        id, page_id, parent_id, avatar, name,
+        html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
+        created_at, updated_at, token, deleted_at
+        {}
+    "#, common);
+
+    if let Some(ref cur) = cursor {
+        if cur.sort != sort || cur.score.is_some() != sort.score().is_some() {
+            return Err(Error::BadRequest("Invalid cursor"))
+        }
+
+        let after = "(created_at > ? OR (created_at = ? AND id > ?))";
+
+        match sort.score() {
+            None => select.push_str(&format!(" AND {}", after)),
+            Some(score) => select.push_str(&format!(
+                " AND ({score} < ? OR ({score} = ? AND {after}))",
+                score = score,
+                after = after,
+            )),
+        }
+    }
+
+    select.push_str(&format!(" ORDER BY {} LIMIT ?", reply_order(sort)));
+
+    let mut results = query_as::<_, Comment>(&select).bind(parent.id);
+    let count = format!("SELECT count(*) {}", common);
+    let mut total = query(&count).bind(parent.id);
 
     if reviewed_only {
-        if let Some(t) = token { results = results.bind(t) }
+        if let Some(t) = owner.token {
+            results = results.bind(t);
+            total = total.bind(t);
+        }
+        if let Some(n) = owner.name {
+            results = results.bind(n);
+            total = total.bind(n);
+        }
     }
 
-    Ok(results.fetch_all(db).await?)
+    if let Some(cur) = cursor {
+        if let Some(score) = cur.score {
+            results = results.bind(score).bind(score);
+        }
+
+        results = results
+            .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
+            .bind(format!("{}", cur.created_at.format(UTC_DATETIME_FORMAT)))
+            .bind(cur.id);
+    }
+
+    results = results.bind(limit);
+
+    Ok((total.fetch_one(db).await?.get(0), results.fetch_all(db).await?))
 }
 
 pub async fn create(
@@ -227,14 +764,16 @@ pub async fn create(
     op: bool,
     moderator: bool,
     token: &Base64,
+    shadowed: bool,
+    signed_name: Option<&str>,
 ) -> sqlx::Result<Comment> {
     let mut tx = db.begin().await?;
 
     let comment = query_as::<_, Comment>(
             r#"
                 INSERT INTO comments
-                (page_id, parent_id, avatar, name, html_body, body, reviewed, op, moderator, token)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                (page_id, parent_id, avatar, name, html_body, body, reviewed, op, moderator, token, shadowed, signed_name)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 RETURNING *
             "#
         )
@@ -248,6 +787,8 @@ pub async fn create(
         .bind(op)
         .bind(moderator)
         .bind(token)
+        .bind(shadowed)
+        .bind(signed_name)
         .fetch_one(&mut tx)
         .await?;
 
@@ -264,6 +805,15 @@ pub async fn update(
 ) -> sqlx::Result<Comment> {
     let mut tx = db.begin().await?;
 
+    // keep the current version around before overwriting it
+    query!(
+        r#"
+            INSERT INTO comment_revisions (comment_id, body, html_body, reviewed, created_at)
+            SELECT id, body, html_body, reviewed, updated_at FROM comments WHERE id = ?
+        "#,
+        id
+    ).execute(&mut tx).await?;
+
     let comment = query_as::<_, Comment>(
             r#"
                 UPDATE comments
@@ -285,3 +835,158 @@ pub async fn update(
 
     Ok(comment)
 }
+
+/// Returns all previous versions of a comment, oldest first
+pub async fn revisions(db: &SqlitePool, comment_id: i64) -> sqlx::Result<Vec<Revision>> {
+    query_as!(
+        Revision,
+        r#"
+            SELECT
+            id, comment_id, body, html_body, reviewed,
+            created_at as "created_at: DateTime<Utc>"
+            FROM comment_revisions
+            WHERE comment_id = ?
+            ORDER BY created_at, id
+        "#,
+        comment_id
+    ).fetch_all(db).await
+}
+
+/// Returns every comment on a page, including deleted ones
+pub async fn all_for_page(db: &SqlitePool, page_id: i64) -> sqlx::Result<Vec<Comment>> {
+    query_as!(
+        Comment,
+        r#"
+            SELECT
+            id, page_id, parent_id, avatar, name,
+            html_body, body, reviewed, moderator, op, shadowed, signed_name, hidden, hidden_from_author,
+            created_at as "created_at: DateTime<Utc>",
+            updated_at as "updated_at: DateTime<Utc>",
+            token as "token: Base64",
+            deleted_at as "deleted_at: DateTime<Utc>"
+            FROM comments
+            WHERE page_id = ?
+            ORDER BY created_at, id
+        "#,
+        page_id
+    ).fetch_all(db).await
+}
+
+/// Inserts a comment with all of its attributes, including timestamps.
+/// A comment on the same page with the same token and creation time
+/// is considered the same comment and is updated instead
+pub async fn upsert(db: impl Acquire<'_, Database = Sqlite>, comment: &ImportedComment<'_>) -> sqlx::Result<i64> {
+    let created_at = format!("{}", comment.created_at.format(UTC_DATETIME_FORMAT));
+    let updated_at = format!("{}", comment.updated_at.format(UTC_DATETIME_FORMAT));
+    let deleted_at = comment.deleted_at.map(|d| format!("{}", d.format(UTC_DATETIME_FORMAT)));
+
+    let mut tx = db.begin().await?;
+
+    let existing = query("SELECT id FROM comments WHERE page_id = ? AND token = ? AND created_at = ? LIMIT 1")
+        .bind(comment.page_id)
+        .bind(comment.token)
+        .bind(&created_at)
+        .fetch_optional(&mut tx)
+        .await?;
+
+    let id = match existing {
+        Some(row) => {
+            let id: i64 = row.get(0);
+            query(
+                r#"
+                    UPDATE comments SET
+                    parent_id = ?, avatar = ?, name = ?, html_body = ?, body = ?,
+                    reviewed = ?, moderator = ?, op = ?, shadowed = ?, signed_name = ?, hidden = ?, hidden_from_author = ?,
+                    updated_at = ?, deleted_at = ?
+                    WHERE id = ?
+                "#
+            )
+            .bind(comment.parent_id)
+            .bind(comment.avatar)
+            .bind(comment.name)
+            .bind(comment.html_body)
+            .bind(comment.body)
+            .bind(comment.reviewed)
+            .bind(comment.moderator)
+            .bind(comment.op)
+            .bind(comment.shadowed)
+            .bind(comment.signed_name)
+            .bind(comment.hidden)
+            .bind(comment.hidden_from_author)
+            .bind(&updated_at)
+            .bind(&deleted_at)
+            .bind(id)
+            .execute(&mut tx)
+            .await?;
+            id
+        },
+        None => {
+            query(
+                r#"
+                    INSERT INTO comments
+                    (page_id, parent_id, avatar, name, html_body, body, reviewed,
+                    moderator, op, shadowed, signed_name, hidden, hidden_from_author, created_at, updated_at, deleted_at, token)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    RETURNING id
+                "#
+            )
+            .bind(comment.page_id)
+            .bind(comment.parent_id)
+            .bind(comment.avatar)
+            .bind(comment.name)
+            .bind(comment.html_body)
+            .bind(comment.body)
+            .bind(comment.reviewed)
+            .bind(comment.moderator)
+            .bind(comment.op)
+            .bind(comment.shadowed)
+            .bind(comment.signed_name)
+            .bind(comment.hidden)
+            .bind(comment.hidden_from_author)
+            .bind(&created_at)
+            .bind(&updated_at)
+            .bind(&deleted_at)
+            .bind(comment.token)
+            .fetch_one(&mut tx)
+            .await?
+            .get(0)
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+pub async fn save_metadata(db: &SqlitePool, metadata: &Metadata) -> sqlx::Result<()> {
+    query!(
+        r#"
+            INSERT INTO comment_metadata (comment_id, ip, user_agent, referrer)
+            VALUES (?, ?, ?, ?)
+        "#,
+        metadata.comment_id,
+        metadata.ip,
+        metadata.user_agent,
+        metadata.referrer,
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+/// Forgets where comments posted before a given time came from
+pub async fn purge_metadata(db: &SqlitePool, before: DateTime<Utc>) -> sqlx::Result<()> {
+    let before = format!("{}", before.format(UTC_DATETIME_FORMAT));
+
+    query!(
+        "DELETE FROM comment_metadata WHERE comment_id IN (SELECT id FROM comments WHERE created_at < ?)",
+        before
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+pub async fn metadata(db: &SqlitePool, comment_id: i64) -> sqlx::Result<Metadata> {
+    query_as!(Metadata, "SELECT * FROM comment_metadata WHERE comment_id = ? LIMIT 1", comment_id)
+        .fetch_one(db)
+        .await
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, query};
+
+/// An email waiting in the queue or already sent
+#[derive(FromRow, Debug)]
+pub struct Email {
+    pub id: i64,
+    pub site: String,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub send_after: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Adds an email to the queue to be sent as soon as possible
+pub async fn enqueue(db: &SqlitePool, site: &str, recipient: &str, subject: &str, body: &str) -> sqlx::Result<()> {
+    query!(
+        "INSERT INTO emails (site, recipient, subject, body) VALUES (?, ?, ?, ?)",
+        site, recipient, subject, body
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+/// Remembers the address of a commenter who wants to know about replies
+pub async fn subscribe(db: &SqlitePool, comment_id: i64, email: &str) -> sqlx::Result<()> {
+    query!("INSERT INTO comment_subscriptions (comment_id, email) VALUES (?, ?)", comment_id, email)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the address to notify about replies to a comment,
+/// unless its owner has unsubscribed from the site
+pub async fn subscriber(db: &SqlitePool, site: &str, comment_id: i64) -> sqlx::Result<Option<String>> {
+    let row = query!(
+        r#"
+            SELECT s.email
+            FROM comment_subscriptions s
+            WHERE s.comment_id = ?
+            AND NOT EXISTS (SELECT 1 FROM email_unsubscribes u WHERE u.site = ? AND u.email = s.email)
+        "#,
+        comment_id, site
+    ).fetch_optional(db).await?;
+
+    Ok(row.map(|r| r.email))
+}
+
+pub async fn unsubscribe(db: &SqlitePool, site: &str, email: &str) -> sqlx::Result<()> {
+    query!("INSERT OR IGNORE INTO email_unsubscribes (site, email) VALUES (?, ?)", site, email)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
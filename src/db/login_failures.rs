@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, query_as, query};
+
+use super::UTC_DATETIME_FORMAT;
+
+/// Failed logins for a moderator name or from an IP address
+#[derive(FromRow, Debug)]
+pub struct LoginFailure {
+    /// Either "name" or "ip"
+    pub kind: String,
+    pub value: String,
+    pub failures: i64,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+pub async fn find(db: &SqlitePool, kind: &str, value: &str) -> sqlx::Result<Option<LoginFailure>> {
+    query_as!(
+        LoginFailure,
+        r#"
+            SELECT kind, value, failures,
+            last_failed_at as "last_failed_at: DateTime<Utc>",
+            locked_until as "locked_until: DateTime<Utc>"
+            FROM login_failures WHERE kind = ? AND value = ?
+        "#,
+        kind, value
+    ).fetch_optional(db).await
+}
+
+/// Returns everything which is locked out at the moment
+pub async fn locked(db: &SqlitePool) -> sqlx::Result<Vec<LoginFailure>> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query_as!(
+        LoginFailure,
+        r#"
+            SELECT kind, value, failures,
+            last_failed_at as "last_failed_at: DateTime<Utc>",
+            locked_until as "locked_until: DateTime<Utc>"
+            FROM login_failures WHERE locked_until > ?
+            ORDER BY locked_until DESC
+        "#,
+        now
+    ).fetch_all(db).await
+}
+
+/// Records a login attempt before it is checked, so that attempts made at
+/// the same time each get their own count. Returns the attempts since the
+/// last successful login along with any lockout. Attempts before
+/// `reset_before` are forgotten
+pub async fn record(db: &SqlitePool, kind: &str, value: &str, reset_before: DateTime<Utc>) -> sqlx::Result<LoginFailure> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+    let reset_before = format!("{}", reset_before.format(UTC_DATETIME_FORMAT));
+
+    query_as!(
+        LoginFailure,
+        r#"
+            INSERT INTO login_failures (kind, value, failures, last_failed_at) VALUES (?, ?, 1, ?)
+            ON CONFLICT (kind, value) DO UPDATE SET
+            failures = CASE WHEN last_failed_at < ? THEN 1 ELSE failures + 1 END,
+            last_failed_at = excluded.last_failed_at
+            RETURNING kind as "kind!", value as "value!", failures as "failures!",
+            last_failed_at as "last_failed_at!: DateTime<Utc>",
+            locked_until as "locked_until: DateTime<Utc>"
+        "#,
+        kind, value, now, reset_before
+    ).fetch_one(db).await
+}
+
+/// Locks out until the given time, unless it's locked out already.
+/// Returns false when another attempt got to lock it out first
+pub async fn lock(db: &SqlitePool, kind: &str, value: &str, until: DateTime<Utc>) -> sqlx::Result<bool> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+    let until = format!("{}", until.format(UTC_DATETIME_FORMAT));
+
+    let result = query!(
+        r#"
+            UPDATE login_failures SET locked_until = ?
+            WHERE kind = ? AND value = ? AND (locked_until IS NULL OR locked_until <= ?)
+        "#,
+        until, kind, value, now
+    ).execute(db).await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Takes back an attempt which turned out not to be a failure
+pub async fn forgive(db: &SqlitePool, kind: &str, value: &str) -> sqlx::Result<u64> {
+    Ok(
+        query!(
+            "UPDATE login_failures SET failures = failures - 1 WHERE kind = ? AND value = ? AND failures > 0",
+            kind, value
+        ).execute(db).await?.rows_affected()
+    )
+}
+
+/// Deletes failures before `reset_before` which aren't locked out any more
+pub async fn delete_stale(db: &SqlitePool, reset_before: DateTime<Utc>) -> sqlx::Result<u64> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+    let reset_before = format!("{}", reset_before.format(UTC_DATETIME_FORMAT));
+
+    Ok(
+        query!(
+            r#"
+                DELETE FROM login_failures
+                WHERE last_failed_at < ? AND (locked_until IS NULL OR locked_until <= ?)
+            "#,
+            reset_before, now
+        ).execute(db).await?.rows_affected()
+    )
+}
+
+pub async fn clear(db: &SqlitePool, kind: &str, value: &str) -> sqlx::Result<u64> {
+    Ok(query!("DELETE FROM login_failures WHERE kind = ? AND value = ?", kind, value).execute(db).await?.rows_affected())
+}
+
+pub async fn clear_all(db: &SqlitePool) -> sqlx::Result<u64> {
+    Ok(query!("DELETE FROM login_failures").execute(db).await?.rows_affected())
+}
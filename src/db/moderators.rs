@@ -1,14 +1,25 @@
-use sqlx::{query_as, SqlitePool, FromRow, query};
+use chrono::{DateTime, Utc};
+use sqlx::{query_as, SqlitePool, SqliteConnection, FromRow, query};
 use serde::Serialize;
-use crate::{cli::ModeratorsAddCommandArgs, api::{Result, Base64}};
+use crate::{cli::ModeratorsAddCommandArgs, api::Result};
+use super::UTC_DATETIME_FORMAT;
 use argon2::{
     password_hash::{
-        rand_core::OsRng,
-        PasswordHasher, SaltString
+        rand_core::{OsRng, RngCore},
+        PasswordHasher, PasswordHash, PasswordVerifier, SaltString
     },
     Argon2
 };
 
+/// Number of recovery codes given out when two-factor authentication is enabled
+const RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+/// Recovery codes start with this many characters which are saved in plain
+/// text, so that only one code has to be checked against its slow hash
+const RECOVERY_CODE_LOOKUP: usize = 5;
+/// Characters of recovery codes which are hashed
+const RECOVERY_CODE_SECRET: usize = 10;
+
 #[derive(FromRow, Debug, Serialize)]
     pub struct Moderator {
     pub name: String,
@@ -16,30 +27,23 @@ use argon2::{
     pub password: String,
     pub op: bool,
     pub avatar: Option<String>,
-    pub sid: Option<Base64>,
+    /// Whether this moderator can moderate every site,
+    /// rather than only the ones they were added to
+    pub all_sites: bool,
+    /// What the moderator is allowed to do: approver, editor or admin
+    pub role: String,
+    /// Secret for two-factor authentication codes, when it's enabled
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<Vec<u8>>,
 }
 
-/// Returns all moderators for a given site
+/// Returns all moderators
 pub async fn all(db: &SqlitePool) -> anyhow::Result<Vec<Moderator>> {
-    let users = query_as!(Moderator, r#"SELECT name, password, avatar, op, sid as "sid: Base64" FROM moderators"#)
+    let users = query_as!(Moderator, r#"SELECT name, password, avatar, op, all_sites, role, totp_secret FROM moderators"#)
         .fetch_all(db).await?;
     Ok(users)
 }
 
-impl Moderator {
-    pub async fn set_sid(&mut self, db: &SqlitePool, sid: Base64) {
-        let mut tx = db.begin().await.unwrap();
-        let name_ref = &self.name;
-        let sid_ref = &sid;
-        query!("UPDATE moderators SET sid = ? WHERE name = ?", sid_ref, name_ref)
-            .execute(&mut tx)
-            .await
-            .unwrap();
-        tx.commit().await.unwrap();
-        self.sid = Some(sid);
-    }
-}
-
 /// Inserts a moderator and returns the newly inserted row.
 /// Password is hashed with Argon2 before saving
 pub async fn insert_moderator(
@@ -56,8 +60,8 @@ pub async fn insert_moderator(
     Ok(
         query_as::<_, Moderator>(
             r#"
-                INSERT INTO moderators (name, password, avatar, op)
-                VALUES(?, ?, ?, ?);
+                INSERT INTO moderators (name, password, avatar, op, role)
+                VALUES(?, ?, ?, ?, ?);
                 SELECT * FROM moderators WHERE name = ? LIMIT 1
             "#,
         )
@@ -65,23 +69,16 @@ pub async fn insert_moderator(
         .bind(&password_hash)
         .bind(&moderator.avatar)
         .bind(&moderator.op.unwrap_or(false))
+        .bind(moderator.role.as_deref().unwrap_or("admin"))
         .bind(&moderator.name)
         .fetch_one(db)
         .await?
     )
 }
 
-pub async fn find_by_sid(db: &SqlitePool, sid: &Base64) -> Result<Moderator> {
-    Ok(
-        query_as!(Moderator, r#"SELECT name, password, avatar, op, sid as "sid: Base64" FROM moderators WHERE sid = ? LIMIT 1"#, sid)
-            .fetch_one(db)
-            .await?
-    )
-}
-
 pub async fn find_by_name(db: &SqlitePool, name: &str) -> Result<Moderator> {
     Ok(
-        query_as!(Moderator, r#"SELECT name, password, avatar, op, sid as "sid: Base64" FROM moderators WHERE name = ? LIMIT 1"#, name)
+        query_as!(Moderator, r#"SELECT name, password, avatar, op, all_sites, role, totp_secret FROM moderators WHERE name = ? LIMIT 1"#, name)
             .fetch_one(db)
             .await?
     )
@@ -93,11 +90,19 @@ pub async fn delete(db: &SqlitePool, name: &str) -> Result<sqlx::sqlite::SqliteQ
         .await
 }
 
-pub async fn update(db: &SqlitePool, name: &str, op: Option<bool>, avatar: Option<String>, password: Option<String>) -> Result<Moderator> {
+pub async fn update(
+    db: &SqlitePool,
+    name: &str,
+    op: Option<bool>,
+    avatar: Option<String>,
+    password: Option<String>,
+    role: Option<String>,
+) -> Result<Moderator> {
     let mut q = String::from("UPDATE moderators SET name = ?");
 
     if op.is_some() { q.push_str(", op = ?") }
     if avatar.is_some() { q.push_str(", avatar = ?") }
+    if role.is_some() { q.push_str(", role = ?") }
     if password.is_some() { q.push_str(", password = ?") }
 
     q.push_str(" WHERE name = ?; SELECT * FROM moderators WHERE name = ? LIMIT 1");
@@ -106,6 +111,7 @@ pub async fn update(db: &SqlitePool, name: &str, op: Option<bool>, avatar: Optio
 
     if op.is_some() { query = query.bind(op.unwrap()) }
     if avatar.is_some() { query = query.bind(avatar.unwrap()) }
+    if let Some(r) = role { query = query.bind(r) }
 
     if password.is_some() {
         let pass = password.unwrap();
@@ -118,7 +124,8 @@ pub async fn update(db: &SqlitePool, name: &str, op: Option<bool>, avatar: Optio
 
         query = query.bind(password_hash);
 
-        let _ = query!("UPDATE moderators SET sid = NULL WHERE name = ?", name).execute(db).await?;
+        // Changing the password logs the moderator out everywhere
+        let _ = query!("DELETE FROM sessions WHERE moderator = ?", name).execute(db).await?;
     }
 
     query = query.bind(name);
@@ -128,3 +135,195 @@ pub async fn update(db: &SqlitePool, name: &str, op: Option<bool>, avatar: Optio
         query.fetch_one(db).await?
     )
 }
+
+/// Returns the sites a moderator was added to
+pub async fn sites(db: &SqlitePool, name: &str) -> sqlx::Result<Vec<String>> {
+    Ok(
+        query!("SELECT site FROM moderator_sites WHERE moderator = ? ORDER BY site", name)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| row.site)
+            .collect()
+    )
+}
+
+/// Replaces the sites a moderator can moderate. No
+/// sites at all let them moderate every site
+pub async fn set_sites(db: &SqlitePool, name: &str, sites: &[String]) -> sqlx::Result<()> {
+    let mut tx = db.begin().await?;
+
+    let all_sites = sites.is_empty();
+    query!("UPDATE moderators SET all_sites = ? WHERE name = ?", all_sites, name)
+        .execute(&mut tx)
+        .await?;
+    query!("DELETE FROM moderator_sites WHERE moderator = ?", name)
+        .execute(&mut tx)
+        .await?;
+
+    for site in sites {
+        query!("INSERT INTO moderator_sites (moderator, site) VALUES (?, ?)", name, site)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Whether a moderator can moderate the given site
+pub async fn moderates(db: &SqlitePool, moderator: &Moderator, site: &str) -> sqlx::Result<bool> {
+    if moderator.all_sites { return Ok(true) }
+
+    let membership = query!(
+        "SELECT site FROM moderator_sites WHERE moderator = ? AND site = ?",
+        moderator.name, site
+    ).fetch_optional(db).await?;
+
+    Ok(membership.is_some())
+}
+
+/// Whether a moderator has logged in from the given IP address before
+pub async fn logged_in_from(db: &SqlitePool, name: &str, ip: &str) -> sqlx::Result<bool> {
+    let login = query!(
+        "SELECT ip FROM moderator_logins WHERE moderator = ? AND ip = ?",
+        name, ip
+    ).fetch_optional(db).await?;
+
+    Ok(login.is_some())
+}
+
+/// Remembers that a moderator logged in from the given IP address
+pub async fn record_login(db: &SqlitePool, name: &str, ip: &str) -> sqlx::Result<()> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query!(
+        r#"
+            INSERT INTO moderator_logins (moderator, ip, last_login_at) VALUES (?, ?, ?)
+            ON CONFLICT (moderator, ip) DO UPDATE SET last_login_at = excluded.last_login_at
+        "#,
+        name, ip, now
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+/// Forgets the IP addresses moderators haven't logged in from since `before`
+pub async fn forget_logins(db: &SqlitePool, before: DateTime<Utc>) -> sqlx::Result<u64> {
+    let before = format!("{}", before.format(UTC_DATETIME_FORMAT));
+
+    Ok(query!("DELETE FROM moderator_logins WHERE last_login_at < ?", before).execute(db).await?.rows_affected())
+}
+
+/// Turns on two-factor authentication with the given secret and logs the
+/// moderator out everywhere. Returns new recovery codes in plain text
+pub async fn enable_two_factor(db: &SqlitePool, name: &str, secret: &[u8]) -> sqlx::Result<Vec<String>> {
+    let mut tx = db.begin().await?;
+
+    query!("UPDATE moderators SET totp_secret = ?, totp_last_step = NULL WHERE name = ?", secret, name)
+        .execute(&mut tx)
+        .await?;
+    query!("DELETE FROM sessions WHERE moderator = ?", name)
+        .execute(&mut tx)
+        .await?;
+    let codes = replace_recovery_codes(&mut tx, name).await?;
+
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+pub async fn disable_two_factor(db: &SqlitePool, name: &str) -> sqlx::Result<()> {
+    query!("UPDATE moderators SET totp_secret = NULL, totp_last_step = NULL WHERE name = ?", name)
+        .execute(db)
+        .await?;
+    query!("DELETE FROM recovery_codes WHERE moderator = ?", name)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces the moderator's recovery codes and returns the new ones in
+/// plain text. Only their Argon2 hashes are saved
+pub async fn regenerate_recovery_codes(db: &SqlitePool, name: &str) -> sqlx::Result<Vec<String>> {
+    let mut tx = db.begin().await?;
+    let codes = replace_recovery_codes(&mut tx, name).await?;
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+async fn replace_recovery_codes(conn: &mut SqliteConnection, name: &str) -> sqlx::Result<Vec<String>> {
+    let codes: Vec<String> = (0..RECOVERY_CODES).map(|_| {
+        let mut bytes = [0_u8; RECOVERY_CODE_LOOKUP + RECOVERY_CODE_SECRET];
+        OsRng.fill_bytes(&mut bytes);
+        let code: String = bytes.iter().map(|b| RECOVERY_CODE_ALPHABET[(b % 32) as usize] as char).collect();
+        let (lookup, secret) = code.split_at(RECOVERY_CODE_LOOKUP);
+        let (first, second) = secret.split_at(RECOVERY_CODE_SECRET / 2);
+        format!("{}-{}-{}", lookup, first, second)
+    }).collect();
+
+    query!("DELETE FROM recovery_codes WHERE moderator = ?", name)
+        .execute(&mut *conn)
+        .await?;
+
+    for code in &codes {
+        let code = normalize_recovery_code(code);
+        let (lookup, secret) = code.split_at(RECOVERY_CODE_LOOKUP);
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(secret.as_bytes(), &salt)
+            .unwrap()
+            .to_string();
+
+        query!("INSERT INTO recovery_codes (moderator, lookup, code) VALUES (?, ?, ?)", name, lookup, hash)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(codes)
+}
+
+/// Marks a two-factor code's time step as used. Returns false if a
+/// code for this or a later step was already accepted
+pub async fn use_totp_step(db: &SqlitePool, name: &str, step: i64) -> sqlx::Result<bool> {
+    let result = query!(
+        "UPDATE moderators SET totp_last_step = ? WHERE name = ? AND (totp_last_step IS NULL OR totp_last_step < ?)",
+        step, name, step
+    ).execute(db).await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Checks a recovery code and deletes it, so it can only be used once
+pub async fn use_recovery_code(db: &SqlitePool, name: &str, code: &str) -> sqlx::Result<bool> {
+    let code = normalize_recovery_code(code);
+    if code.len() != RECOVERY_CODE_LOOKUP + RECOVERY_CODE_SECRET { return Ok(false) }
+
+    let (lookup, secret) = code.split_at(RECOVERY_CODE_LOOKUP);
+    let hashes = query!("SELECT id, code FROM recovery_codes WHERE moderator = ? AND lookup = ?", name, lookup)
+        .fetch_all(db)
+        .await?;
+
+    for row in hashes {
+        let Ok(hash) = PasswordHash::new(&row.code) else { continue };
+
+        if Argon2::default().verify_password(secret.as_bytes(), &hash).is_ok() {
+            // Another login may have used the same code in the meantime
+            let result = query!("DELETE FROM recovery_codes WHERE id = ?", row.id)
+                .execute(db)
+                .await?;
+            return Ok(result.rows_affected() == 1)
+        }
+    }
+
+    Ok(false)
+}
+
+/// Recovery codes are case insensitive and the dashes are optional
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -1,5 +1,5 @@
 use serde::Serialize;
-use sqlx::{query_as, SqlitePool, FromRow, query};
+use sqlx::{query_as, Acquire, Sqlite, SqlitePool, FromRow, query};
 
 #[derive(FromRow, Debug, Serialize)]
 pub struct Page {
@@ -33,13 +33,26 @@ pub async fn find_by_site_and_path(db: &SqlitePool, site: &str, path: &str) -> s
     .await
 }
 
+pub async fn all_for_site(db: &SqlitePool, site: &str) -> sqlx::Result<Vec<Page>> {
+    query_as!(Page, "SELECT * FROM pages WHERE site = ? ORDER BY id", site)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn update(db: impl Acquire<'_, Database = Sqlite>, id: i64, title: &Option<String>, locked: bool) -> sqlx::Result<sqlx::sqlite::SqliteQueryResult> {
+    let mut conn = db.acquire().await?;
+    query!("UPDATE pages SET title = ?, locked = ? WHERE id = ?", title, locked, id)
+    .execute(&mut *conn)
+    .await
+}
+
 pub async fn toggle_lock(db: &SqlitePool, id: i64) -> sqlx::Result<sqlx::sqlite::SqliteQueryResult> {
     query!("UPDATE pages SET locked = NOT locked WHERE id = ?", id)
     .execute(db)
     .await
 }
 
-pub async fn create_or_find_by_site_and_path(db: &SqlitePool, site: &str, path: &str, title: &Option<String>) -> sqlx::Result<Page> {
+pub async fn create_or_find_by_site_and_path(db: impl Acquire<'_, Database = Sqlite>, site: &str, path: &str, title: &Option<String>) -> sqlx::Result<Page> {
     let mut tx = db.begin().await?;
 
     let page = match query_as::<_, Page>("INSERT INTO pages (site, path, title) VALUES(?, ?, ?) RETURNING * ")
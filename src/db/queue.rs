@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, sqlite::SqliteRow, query_as, query};
+
+use super::UTC_DATETIME_FORMAT;
+
+/// A table holding a background queue, e.g. of emails. Every such
+/// table has `id`, `attempts`, `last_error` and `send_after` columns,
+/// and one which holds when the job was done
+pub struct Table {
+    pub name: &'static str,
+    pub done_at: &'static str,
+}
+
+/// Returns the jobs which aren't done yet and are due, oldest first
+pub async fn due<T>(db: &SqlitePool, table: &Table, max_attempts: i64, limit: i64) -> sqlx::Result<Vec<T>>
+where
+    T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query_as(&format!(
+        r#"
+            SELECT * FROM {}
+            WHERE {} IS NULL AND attempts < ? AND send_after <= ?
+            ORDER BY send_after, id
+            LIMIT ?
+        "#,
+        table.name, table.done_at
+    ))
+    .bind(max_attempts)
+    .bind(now)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+}
+
+pub async fn mark_done(db: &SqlitePool, table: &Table, id: i64) -> sqlx::Result<()> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query(&format!("UPDATE {} SET {} = ?, attempts = attempts + 1 WHERE id = ?", table.name, table.done_at))
+        .bind(now)
+        .bind(id)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Records a failed attempt and postpones the next one
+pub async fn mark_failed(db: &SqlitePool, table: &Table, id: i64, error: &str, retry_at: DateTime<Utc>) -> sqlx::Result<()> {
+    let retry_at = format!("{}", retry_at.format(UTC_DATETIME_FORMAT));
+
+    query(&format!("UPDATE {} SET attempts = attempts + 1, last_error = ?, send_after = ? WHERE id = ?", table.name))
+        .bind(error)
+        .bind(retry_at)
+        .bind(id)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
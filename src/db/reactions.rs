@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use sqlx::{SqlitePool, query, query_as};
+
+/// Reactions to a comment as seen by the person asking
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Reactions {
+    pub count: i64,
+    /// Whether the person asking has reacted
+    pub reacted: bool,
+}
+
+/// Adds a reaction, reacting more than once has no effect. Anonymous
+/// reactions carry the IP address, which can only react once too
+pub async fn add(db: &SqlitePool, comment_id: i64, reactor: &str, ip: Option<&str>) -> sqlx::Result<()> {
+    query!("INSERT OR IGNORE INTO reactions (comment_id, reactor, ip) VALUES (?, ?, ?)", comment_id, reactor, ip)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn remove(db: &SqlitePool, comment_id: i64, reactor: &str) -> sqlx::Result<()> {
+    query!("DELETE FROM reactions WHERE comment_id = ? AND reactor = ?", comment_id, reactor)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the reactions to each of the given comments. Comments
+/// without any reactions are missing from the result
+pub async fn for_comments(db: &SqlitePool, comment_ids: &[i64], reactor: Option<&str>) -> sqlx::Result<HashMap<i64, Reactions>> {
+    if comment_ids.is_empty() { return Ok(HashMap::new()) }
+
+    let ids: Vec<String> = comment_ids.iter().map(|id| id.to_string()).collect();
+
+    let rows: Vec<(i64, i64, bool)> = query_as(&format!(
+        r#"
+            SELECT comment_id, count(*), max(reactor = ?)
+            FROM reactions
+            WHERE comment_id IN ({})
+            GROUP BY comment_id
+        "#,
+        ids.join(",")
+    ))
+    .bind(reactor.unwrap_or_default())
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(|(id, count, reacted)| (id, Reactions { count, reacted })).collect())
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, query, query_as};
+
+use super::{comments::Comment, pages::Page};
+
+/// A reader's complaint about a comment, along with
+/// the comment and a few things about its page
+#[derive(FromRow, Debug)]
+pub struct Report {
+    #[sqlx(flatten)]
+    pub comment: Comment,
+    pub page_site: String,
+    pub page_path: String,
+    pub page_locked: bool,
+    pub page_title: Option<String>,
+    pub reason: String,
+    pub reported_at: DateTime<Utc>,
+}
+
+impl Report {
+    pub fn page(&self) -> Page {
+        Page {
+            id: self.comment.page_id,
+            site: self.page_site.clone(),
+            path: self.page_path.clone(),
+            locked: self.page_locked,
+            title: self.page_title.clone(),
+        }
+    }
+}
+
+/// Adds a report, reporting a comment more than once has no effect.
+/// Anonymous readers can only report a comment once from each IP
+/// address. Returns how many signed users and addresses of anonymous
+/// readers have reported the comment
+pub async fn add(db: &SqlitePool, comment_id: i64, reporter: &str, ip: &str, reason: &str) -> sqlx::Result<i64> {
+    query!(
+        "INSERT OR IGNORE INTO reports (comment_id, reporter, ip, reason) VALUES (?, ?, ?, ?)",
+        comment_id, reporter, ip, reason
+    ).execute(db).await?;
+
+    query!(
+        r#"
+            SELECT count(DISTINCT CASE WHEN reporter LIKE 'user:%' THEN reporter ELSE 'ip:' || ip END) as "count!: i64"
+            FROM reports WHERE comment_id = ?
+        "#,
+        comment_id
+    ).fetch_one(db).await.map(|r| r.count)
+}
+
+/// Returns reports about comments of a site which are not deleted,
+/// oldest first
+pub async fn for_site(db: &SqlitePool, site: &str) -> sqlx::Result<Vec<Report>> {
+    query_as::<_, Report>(
+        r#"
+            SELECT
+            comments.id, comments.page_id, comments.parent_id, comments.avatar, comments.name,
+            comments.html_body, comments.body, comments.reviewed, comments.moderator, comments.op,
+            comments.shadowed, comments.signed_name, comments.hidden, comments.hidden_from_author,
+            comments.created_at, comments.updated_at, comments.token, comments.deleted_at,
+            pages.site as page_site, pages.path as page_path,
+            pages.locked as page_locked, pages.title as page_title,
+            reports.reason, reports.created_at as reported_at
+            FROM reports
+            JOIN comments ON comments.id = reports.comment_id
+            JOIN pages ON pages.id = comments.page_id
+            WHERE pages.site = ?
+            AND comments.deleted_at IS NULL
+            ORDER BY reports.created_at, reports.id
+        "#
+    )
+    .bind(site)
+    .fetch_all(db)
+    .await
+}
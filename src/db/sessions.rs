@@ -0,0 +1,129 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{SqlitePool, FromRow, query_as, query};
+
+use crate::api::Base64;
+
+use super::UTC_DATETIME_FORMAT;
+
+/// A moderator logged in on a device
+#[derive(FromRow, Debug)]
+pub struct Session {
+    pub id: i64,
+    pub sid: Base64,
+    pub moderator: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub async fn create(
+    db: &SqlitePool,
+    moderator: &str,
+    sid: &Base64,
+    user_agent: Option<&str>,
+    lifetime: Duration,
+) -> sqlx::Result<Session> {
+    let expires_at = format!("{}", (Utc::now() + lifetime).format(UTC_DATETIME_FORMAT));
+
+    let id = query!(
+        "INSERT INTO sessions (sid, moderator, user_agent, expires_at) VALUES (?, ?, ?, ?)",
+        sid, moderator, user_agent, expires_at
+    ).execute(db).await?.last_insert_rowid();
+
+    find(db, id).await
+}
+
+pub async fn find(db: &SqlitePool, id: i64) -> sqlx::Result<Session> {
+    query_as!(
+        Session,
+        r#"
+            SELECT
+            id, sid as "sid: Base64", moderator, user_agent,
+            created_at as "created_at: DateTime<Utc>",
+            last_seen_at as "last_seen_at: DateTime<Utc>",
+            expires_at as "expires_at: DateTime<Utc>"
+            FROM sessions WHERE id = ?
+        "#,
+        id
+    ).fetch_one(db).await
+}
+
+/// Finds a session which hasn't expired yet
+pub async fn find_active(db: &SqlitePool, sid: &Base64) -> sqlx::Result<Session> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query_as!(
+        Session,
+        r#"
+            SELECT
+            id, sid as "sid: Base64", moderator, user_agent,
+            created_at as "created_at: DateTime<Utc>",
+            last_seen_at as "last_seen_at: DateTime<Utc>",
+            expires_at as "expires_at: DateTime<Utc>"
+            FROM sessions WHERE sid = ? AND expires_at > ?
+        "#,
+        sid, now
+    ).fetch_one(db).await
+}
+
+/// Returns the sessions of a moderator which haven't expired, most recently used first
+pub async fn for_moderator(db: &SqlitePool, moderator: &str) -> sqlx::Result<Vec<Session>> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+
+    query_as!(
+        Session,
+        r#"
+            SELECT
+            id, sid as "sid: Base64", moderator, user_agent,
+            created_at as "created_at: DateTime<Utc>",
+            last_seen_at as "last_seen_at: DateTime<Utc>",
+            expires_at as "expires_at: DateTime<Utc>"
+            FROM sessions WHERE moderator = ? AND expires_at > ?
+            ORDER BY last_seen_at DESC, id DESC
+        "#,
+        moderator, now
+    ).fetch_all(db).await
+}
+
+/// Marks a session as used and pushes its expiry back, but never past
+/// `max_lifetime` after the session was created. Sessions used within
+/// the last minute are left alone to spare a write per request
+pub async fn touch(
+    db: &SqlitePool,
+    session: &Session,
+    lifetime: Duration,
+    max_lifetime: Duration,
+) -> sqlx::Result<()> {
+    let now = Utc::now();
+    if now - session.last_seen_at < Duration::minutes(1) { return Ok(()) }
+
+    let last_seen_at = format!("{}", now.format(UTC_DATETIME_FORMAT));
+    let expires_at = (now + lifetime).min(session.created_at + max_lifetime);
+    let expires_at = format!("{}", expires_at.format(UTC_DATETIME_FORMAT));
+
+    query!(
+        "UPDATE sessions SET last_seen_at = ?, expires_at = ? WHERE id = ?",
+        last_seen_at, expires_at, session.id
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+pub async fn delete(db: &SqlitePool, id: i64) -> sqlx::Result<u64> {
+    Ok(query!("DELETE FROM sessions WHERE id = ?", id).execute(db).await?.rows_affected())
+}
+
+pub async fn delete_by_sid(db: &SqlitePool, sid: &Base64) -> sqlx::Result<u64> {
+    Ok(query!("DELETE FROM sessions WHERE sid = ?", sid).execute(db).await?.rows_affected())
+}
+
+/// Logs a moderator out everywhere
+pub async fn delete_for_moderator(db: &SqlitePool, moderator: &str) -> sqlx::Result<u64> {
+    Ok(query!("DELETE FROM sessions WHERE moderator = ?", moderator).execute(db).await?.rows_affected())
+}
+
+pub async fn delete_expired(db: &SqlitePool) -> sqlx::Result<u64> {
+    let now = format!("{}", Utc::now().format(UTC_DATETIME_FORMAT));
+    Ok(query!("DELETE FROM sessions WHERE expires_at <= ?", now).execute(db).await?.rows_affected())
+}
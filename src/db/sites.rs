@@ -1,5 +1,5 @@
 use ring::hmac;
-use sqlx::{SqlitePool, FromRow, query_as, query};
+use sqlx::{Acquire, Sqlite, SqlitePool, FromRow, query_as, query};
 use serde::Serialize;
 
 use crate::cli::SitesCommandArgs;
@@ -11,6 +11,27 @@ pub struct Site {
     pub private: bool,
     pub anonymous: bool,
     pub moderated: bool,
+    pub max_depth: i64,
+    pub public_revisions: bool,
+    pub rate_limit: i64,
+    pub rate_limit_window: i64,
+    pub max_links: i64,
+    pub max_links_action: String,
+    pub banned_words: String,
+    pub banned_words_action: String,
+    pub honeypot: bool,
+    pub honeypot_action: String,
+    pub min_submit_time: i64,
+    pub min_submit_time_action: String,
+    pub akismet_key: String,
+    pub akismet_url: String,
+    pub akismet_action: String,
+    pub smtp_url: String,
+    pub email_from: String,
+    pub moderator_emails: String,
+    pub reply_notifications: bool,
+    pub default_sort: String,
+    pub report_threshold: i64,
 }
 
 impl Site {
@@ -32,9 +53,10 @@ pub async fn all(db: &SqlitePool) -> sqlx::Result<Vec<Site>> {
 }
 
 /// Finds a config for a given site
-pub async fn find(db: &SqlitePool, site: &str) -> sqlx::Result<Site> {
-    Ok(query_as!(Site, "SELECT * FROM sites WHERE site = ? LIMIT 1", site)
-        .fetch_one(db).await?)
+pub async fn find(db: impl Acquire<'_, Database = Sqlite>, site: &str) -> sqlx::Result<Site> {
+    let mut conn = db.acquire().await?;
+    query_as!(Site, "SELECT * FROM sites WHERE site = ? LIMIT 1", site)
+        .fetch_one(&mut *conn).await
 }
 
 /// Deletes a config for a site
@@ -46,7 +68,7 @@ pub async fn delete(db: &SqlitePool, site: &str) -> Result<sqlx::sqlite::SqliteQ
 
 /// Creates a new configration for a site from
 /// command line arguments and returns the result
-pub async fn insert(db: &SqlitePool, args: &SitesCommandArgs) -> sqlx::Result<Site> {
+pub async fn insert(db: impl Acquire<'_, Database = Sqlite>, args: &SitesCommandArgs) -> sqlx::Result<Site> {
     fn append<T>(value: &Option<T>, attribute: &str, query: &mut String, values: &mut String) {
         if let Some(_) = value {
             query.push_str(&format!(", {}", attribute));
@@ -60,6 +82,27 @@ pub async fn insert(db: &SqlitePool, args: &SitesCommandArgs) -> sqlx::Result<Si
     append(&args.private, "private", &mut insert, &mut values);
     append(&args.anonymous, "anonymous", &mut insert, &mut values);
     append(&args.moderated, "moderated", &mut insert, &mut values);
+    append(&args.max_depth, "max_depth", &mut insert, &mut values);
+    append(&args.public_revisions, "public_revisions", &mut insert, &mut values);
+    append(&args.rate_limit, "rate_limit", &mut insert, &mut values);
+    append(&args.rate_limit_window, "rate_limit_window", &mut insert, &mut values);
+    append(&args.max_links, "max_links", &mut insert, &mut values);
+    append(&args.banned_words, "banned_words", &mut insert, &mut values);
+    append(&args.honeypot, "honeypot", &mut insert, &mut values);
+    append(&args.min_submit_time, "min_submit_time", &mut insert, &mut values);
+    append(&args.akismet_key, "akismet_key", &mut insert, &mut values);
+    append(&args.akismet_url, "akismet_url", &mut insert, &mut values);
+    append(&args.smtp_url, "smtp_url", &mut insert, &mut values);
+    append(&args.email_from, "email_from", &mut insert, &mut values);
+    append(&args.moderator_emails, "moderator_emails", &mut insert, &mut values);
+    append(&args.reply_notifications, "reply_notifications", &mut insert, &mut values);
+    append(&args.default_sort, "default_sort", &mut insert, &mut values);
+    append(&args.report_threshold, "report_threshold", &mut insert, &mut values);
+    append(&args.max_links_action, "max_links_action", &mut insert, &mut values);
+    append(&args.banned_words_action, "banned_words_action", &mut insert, &mut values);
+    append(&args.honeypot_action, "honeypot_action", &mut insert, &mut values);
+    append(&args.min_submit_time_action, "min_submit_time_action", &mut insert, &mut values);
+    append(&args.akismet_action, "akismet_action", &mut insert, &mut values);
 
     insert.push_str(") ");
     values.push_str(")");
@@ -72,22 +115,74 @@ pub async fn insert(db: &SqlitePool, args: &SitesCommandArgs) -> sqlx::Result<Si
     if let Some(a) = args.private { result = result.bind(a) }
     if let Some(a) = args.anonymous { result = result.bind(a) }
     if let Some(a) = args.moderated { result = result.bind(a) }
+    if let Some(a) = args.max_depth { result = result.bind(a) }
+    if let Some(a) = args.public_revisions { result = result.bind(a) }
+    if let Some(a) = args.rate_limit { result = result.bind(a) }
+    if let Some(a) = args.rate_limit_window { result = result.bind(a) }
+    if let Some(a) = args.max_links { result = result.bind(a) }
+    if let Some(ref a) = args.banned_words { result = result.bind(a) }
+    if let Some(a) = args.honeypot { result = result.bind(a) }
+    if let Some(a) = args.min_submit_time { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_key { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_url { result = result.bind(a) }
+    if let Some(ref a) = args.smtp_url { result = result.bind(a) }
+    if let Some(ref a) = args.email_from { result = result.bind(a) }
+    if let Some(ref a) = args.moderator_emails { result = result.bind(a) }
+    if let Some(a) = args.reply_notifications { result = result.bind(a) }
+    if let Some(ref a) = args.default_sort { result = result.bind(a) }
+    if let Some(a) = args.report_threshold { result = result.bind(a) }
+    if let Some(ref a) = args.max_links_action { result = result.bind(a) }
+    if let Some(ref a) = args.banned_words_action { result = result.bind(a) }
+    if let Some(ref a) = args.honeypot_action { result = result.bind(a) }
+    if let Some(ref a) = args.min_submit_time_action { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_action { result = result.bind(a) }
 
     result = result.bind(&args.site);
 
-    result.execute(db).await?;
+    let mut conn = db.acquire().await?;
+    result.execute(&mut *conn).await?;
 
-    Ok(find(db, &args.site).await?)
+    find(&mut *conn, &args.site).await
+}
+
+/// Replaces the secret of a site, e.g. when moving
+/// it over from another server
+pub async fn set_secret(db: impl Acquire<'_, Database = Sqlite>, site: &str, secret: &[u8]) -> sqlx::Result<sqlx::sqlite::SqliteQueryResult> {
+    let mut conn = db.acquire().await?;
+    query!("UPDATE sites SET secret = ? WHERE site = ?", secret, site)
+        .execute(&mut *conn)
+        .await
 }
 
 /// Updates a configuration for a given site from
 /// command line arguments and returns the updated row
-pub async fn update(db: &SqlitePool, existing: Site, args: SitesCommandArgs) -> sqlx::Result<Site> {
+pub async fn update(db: impl Acquire<'_, Database = Sqlite>, existing: Site, args: SitesCommandArgs) -> sqlx::Result<Site> {
     let mut update = String::from("UPDATE sites SET site = ?");
 
     if let Some(_) = args.private { update.push_str(", private = ?") };
     if let Some(_) = args.anonymous { update.push_str(", anonymous = ?") };
     if let Some(_) = args.moderated { update.push_str(", moderated = ?") };
+    if args.max_depth.is_some() { update.push_str(", max_depth = ?") };
+    if args.public_revisions.is_some() { update.push_str(", public_revisions = ?") };
+    if args.rate_limit.is_some() { update.push_str(", rate_limit = ?") };
+    if args.rate_limit_window.is_some() { update.push_str(", rate_limit_window = ?") };
+    if args.max_links.is_some() { update.push_str(", max_links = ?") };
+    if args.banned_words.is_some() { update.push_str(", banned_words = ?") };
+    if args.honeypot.is_some() { update.push_str(", honeypot = ?") };
+    if args.min_submit_time.is_some() { update.push_str(", min_submit_time = ?") };
+    if args.akismet_key.is_some() { update.push_str(", akismet_key = ?") };
+    if args.akismet_url.is_some() { update.push_str(", akismet_url = ?") };
+    if args.smtp_url.is_some() { update.push_str(", smtp_url = ?") };
+    if args.email_from.is_some() { update.push_str(", email_from = ?") };
+    if args.moderator_emails.is_some() { update.push_str(", moderator_emails = ?") };
+    if args.reply_notifications.is_some() { update.push_str(", reply_notifications = ?") };
+    if args.default_sort.is_some() { update.push_str(", default_sort = ?") };
+    if args.report_threshold.is_some() { update.push_str(", report_threshold = ?") };
+    if args.max_links_action.is_some() { update.push_str(", max_links_action = ?") };
+    if args.banned_words_action.is_some() { update.push_str(", banned_words_action = ?") };
+    if args.honeypot_action.is_some() { update.push_str(", honeypot_action = ?") };
+    if args.min_submit_time_action.is_some() { update.push_str(", min_submit_time_action = ?") };
+    if args.akismet_action.is_some() { update.push_str(", akismet_action = ?") };
 
     update.push_str(" WHERE site = ?");
 
@@ -98,10 +193,32 @@ pub async fn update(db: &SqlitePool, existing: Site, args: SitesCommandArgs) ->
     if let Some(a) = args.private { result = result.bind(a) }
     if let Some(a) = args.anonymous { result = result.bind(a) }
     if let Some(a) = args.moderated { result = result.bind(a) }
+    if let Some(a) = args.max_depth { result = result.bind(a) }
+    if let Some(a) = args.public_revisions { result = result.bind(a) }
+    if let Some(a) = args.rate_limit { result = result.bind(a) }
+    if let Some(a) = args.rate_limit_window { result = result.bind(a) }
+    if let Some(a) = args.max_links { result = result.bind(a) }
+    if let Some(ref a) = args.banned_words { result = result.bind(a) }
+    if let Some(a) = args.honeypot { result = result.bind(a) }
+    if let Some(a) = args.min_submit_time { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_key { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_url { result = result.bind(a) }
+    if let Some(ref a) = args.smtp_url { result = result.bind(a) }
+    if let Some(ref a) = args.email_from { result = result.bind(a) }
+    if let Some(ref a) = args.moderator_emails { result = result.bind(a) }
+    if let Some(a) = args.reply_notifications { result = result.bind(a) }
+    if let Some(ref a) = args.default_sort { result = result.bind(a) }
+    if let Some(a) = args.report_threshold { result = result.bind(a) }
+    if let Some(ref a) = args.max_links_action { result = result.bind(a) }
+    if let Some(ref a) = args.banned_words_action { result = result.bind(a) }
+    if let Some(ref a) = args.honeypot_action { result = result.bind(a) }
+    if let Some(ref a) = args.min_submit_time_action { result = result.bind(a) }
+    if let Some(ref a) = args.akismet_action { result = result.bind(a) }
 
     result = result.bind(&existing.site);
 
-    result.execute(db).await?;
+    let mut conn = db.acquire().await?;
+    result.execute(&mut *conn).await?;
 
-    Ok(find(db, &existing.site).await?)
+    find(&mut *conn, &existing.site).await
 }
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use sqlx::{SqlitePool, FromRow, query_as, query};
+
+#[derive(FromRow, Debug)]
+pub struct Webhook {
+    pub id: i64,
+    pub site: String,
+    pub url: String,
+    /// Comma separated list of events, empty for all of them
+    pub events: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event waiting to be delivered to a webhook, or already delivered
+#[derive(FromRow, Debug)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i64,
+    pub last_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub send_after: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn create(db: &SqlitePool, site: &str, url: &str, events: &str) -> sqlx::Result<Webhook> {
+    let id = query!("INSERT INTO webhooks (site, url, events) VALUES (?, ?, ?)", site, url, events)
+        .execute(db)
+        .await?
+        .last_insert_rowid();
+
+    find(db, id).await
+}
+
+/// Returns all webhooks, or only those of a site
+pub async fn all(db: &SqlitePool, site: Option<&str>) -> sqlx::Result<Vec<Webhook>> {
+    let mut select = String::from("SELECT * FROM webhooks");
+
+    if site.is_some() { select.push_str(" WHERE site = ?") }
+
+    select.push_str(" ORDER BY site, id");
+
+    let mut results = query_as::<_, Webhook>(&select);
+
+    if let Some(s) = site { results = results.bind(s) }
+
+    results.fetch_all(db).await
+}
+
+pub async fn find(db: &SqlitePool, id: i64) -> sqlx::Result<Webhook> {
+    query_as!(
+        Webhook,
+        r#"SELECT id, site, url, events, created_at as "created_at: DateTime<Utc>" FROM webhooks WHERE id = ?"#,
+        id
+    ).fetch_one(db).await
+}
+
+/// Removes a webhook along with its delivery log
+pub async fn delete(db: &SqlitePool, id: i64) -> sqlx::Result<()> {
+    query!("DELETE FROM webhooks WHERE id = ?", id).execute(db).await?;
+    Ok(())
+}
+
+/// Returns the webhooks of a site which are subscribed to an event
+pub async fn subscribed(db: &SqlitePool, site: &str, event: &str) -> sqlx::Result<Vec<Webhook>> {
+    query_as!(
+        Webhook,
+        r#"
+            SELECT id, site, url, events, created_at as "created_at: DateTime<Utc>"
+            FROM webhooks
+            WHERE site = ? AND (events = '' OR ',' || events || ',' LIKE '%,' || ? || ',%')
+        "#,
+        site, event
+    ).fetch_all(db).await
+}
+
+/// Adds a delivery to the queue to be sent as soon as possible
+pub async fn enqueue(db: &SqlitePool, webhook_id: i64, event: &str, payload: &str) -> sqlx::Result<()> {
+    query!(
+        "INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES (?, ?, ?)",
+        webhook_id, event, payload
+    ).execute(db).await?;
+
+    Ok(())
+}
+
+/// Returns the most recent deliveries of a webhook, newest first
+pub async fn deliveries(db: &SqlitePool, webhook_id: i64, limit: i64) -> sqlx::Result<Vec<Delivery>> {
+    query_as!(
+        Delivery,
+        r#"
+            SELECT
+            id, webhook_id, event, payload, attempts, last_status, last_error,
+            send_after as "send_after: DateTime<Utc>",
+            delivered_at as "delivered_at: DateTime<Utc>",
+            created_at as "created_at: DateTime<Utc>"
+            FROM webhook_deliveries
+            WHERE webhook_id = ?
+            ORDER BY id DESC
+            LIMIT ?
+        "#,
+        webhook_id, limit
+    ).fetch_all(db).await
+}
+
+/// Remembers the status the webhook responded with, which is
+/// missing when the request didn't get a response at all
+pub async fn record_status(db: &SqlitePool, id: i64, status: Option<i64>) -> sqlx::Result<()> {
+    query!("UPDATE webhook_deliveries SET last_status = ? WHERE id = ?", status, id)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
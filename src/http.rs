@@ -0,0 +1,14 @@
+use std::{sync::OnceLock, time::Duration};
+
+/// The HTTP client for calling other services, like Akismet or
+/// webhooks. Requests which must be quicker set their own timeout
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!("Besedka/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Can't build an HTTP client")
+    })
+}
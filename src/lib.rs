@@ -2,3 +2,8 @@ pub mod db;
 pub mod cli;
 pub mod server;
 pub mod api;
+pub mod notifications;
+pub mod webhooks;
+pub mod queue;
+pub mod http;
+pub mod totp;
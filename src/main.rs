@@ -24,7 +24,7 @@ async fn main() -> anyhow::Result<()> {
 
     match args.command {
         cli::Commands::Server(config) => server::run(config, db).await?,
-        cli::Commands::Sites(config) => match config {
+        cli::Commands::Sites(config) => match *config {
             cli::SitesCommands::List => cli::sites::list(&db).await,
             cli::SitesCommands::Get { site } => cli::sites::print(&db, &site).await,
             cli::SitesCommands::Add(args) => cli::sites::create(&db, args).await,
@@ -36,6 +36,40 @@ async fn main() -> anyhow::Result<()> {
             cli::ModeratorsCommands::List => cli::moderators::list(&db).await,
             cli::ModeratorsCommands::Remove { name } => cli::moderators::remove(&db, &name).await,
             cli::ModeratorsCommands::Update(args) => cli::moderators::update(&db, args).await,
+            cli::ModeratorsCommands::Sessions { name, revoke, revoke_all } => {
+                cli::moderators::sessions(&db, &name, revoke, revoke_all).await
+            },
+            cli::ModeratorsCommands::Lockouts { name, ip, clear } => {
+                cli::moderators::lockouts(&db, name.as_deref(), ip.as_deref(), clear).await
+            },
+            cli::ModeratorsCommands::TwoFactor(two_factor) => match two_factor {
+                cli::ModeratorsTwoFactorCommands::Enable { name } => cli::moderators::enable_two_factor(&db, &name).await,
+                cli::ModeratorsTwoFactorCommands::Disable { name } => cli::moderators::disable_two_factor(&db, &name).await,
+                cli::ModeratorsTwoFactorCommands::RecoveryCodes { name } => cli::moderators::recovery_codes(&db, &name).await,
+            },
+        },
+        cli::Commands::Comments(comments) => match comments {
+            cli::CommentsCommands::Purge { older_than } => cli::comments::purge(&db, older_than).await,
+            cli::CommentsCommands::Unapprove { id, from_author } => cli::comments::unapprove(&db, id, from_author).await,
+        },
+        cli::Commands::Webhooks(webhooks) => match webhooks {
+            cli::WebhooksCommands::List { site } => cli::webhooks::list(&db, site.as_deref()).await,
+            cli::WebhooksCommands::Add(args) => cli::webhooks::create(&db, args).await,
+            cli::WebhooksCommands::Remove { id } => cli::webhooks::remove(&db, id).await,
+            cli::WebhooksCommands::Deliveries { id, limit } => cli::webhooks::deliveries(&db, id, limit).await,
+        },
+        cli::Commands::Bans(bans) => match bans {
+            cli::BansCommands::List { site } => cli::bans::list(&db, site.as_deref()).await,
+            cli::BansCommands::Add(args) => cli::bans::create(&db, args).await,
+            cli::BansCommands::Remove { id } => cli::bans::remove(&db, id).await,
+        },
+        cli::Commands::Audit(args) => cli::audit::list(&db, args).await,
+        cli::Commands::Export(args) => cli::export::export(&db, args).await,
+        cli::Commands::Import(args) => match args.source {
+            Some(cli::ImportCommands::Disqus(args)) => cli::import::disqus::import(&db, args).await,
+            Some(cli::ImportCommands::Wordpress(args)) => cli::import::wordpress::import(&db, args).await,
+            Some(cli::ImportCommands::Isso(args)) => cli::import::isso::import(&db, args).await,
+            None => cli::import::import(&db, &args.file.unwrap()).await,
         },
     };
 
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::async_trait;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use lettre::{message::header::ContentType, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use reqwest::Url;
+use ring::hmac;
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{
+        comments::Comment,
+        emails::{self, Email},
+        pages::Page,
+        queue::Table,
+        sites::{self, Site},
+    },
+    queue::{Job, Worker},
+};
+
+/// Queues notification emails and delivers them in the background
+#[derive(Clone)]
+pub struct Notifier {
+    public_url: String,
+    worker: Worker,
+}
+
+impl Notifier {
+    pub fn new(public_url: &str) -> Self {
+        Self {
+            public_url: public_url.trim_end_matches('/').to_string(),
+            worker: Worker::default(),
+        }
+    }
+
+    /// Emails the moderators of a site about a comment waiting to be reviewed
+    pub async fn unreviewed_comment(&self, db: &SqlitePool, site: &Site, page: &Page, comment: &Comment) -> sqlx::Result<()> {
+        if site.smtp_url.is_empty() { return Ok(()) }
+
+        let subject = format!("New comment awaiting review on {}", site.site);
+        let body = format!(
+            "{} commented on https://{}{}\n\n{}\n\nThe comment won't be visible until a moderator approves it.\n",
+            comment.name, site.site, page.path, comment.body
+        );
+
+        for recipient in site.moderator_emails.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            emails::enqueue(db, &site.site, recipient, &subject, &body).await?;
+        }
+
+        self.worker.wake();
+        Ok(())
+    }
+
+    /// Emails the moderators of a site about a comment hidden after readers reported it
+    pub async fn reported_comment(&self, db: &SqlitePool, site: &Site, page: &Page, comment: &Comment, reports: i64) -> sqlx::Result<()> {
+        if site.smtp_url.is_empty() { return Ok(()) }
+
+        let subject = format!("Comment reported on {}", site.site);
+        let body = format!(
+            "{} reader(s) reported a comment by {} on https://{}{}\n\n{}\n\nThe comment won't be visible until a moderator approves it again.\n",
+            reports, comment.name, site.site, page.path, comment.body
+        );
+
+        for recipient in site.moderator_emails.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            emails::enqueue(db, &site.site, recipient, &subject, &body).await?;
+        }
+
+        self.worker.wake();
+        Ok(())
+    }
+
+    /// Emails the author of a comment about a reply, if they left an address
+    pub async fn reply(&self, db: &SqlitePool, site: &Site, page: &Page, parent: &Comment, reply: &Comment) -> sqlx::Result<()> {
+        if site.smtp_url.is_empty() || !site.reply_notifications { return Ok(()) }
+        // nobody needs to know that they replied to themselves
+        if parent.token == reply.token { return Ok(()) }
+
+        let Some(email) = emails::subscriber(db, &site.site, parent.id).await? else { return Ok(()) };
+
+        let subject = format!("{} replied to your comment on {}", reply.name, site.site);
+        let body = format!(
+            "{} replied to your comment on https://{}{}\n\n{}\n\n--\nYou asked to be notified about replies to your comments. \
+            To stop receiving these emails, visit {}\n",
+            reply.name, site.site, page.path, reply.body, self.unsubscribe_url(site, &email)
+        );
+
+        emails::enqueue(db, &site.site, &email, &subject, &body).await?;
+
+        self.worker.wake();
+        Ok(())
+    }
+
+    fn unsubscribe_url(&self, site: &Site, email: &str) -> String {
+        let url = format!("{}/api/unsubscribe", self.public_url);
+        let params = [("site", site.site.as_str()), ("email", email), ("signature", &unsubscribe_signature(site, email))];
+
+        Url::parse_with_params(&url, params).map_or(url, String::from)
+    }
+
+    /// Delivers queued emails for as long as the server runs
+    pub async fn run(self, db: SqlitePool) {
+        self.worker.run::<Email>(db).await
+    }
+}
+
+/// Signs an address so that only links from our own
+/// emails can unsubscribe it from a site
+pub fn unsubscribe_signature(site: &Site, email: &str) -> String {
+    let tag = hmac::sign(&site.key(), format!("unsubscribe:{}", email).as_bytes());
+    URL_SAFE_NO_PAD.encode(tag.as_ref())
+}
+
+pub fn verify_unsubscribe_signature(site: &Site, email: &str, signature: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(signature)
+        .is_ok_and(|s| hmac::verify(&site.key(), format!("unsubscribe:{}", email).as_bytes(), &s).is_ok())
+}
+
+#[async_trait]
+impl Job for Email {
+    const TABLE: Table = Table { name: "emails", done_at: "sent_at" };
+
+    fn id(&self) -> i64 { self.id }
+
+    fn attempts(&self) -> i64 { self.attempts }
+
+    async fn perform(&self, db: &SqlitePool) -> anyhow::Result<()> {
+        let site = sites::find(db, &self.site).await.context("Site not found")?;
+        send(&site, self).await
+    }
+}
+
+async fn send(site: &Site, email: &Email) -> anyhow::Result<()> {
+    let from = match site.email_from.is_empty() {
+        true => format!("Besedka <noreply@{}>", site.site),
+        false => site.email_from.clone(),
+    };
+
+    let message = Message::builder()
+        .from(from.parse().context("Invalid sender address")?)
+        .to(email.recipient.parse().context("Invalid recipient address")?)
+        .subject(&email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())?;
+
+    AsyncSmtpTransport::<Tokio1Executor>::from_url(&site.smtp_url)?
+        .timeout(Some(Duration::from_secs(10)))
+        .build()
+        .send(message)
+        .await?;
+
+    Ok(())
+}
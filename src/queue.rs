@@ -0,0 +1,74 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::async_trait;
+use chrono::Utc;
+use sqlx::{FromRow, SqlitePool, sqlite::SqliteRow};
+use tokio::sync::Notify;
+
+use crate::db::queue::{self, Table};
+
+/// Jobs are given up on after this many failed attempts
+pub const MAX_ATTEMPTS: i64 = 8;
+
+/// How often queues are checked for jobs due to be retried
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How many jobs are taken from a queue at once
+const BATCH_SIZE: i64 = 50;
+
+/// Something to do in the background, like sending an email,
+/// which is kept in a database table until it succeeds
+#[async_trait]
+pub trait Job: for<'r> FromRow<'r, SqliteRow> + Send + Sync + Unpin {
+    const TABLE: Table;
+
+    fn id(&self) -> i64;
+
+    /// How many times the job has failed so far
+    fn attempts(&self) -> i64;
+
+    async fn perform(&self, db: &SqlitePool) -> anyhow::Result<()>;
+}
+
+/// Performs the jobs of a queue in the background,
+/// retrying failed ones with a growing delay
+#[derive(Clone, Default)]
+pub struct Worker {
+    wake: Arc<Notify>,
+}
+
+impl Worker {
+    /// Lets the worker know that a job was added
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Performs queued jobs for as long as the server runs
+    pub async fn run<J: Job>(self, db: SqlitePool) {
+        loop {
+            if let Err(e) = perform_due::<J>(&db).await {
+                tracing::error!("Failed performing jobs from {}: {:?}", J::TABLE.name, e);
+            }
+
+            tokio::select! {
+                _ = self.wake.notified() => (),
+                _ = tokio::time::sleep(POLL_INTERVAL) => (),
+            }
+        }
+    }
+}
+
+async fn perform_due<J: Job>(db: &SqlitePool) -> sqlx::Result<()> {
+    for job in queue::due::<J>(db, &J::TABLE, MAX_ATTEMPTS, BATCH_SIZE).await? {
+        match job.perform(db).await {
+            Ok(()) => queue::mark_done(db, &J::TABLE, job.id()).await?,
+            Err(e) => {
+                tracing::warn!("Failed performing job {} from {}: {:#}", job.id(), J::TABLE.name, e);
+                let backoff = chrono::Duration::minutes(1 << job.attempts().min(10));
+                queue::mark_failed(db, &J::TABLE, job.id(), &format!("{:#}", e), Utc::now() + backoff).await?;
+            },
+        }
+    }
+
+    Ok(())
+}
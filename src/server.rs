@@ -1,6 +1,6 @@
 mod assets;
 
-use std::time::Duration;
+use std::{net::SocketAddr, time::Duration};
 
 use anyhow::Context;
 
@@ -16,7 +16,11 @@ use tower_http::{
     LatencyUnit, timeout::TimeoutLayer, compression::CompressionLayer, cors::CorsLayer,
 };
 
-use crate::api::{self, AppState};
+use crate::{
+    api::{self, spam::akismet::Feedback, AppState, RateLimiter},
+    notifications::Notifier,
+    webhooks::Webhooks,
+};
 use super::cli::ServerArgs;
 
 use axum_server::tls_rustls::RustlsConfig;
@@ -32,7 +36,25 @@ pub async fn run(config: ServerArgs, db: SqlitePool) -> anyhow::Result<()> {
     tracing::debug!("{:#?}", config);
     tracing::info!("Listening on {}", config.bind);
 
-    let app = router(db);
+    let public_url = match config.public_url {
+        Some(ref url) => url.clone(),
+        None => format!("{}://{}", if config.ssl() { "https" } else { "http" }, config.bind),
+    };
+    let notifier = Notifier::new(&public_url);
+    tokio::spawn(notifier.clone().run(db.clone()));
+
+    let webhooks = Webhooks::default();
+    tokio::spawn(webhooks.clone().run(db.clone()));
+
+    let akismet = Feedback::default();
+    tokio::spawn(akismet.clone().run(db.clone()));
+    tokio::spawn(Feedback::purge_metadata(db.clone()));
+
+    let limiter = RateLimiter::default();
+    tokio::spawn(limiter.clone().run());
+
+    let state = AppState { db, limiter, notifier, webhooks, akismet, behind_proxy: config.behind_proxy };
+    let app = router(state);
 
     if config.ssl() {
         let ssl_config = RustlsConfig::from_pem_file(
@@ -40,20 +62,18 @@ pub async fn run(config: ServerArgs, db: SqlitePool) -> anyhow::Result<()> {
             config.ssl_key.unwrap()
         ).await.unwrap();
         axum_server::bind_rustls(config.bind, ssl_config)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .context("Failed running HTTPs server")
     } else {
         axum_server::bind(config.bind)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .context("Failed running HTTP server")
     }
 }
 
-fn router(db: SqlitePool) -> Router {
-    let state = AppState { db };
-
+fn router(state: AppState) -> Router {
     let middleware = ServiceBuilder::new()
         .layer(
             TraceLayer::new_for_http()
@@ -74,6 +94,10 @@ fn router(db: SqlitePool) -> Router {
         .merge(api::preview::router())
         .merge(api::sites::router())
         .merge(api::pages::router())
+        .merge(api::emails::router())
+        .merge(api::reactions::router())
+        .merge(api::bans::router())
+        .merge(api::reports::router())
         .merge(assets::router())
         .layer(middleware)
         .with_state(state)
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use url::Url;
+use ring::{hmac, rand::{SecureRandom, SystemRandom}};
+
+/// Seconds each code is valid for
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from this many steps before or after the current one are still
+/// accepted, to make up for clocks which are slightly off
+const SKEW: i64 = 1;
+const ISSUER: &str = "Besedka";
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generates a secret to share with an authenticator app
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = [0_u8; 20];
+    let _ = SystemRandom::new().fill(&mut secret);
+    secret.to_vec()
+}
+
+/// Returns the otpauth URI to add to an authenticator app,
+/// usually by turning it into a QR code
+pub fn uri(secret: &[u8], name: &str) -> String {
+    let label = format!("otpauth://totp/{}:{}", ISSUER, name);
+    let secret = base32(secret);
+    let digits = DIGITS.to_string();
+    let period = STEP.to_string();
+    let params = [
+        ("secret", secret.as_str()),
+        ("issuer", ISSUER),
+        ("algorithm", "SHA1"),
+        ("digits", digits.as_str()),
+        ("period", period.as_str()),
+    ];
+
+    Url::parse_with_params(&label, params).map_or(label, String::from)
+}
+
+/// Checks a code against the secret and returns the time step
+/// it was valid for, so that it can't be used again
+pub fn verify(secret: &[u8], code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) { return None }
+
+    let current = now.timestamp() / STEP;
+    (current - SKEW..=current + SKEW).find(|step| generate(secret, *step) == code)
+}
+
+/// Generates the code for a time step as described in RFC 6238
+fn generate(secret: &[u8], step: i64) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let digest = hmac::sign(&key, &step.to_be_bytes());
+    let digest = digest.as_ref();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+
+    format!("{:0width$}", binary % 10_u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+/// Unpadded base32 as authenticator apps expect the secret
+fn base32(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
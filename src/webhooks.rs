@@ -0,0 +1,183 @@
+use axum::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use ring::hmac;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{
+        comments::Comment,
+        pages::Page,
+        queue::Table,
+        sites::{self, Site},
+        webhooks::{self, Delivery},
+    },
+    http,
+    queue::{Job, Worker},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Event {
+    #[value(name = "comment.created")]
+    CommentCreated,
+    #[value(name = "comment.updated")]
+    CommentUpdated,
+    #[value(name = "comment.approved")]
+    CommentApproved,
+    #[value(name = "comment.unapproved")]
+    CommentUnapproved,
+    #[value(name = "comment.deleted")]
+    CommentDeleted,
+    #[value(name = "page.locked")]
+    PageLocked,
+    #[value(name = "page.unlocked")]
+    PageUnlocked,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CommentCreated => "comment.created",
+            Self::CommentUpdated => "comment.updated",
+            Self::CommentApproved => "comment.approved",
+            Self::CommentUnapproved => "comment.unapproved",
+            Self::CommentDeleted => "comment.deleted",
+            Self::PageLocked => "page.locked",
+            Self::PageUnlocked => "page.unlocked",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    site: &'a str,
+    created_at: DateTime<Utc>,
+    page: PagePayload<'a>,
+    comment: Option<CommentPayload<'a>>,
+}
+
+#[derive(Serialize)]
+struct PagePayload<'a> {
+    id: i64,
+    path: &'a str,
+    title: Option<&'a str>,
+    locked: bool,
+}
+
+#[derive(Serialize)]
+struct CommentPayload<'a> {
+    id: i64,
+    parent_id: Option<i64>,
+    name: &'a str,
+    body: &'a str,
+    html_body: &'a str,
+    avatar: Option<&'a str>,
+    reviewed: bool,
+    op: bool,
+    moderator: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Queues events for the webhooks of a site and delivers them in the background
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    worker: Worker,
+}
+
+impl Webhooks {
+    /// Queues an event about a comment
+    pub async fn comment(&self, db: &SqlitePool, site: &Site, event: Event, page: &Page, comment: &Comment) -> sqlx::Result<()> {
+        self.dispatch(db, site, event, page, Some(CommentPayload {
+            id: comment.id,
+            parent_id: comment.parent_id,
+            name: &comment.name,
+            body: &comment.body,
+            html_body: &comment.html_body,
+            avatar: comment.avatar.as_deref(),
+            reviewed: comment.reviewed,
+            op: comment.op,
+            moderator: comment.moderator,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+        })).await
+    }
+
+    /// Queues an event about a page
+    pub async fn page(&self, db: &SqlitePool, site: &Site, event: Event, page: &Page) -> sqlx::Result<()> {
+        self.dispatch(db, site, event, page, None).await
+    }
+
+    async fn dispatch(
+        &self,
+        db: &SqlitePool,
+        site: &Site,
+        event: Event,
+        page: &Page,
+        comment: Option<CommentPayload<'_>>,
+    ) -> sqlx::Result<()> {
+        let hooks = webhooks::subscribed(db, &site.site, event.as_str()).await?;
+        if hooks.is_empty() { return Ok(()) }
+
+        let payload = serde_json::to_string(&Payload {
+            event: event.as_str(),
+            site: &site.site,
+            created_at: Utc::now(),
+            page: PagePayload { id: page.id, path: &page.path, title: page.title.as_deref(), locked: page.locked },
+            comment,
+        }).expect("Can't serialize webhook payload");
+
+        for hook in hooks {
+            webhooks::enqueue(db, hook.id, event.as_str(), &payload).await?;
+        }
+
+        self.worker.wake();
+        Ok(())
+    }
+
+    /// Delivers queued events for as long as the server runs
+    pub async fn run(self, db: SqlitePool) {
+        self.worker.run::<Delivery>(db).await
+    }
+}
+
+/// Signs a payload with the site's secret. The signed text is prefixed,
+/// so that a payload can't pass for a signed user object or vice versa
+pub fn signature(site: &Site, payload: &str) -> String {
+    STANDARD.encode(hmac::sign(&site.key(), format!("webhook:{}", payload).as_bytes()).as_ref())
+}
+
+#[async_trait]
+impl Job for Delivery {
+    const TABLE: Table = Table { name: "webhook_deliveries", done_at: "delivered_at" };
+
+    fn id(&self) -> i64 { self.id }
+
+    fn attempts(&self) -> i64 { self.attempts }
+
+    async fn perform(&self, db: &SqlitePool) -> anyhow::Result<()> {
+        let hook = webhooks::find(db, self.webhook_id).await?;
+        let site = sites::find(db, &hook.site).await?;
+
+        let result = http::client()
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Besedka-Event", &self.event)
+            .header("X-Besedka-Delivery", self.id)
+            .header("X-Besedka-Signature", signature(&site, &self.payload))
+            .body(self.payload.clone())
+            .send()
+            .await;
+
+        let status = result.as_ref().ok().map(|r| i64::from(r.status().as_u16()));
+        webhooks::record_status(db, self.id, status).await?;
+
+        let status = result?.status();
+        if !status.is_success() { anyhow::bail!("Unexpected response status {}", status) }
+
+        Ok(())
+    }
+}